@@ -1,6 +1,7 @@
 use hashbrown::HashMap;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[inline]
 pub fn cmp(a1: Atom, a2: Atom) -> std::cmp::Ordering {
@@ -10,6 +11,13 @@ pub fn cmp(a1: Atom, a2: Atom) -> std::cmp::Ordering {
 /// Maximum character length of an atom.
 pub const MAX_ATOM_CHARS: usize = 255;
 
+/// Default maximum number of atoms.
+pub const DEFAULT_ATOM_LIMIT: usize = 1_048_576;
+
+/// Maximum number of atoms that can be created at runtime, 0 until a machine sets it. The atom
+/// table is shared by all machines in the OS process, so is the limit.
+static LIMIT: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Atom(pub u32);
 
@@ -18,6 +26,23 @@ impl Atom {
     pub fn to_str(&self) -> Option<&'static str> {
         ATOMS.read().to_str(self.0)
     }
+
+    /// Looks up the atom, creating it unless the atom table is full. Names longer than
+    /// `MAX_ATOM_CHARS` characters can't be atoms.
+    pub fn try_from_str(value: &str) -> Option<Self> {
+        if value.chars().count() > MAX_ATOM_CHARS {
+            return None;
+        }
+        if let Some(id) = ATOMS.read().lookup(&value) {
+            return Some(Atom(id));
+        }
+        let mut atoms = ATOMS.write();
+        if atoms.names.len() >= limit() && atoms.lookup(&value).is_none() {
+            return None;
+        }
+        let name = Box::leak(String::from(value).into_boxed_str());
+        Some(Atom(atoms.insert(name)))
+    }
 }
 
 impl std::fmt::Display for Atom {
@@ -28,7 +53,7 @@ impl std::fmt::Display for Atom {
 
 impl From<&str> for Atom {
     fn from(value: &str) -> Self {
-        assert!(value.len() <= MAX_ATOM_CHARS);
+        assert!(value.chars().count() <= MAX_ATOM_CHARS);
         if let Some(id) = ATOMS.read().lookup(&value) {
             return Atom(id);
         }
@@ -40,7 +65,7 @@ impl From<&str> for Atom {
 
 impl From<String> for Atom {
     fn from(value: String) -> Self {
-        assert!(value.len() <= MAX_ATOM_CHARS);
+        assert!(value.chars().count() <= MAX_ATOM_CHARS);
         if let Some(id) = ATOMS.read().lookup(&value) {
             return Atom(id);
         }
@@ -88,6 +113,46 @@ impl AtomTable {
     pub fn to_str(&self, index: u32) -> Option<&'static str> {
         self.names.get(index as usize).copied()
     }
+
+    /// Memory used by the table in bytes, as `(allocated, used)`.
+    pub fn memory(&self) -> (usize, usize) {
+        let text: usize = self.names.iter().map(|name| name.len()).sum();
+        let entry = std::mem::size_of::<(&'static str, u32)>();
+        let name = std::mem::size_of::<&'static str>();
+
+        let allocated = text + self.ids.capacity() * entry + self.names.capacity() * name;
+        let used = text + self.ids.len() * entry + self.names.len() * name;
+        (allocated, used)
+    }
+}
+
+/// Memory used by the atom table in bytes, as `(allocated, used)`.
+pub fn memory() -> (usize, usize) {
+    ATOMS.read().memory()
+}
+
+/// Number of atoms in the atom table.
+pub fn count() -> usize {
+    ATOMS.read().names.len()
+}
+
+pub fn limit() -> usize {
+    match LIMIT.load(Ordering::Relaxed) {
+        0 => DEFAULT_ATOM_LIMIT,
+        limit => limit,
+    }
+}
+
+/// Raises the maximum number of atoms to `limit`. The first call sets it, later ones can only
+/// raise it, so that no machine ends up with less room than it was built with.
+pub fn raise_limit(limit: usize) {
+    let mut current = LIMIT.load(Ordering::Relaxed);
+    while current < limit {
+        match LIMIT.compare_exchange_weak(current, limit, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
+    }
 }
 
 pub(self) static ATOMS: Lazy<RwLock<AtomTable>> = Lazy::new(|| {
@@ -418,6 +483,102 @@ pub(self) static ATOMS: Lazy<RwLock<AtomTable>> = Lazy::new(|| {
     atoms.insert("dotall");
     atoms.insert("re_pattern");
 
+    atoms.insert("garbage_collect");
+    atoms.insert("major");
+    atoms.insert("minor");
+    atoms.insert("minor_gcs");
+    atoms.insert("heap_block_size");
+    atoms.insert("old_heap_block_size");
+    atoms.insert("mbuf_size");
+    atoms.insert("recent_size");
+    atoms.insert("old_heap_size");
+    atoms.insert("inherit");
+
+    atoms.insert("log");
+    atoms.insert("gl");
+    atoms.insert("time");
+    atoms.insert("error_logger");
+    atoms.insert("tag");
+    atoms.insert("emulator");
+
+    atoms.insert("bin_vheap_size");
+    atoms.insert("bin_vheap_block_size");
+    atoms.insert("bin_old_vheap_size");
+    atoms.insert("bin_old_vheap_block_size");
+
+    atoms.insert("copy_literals");
+    atoms.insert("check_process_code");
+    atoms.insert("prepare");
+    atoms.insert("prepare_on_load");
+    atoms.insert("abort");
+    atoms.insert("complete");
+
+    atoms.insert("total");
+    atoms.insert("processes");
+    atoms.insert("processes_used");
+    atoms.insert("system");
+    atoms.insert("atom");
+    atoms.insert("atom_used");
+    atoms.insert("code");
+    atoms.insert("ets");
+
+    atoms.insert("scheduler");
+    atoms.insert("dirty_cpu");
+    atoms.insert("dirty_io");
+    atoms.insert("notsup");
+    atoms.insert("copy");
+    atoms.insert("send");
+    atoms.insert("wait");
+    atoms.insert("reschedule");
+    atoms.insert("ready_wait6_done");
+    atoms.insert("alive_waitexiting");
+    atoms.insert("ready");
+    atoms.insert("done");
+    atoms.insert("alive");
+    atoms.insert("dirty_cpu_schedulers");
+    atoms.insert("dirty_cpu_schedulers_online");
+    atoms.insert("dirty_io_schedulers");
+
+    atoms.insert("exact_reductions");
+    atoms.insert("runtime");
+    atoms.insert("wall_clock");
+    atoms.insert("run_queue");
+    atoms.insert("run_queue_lengths");
+    atoms.insert("total_run_queue_lengths");
+    atoms.insert("active_tasks");
+    atoms.insert("context_switches");
+    atoms.insert("io");
+    atoms.insert("input");
+    atoms.insert("output");
+    atoms.insert("scheduler_wall_time");
+    atoms.insert("scheduler_wall_time_all");
+
+    atoms.insert("asynchronous");
+    atoms.insert("unless_suspending");
+    atoms.insert("not_suspended");
+    atoms.insert("exited");
+
+    atoms.insert("process_limit");
+    atoms.insert("process_count");
+    atoms.insert("atom_limit");
+    atoms.insert("atom_count");
+    atoms.insert("schedulers");
+    atoms.insert("schedulers_online");
+    atoms.insert("hibernate");
+    atoms.insert("monitors");
+    atoms.insert("save_calls");
+    atoms.insert("receive");
+    atoms.insert("timeout");
+    atoms.insert("sensitive");
+    atoms.insert("alias");
+    atoms.insert("explicit_unalias");
+    atoms.insert("reply");
+    atoms.insert("demonitor");
+    atoms.insert("reply_demonitor");
+    atoms.insert("noconnection");
+    atoms.insert("not_purged");
+    atoms.insert("clock_service");
+
     RwLock::new(atoms)
 });
 
@@ -744,3 +905,99 @@ pub const UNGREEDY: Atom = Atom(269);
 pub const MULTILINE: Atom = Atom(270);
 pub const DOTALL: Atom = Atom(271);
 pub const RE_PATTERN: Atom = Atom(272);
+
+pub const GARBAGE_COLLECT: Atom = Atom(273);
+pub const MAJOR: Atom = Atom(274);
+pub const MINOR: Atom = Atom(275);
+pub const MINOR_GCS: Atom = Atom(276);
+pub const HEAP_BLOCK_SIZE: Atom = Atom(277);
+pub const OLD_HEAP_BLOCK_SIZE: Atom = Atom(278);
+pub const MBUF_SIZE: Atom = Atom(279);
+pub const RECENT_SIZE: Atom = Atom(280);
+pub const OLD_HEAP_SIZE: Atom = Atom(281);
+pub const INHERIT: Atom = Atom(282);
+
+pub const LOG: Atom = Atom(283);
+pub const GL: Atom = Atom(284);
+pub const TIME: Atom = Atom(285);
+pub const ERROR_LOGGER: Atom = Atom(286);
+pub const TAG: Atom = Atom(287);
+pub const EMULATOR: Atom = Atom(288);
+
+pub const BIN_VHEAP_SIZE: Atom = Atom(289);
+pub const BIN_VHEAP_BLOCK_SIZE: Atom = Atom(290);
+pub const BIN_OLD_VHEAP_SIZE: Atom = Atom(291);
+pub const BIN_OLD_VHEAP_BLOCK_SIZE: Atom = Atom(292);
+
+pub const COPY_LITERALS: Atom = Atom(293);
+pub const CHECK_PROCESS_CODE: Atom = Atom(294);
+pub const PREPARE: Atom = Atom(295);
+pub const PREPARE_ON_LOAD: Atom = Atom(296);
+pub const ABORT: Atom = Atom(297);
+pub const COMPLETE: Atom = Atom(298);
+
+pub const TOTAL: Atom = Atom(299);
+pub const PROCESSES: Atom = Atom(300);
+pub const PROCESSES_USED: Atom = Atom(301);
+pub const SYSTEM: Atom = Atom(302);
+pub const ATOM: Atom = Atom(303);
+pub const ATOM_USED: Atom = Atom(304);
+pub const CODE: Atom = Atom(305);
+pub const ETS: Atom = Atom(306);
+
+pub const SCHEDULER: Atom = Atom(307);
+pub const DIRTY_CPU: Atom = Atom(308);
+pub const DIRTY_IO: Atom = Atom(309);
+pub const NOTSUP: Atom = Atom(310);
+pub const COPY: Atom = Atom(311);
+pub const SEND: Atom = Atom(312);
+pub const WAIT: Atom = Atom(313);
+pub const RESCHEDULE: Atom = Atom(314);
+pub const READY_WAIT6_DONE: Atom = Atom(315);
+pub const ALIVE_WAITEXITING: Atom = Atom(316);
+pub const READY: Atom = Atom(317);
+pub const DONE: Atom = Atom(318);
+pub const ALIVE: Atom = Atom(319);
+pub const DIRTY_CPU_SCHEDULERS: Atom = Atom(320);
+pub const DIRTY_CPU_SCHEDULERS_ONLINE: Atom = Atom(321);
+pub const DIRTY_IO_SCHEDULERS: Atom = Atom(322);
+
+pub const EXACT_REDUCTIONS: Atom = Atom(323);
+pub const RUNTIME: Atom = Atom(324);
+pub const WALL_CLOCK: Atom = Atom(325);
+pub const RUN_QUEUE: Atom = Atom(326);
+pub const RUN_QUEUE_LENGTHS: Atom = Atom(327);
+pub const TOTAL_RUN_QUEUE_LENGTHS: Atom = Atom(328);
+pub const ACTIVE_TASKS: Atom = Atom(329);
+pub const CONTEXT_SWITCHES: Atom = Atom(330);
+pub const IO: Atom = Atom(331);
+pub const INPUT: Atom = Atom(332);
+pub const OUTPUT: Atom = Atom(333);
+pub const SCHEDULER_WALL_TIME: Atom = Atom(334);
+pub const SCHEDULER_WALL_TIME_ALL: Atom = Atom(335);
+
+pub const ASYNCHRONOUS: Atom = Atom(336);
+pub const UNLESS_SUSPENDING: Atom = Atom(337);
+pub const NOT_SUSPENDED: Atom = Atom(338);
+pub const EXITED: Atom = Atom(339);
+
+pub const PROCESS_LIMIT: Atom = Atom(340);
+pub const PROCESS_COUNT: Atom = Atom(341);
+pub const ATOM_LIMIT: Atom = Atom(342);
+pub const ATOM_COUNT: Atom = Atom(343);
+pub const SCHEDULERS: Atom = Atom(344);
+pub const SCHEDULERS_ONLINE: Atom = Atom(345);
+pub const HIBERNATE: Atom = Atom(346);
+pub const MONITORS: Atom = Atom(347);
+pub const SAVE_CALLS: Atom = Atom(348);
+pub const RECEIVE: Atom = Atom(349);
+pub const TIMEOUT: Atom = Atom(350);
+pub const SENSITIVE: Atom = Atom(351);
+pub const ALIAS: Atom = Atom(352);
+pub const EXPLICIT_UNALIAS: Atom = Atom(353);
+pub const REPLY: Atom = Atom(354);
+pub const DEMONITOR: Atom = Atom(355);
+pub const REPLY_DEMONITOR: Atom = Atom(356);
+pub const NOCONNECTION: Atom = Atom(357);
+pub const NOT_PURGED: Atom = Atom(358);
+pub const CLOCK_SERVICE: Atom = Atom(359);
@@ -5,8 +5,10 @@ use crate::module;
 use crate::persistent_term;
 use crate::port;
 use crate::regex;
+use crate::scheduler::Priority;
 
 use crate::exception::{Exception, Reason, StackTrace};
+use crate::immix::Heap;
 use crate::process::{self, RcProcess};
 use crate::value::{self, Atom, BigInt, CastFrom, CastInto, Cons, Term, Tuple, Variant};
 use crate::vm::Machine;
@@ -14,12 +16,49 @@ use crate::vm::Machine;
 use hashbrown::HashMap;
 use once_cell::sync::Lazy;
 
+/// Yields once the BIF runs out of reductions: `$bif` gets called with the given arguments once
+/// the process is scheduled again.
+macro_rules! bif_yield {
+    ($context:expr, $bif:expr, $($arg:expr),*) => {{
+        let mut _i = 0usize;
+        $(
+            $context.x[_i] = $arg;
+            _i += 1usize;
+        )*
+        $context.trap = Some(crate::process::Trap {
+            bif: $bif,
+            arity: _i,
+            tail: false,
+            // a dirty BIF stays on the same type of scheduler
+            dirty: crate::scheduler::current(),
+        });
+        $context.bump_all_reductions();
+        return Err(crate::exception::Exception::new(crate::exception::Reason::YIELD));
+    }};
+}
+
+/// Marks a BIF or NIF as dirty: calling it moves the process over to a dirty scheduler of the
+/// given type (`Cpu` or `Io`), which then runs `$bif`.
+macro_rules! dirty {
+    ($dirty:ident, $bif:path) => {
+        (|_vm: &Machine, process: &RcProcess, args: &[Term]| -> Result {
+            crate::scheduler::schedule(
+                process,
+                Some(crate::scheduler::Dirty::$dirty),
+                $bif,
+                args,
+            )
+        }) as crate::bif::Fn
+    };
+}
+
 pub mod arith;
 pub mod binary;
 mod chrono;
 mod dtrace;
 pub mod erf;
 pub mod erlang;
+mod erts_debug;
 mod info;
 mod lists;
 mod load;
@@ -46,7 +85,7 @@ macro_rules! trap {
 // maybe use https://github.com/sfackler/rust-phf
 
 macro_rules! bif_map {
-    ($($module:expr => {$($fun:expr, $arity:expr => $rust_fn:path,)*},)*) => {
+    ($($module:expr => {$($fun:expr, $arity:expr => $rust_fn:expr,)*},)*) => {
         {
             let mut table: BifTable = HashMap::new();
             $(
@@ -91,6 +130,10 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "link", 1 => bif_erlang_link_1,
             "unlink", 1 => bif_erlang_unlink_1,
             "monitor", 2 => bif_erlang_monitor_2,
+            "monitor", 3 => bif_erlang_monitor_3,
+            "alias", 0 => bif_erlang_alias_0,
+            "alias", 1 => bif_erlang_alias_1,
+            "unalias", 1 => bif_erlang_unalias_1,
             "demonitor", 1 => bif_erlang_demonitor_1,
             "demonitor", 2 => bif_erlang_demonitor_2,
             "self", 0 => bif_erlang_self_0,
@@ -130,13 +173,17 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "throw", 1 => bif_erlang_throw_1,
             "exit", 1 => bif_erlang_exit_1,
             "exit", 2 => bif_erlang_exit_2,
+            "halt", 0 => bif_erlang_halt_0,
+            "halt", 1 => bif_erlang_halt_1,
             "halt", 2 => bif_erlang_halt_2,
+            "resume_process", 1 => resume_process_1,
             "whereis", 1 => bif_erlang_whereis_1,
             "nif_error", 1 => bif_erlang_nif_error_1,
             "nif_error", 2 => bif_erlang_nif_error_2,
             "load_nif", 2 => bif_erlang_load_nif_2,
             "apply", 2 => bif_erlang_apply_2,
             "apply", 3 => bif_erlang_apply_3,
+            "hibernate", 3 => bif_erlang_hibernate_3,
             "register", 2 => bif_erlang_register_2,
             "unregister", 1 => bif_erlang_unregister_1,
             "is_process_alive", 1 => bif_erlang_is_process_alive_1,
@@ -144,6 +191,7 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "loaded", 0 => bif_erlang_loaded_0,
             "module_loaded", 1 => bif_erlang_module_loaded_1,
             "process_flag", 2 => bif_erlang_process_flag_2,
+            "bump_reductions", 1 => bif_erlang_bump_reductions_1,
             "process_info", 2 => info::process_info_2,
             "group_leader", 0 => info::group_leader_0,
             "make_tuple", 2 => erlang::make_tuple_2,
@@ -164,6 +212,10 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "atom_to_list", 1 => erlang::atom_to_list_1,
             "atom_to_binary", 2 => erlang::atom_to_binary_2,
             "pid_to_list", 1 => erlang::pid_to_list_1,
+            "list_to_pid", 1 => erlang::list_to_pid_1,
+            "port_to_list", 1 => erlang::port_to_list_1,
+            "list_to_port", 1 => erlang::list_to_port_1,
+            "list_to_ref", 1 => erlang::list_to_ref_1,
             "integer_to_list", 1 => erlang::integer_to_list_1,
             "integer_to_list", 2 => erlang::integer_to_list_2,
             "integer_to_binary", 1 => erlang::integer_to_binary_1,
@@ -179,9 +231,12 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "--", 2 => erlang::subtract_2,
             "subtract", 2 => erlang::subtract_2,
             "make_ref", 0 => erlang::make_ref_0,
-            "process_info", 2 => info::process_info_2,
+            "process_info", 1 => info::process_info_1,
             "fun_info", 2 => info::fun_info_2,
             "system_info", 1 => info::system_info_1,
+            "statistics", 1 => info::statistics_1,
+            "memory", 0 => info::memory_0,
+            "memory", 1 => info::memory_1,
             "system_flag", 2 => info::system_flag_2,
             "get_module_info", 1 => load::get_module_info_1,
             "get_module_info", 2 => load::get_module_info_2,
@@ -336,11 +391,20 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
             "open_port", 2 => open_port_2,
             "port_close", 1 => erts_internal_port_close_1,
             "port_control", 3 => port_control_3,
-            "spawn_system_process", 3 => bif_erlang_spawn_3, // TODO: aliased to normal spawn for now
+            "spawn_system_process", 3 => erts_internal_spawn_system_process_3,
             "map_next", 3 => erts_internal_map_next_3,
             "time_unit", 0 =>  erts_internal_time_unit_0,
             "purge_module", 2 => load::erts_internal_purge_module_2,
+            "release_literal_area_switch", 0 => load::erts_internal_release_literal_area_switch_0,
             "request_system_task", 3 => erts_internal_request_system_task_3,
+            "suspend_process", 2 => erts_internal_suspend_process_2,
+            "process_flag", 3 => erts_internal_process_flag_3,
+            "dirty_process_handle_signals", 1 => erts_internal_dirty_process_handle_signals_1,
+        },
+        "erts_debug" => {
+            "dirty_cpu", 2 => dirty!(Cpu, erts_debug::dirty_cpu_2),
+            "dirty_io", 2 => dirty!(Io, erts_debug::dirty_io_2),
+            "dirty", 3 => erts_debug::dirty_3,
         },
         "string" => {
             "list_to_integer", 1 => erlang::string_list_to_integer_1,
@@ -402,7 +466,7 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
         },
         "zlib" => {
             // override like we did for beam_file
-            "compress", 1 => prim_file::zlib_compress_1,
+            "compress", 1 => dirty!(Cpu, prim_file::zlib_compress_1),
         },
     ]
 });
@@ -410,7 +474,7 @@ pub static BIFS: Lazy<BifTable> = Lazy::new(|| {
 type NifTable = HashMap<Atom, Vec<(Atom, u32, Fn)>>;
 
 macro_rules! nif_map {
-    ($($module:expr => {$($fun:expr, $arity:expr => $rust_fn:path,)*},)*) => {
+    ($($module:expr => {$($fun:expr, $arity:expr => $rust_fn:expr,)*},)*) => {
         {
             let mut table: NifTable = HashMap::new();
             $(
@@ -427,43 +491,43 @@ macro_rules! nif_map {
 pub static NIFS: Lazy<NifTable> = Lazy::new(|| {
     nif_map![
         "beam_lib" => {
-            "compress", 1 => prim_file::compress_1,
-            "uncompress", 1 => prim_file::uncompress_1,
+            "compress", 1 => dirty!(Cpu, prim_file::compress_1),
+            "uncompress", 1 => dirty!(Cpu, prim_file::uncompress_1),
         },
         "prim_file" => {
-            "open_nif", 2 => prim_file::open_nif_2,
-            "close_nif", 1 => prim_file::close_nif_1,
-            "read_nif", 2 => prim_file::read_nif_2,
-            "write_nif", 2 => prim_file::write_nif_2,
-            "pread_nif", 3 => prim_file::pread_nif_3,
-            "pwrite_nif", 3 => prim_file::pwrite_nif_3,
-            "seek_nif", 3 => prim_file::seek_nif_3,
-            "sync_nif", 2 => prim_file::sync_nif_2,
-            "truncate_nif", 1 => prim_file::truncate_nif_1,
-            "allocate_nif", 3 => prim_file::allocate_nif_3,
-            "advise_nif", 4 => prim_file::advise_nif_4,
+            "open_nif", 2 => dirty!(Io, prim_file::open_nif_2),
+            "close_nif", 1 => dirty!(Io, prim_file::close_nif_1),
+            "read_nif", 2 => dirty!(Io, prim_file::read_nif_2),
+            "write_nif", 2 => dirty!(Io, prim_file::write_nif_2),
+            "pread_nif", 3 => dirty!(Io, prim_file::pread_nif_3),
+            "pwrite_nif", 3 => dirty!(Io, prim_file::pwrite_nif_3),
+            "seek_nif", 3 => dirty!(Io, prim_file::seek_nif_3),
+            "sync_nif", 2 => dirty!(Io, prim_file::sync_nif_2),
+            "truncate_nif", 1 => dirty!(Io, prim_file::truncate_nif_1),
+            "allocate_nif", 3 => dirty!(Io, prim_file::allocate_nif_3),
+            "advise_nif", 4 => dirty!(Io, prim_file::advise_nif_4),
 
             // filesystem ops
-            "make_hard_link_nif", 2 => prim_file::make_hard_link_nif_2,
-            "make_soft_link_nif", 2 => prim_file::make_soft_link_nif_2,
-            "rename_nif", 2 => prim_file::rename_nif_2,
-            "read_info_nif", 2 => prim_file::read_info_nif_2,
-            "set_permissions_nif", 2 => prim_file::set_permissions_nif_2,
-            "set_owner_nif", 3 => prim_file::set_owner_nif_3,
-            "set_time_nif", 4 => prim_file::set_time_nif_4,
-            "read_link_nif", 1 => prim_file::read_link_nif_1,
-            "list_dir_nif", 1 => prim_file::list_dir_nif_1,
-            "make_dir_nif", 1 => prim_file::make_dir_nif_1,
-            "del_file_nif", 1 => prim_file::del_file_nif_1,
-            "del_dir_nif", 1 => prim_file::del_dir_nif_1,
-            "get_device_cwd_nif", 1 => prim_file::get_device_cwd_nif_1,
-            "get_cwd_nif", 0 => prim_file::get_cwd_nif_0,
-            "set_cwd_nif", 1 => prim_file::set_cwd_nif_1,
+            "make_hard_link_nif", 2 => dirty!(Io, prim_file::make_hard_link_nif_2),
+            "make_soft_link_nif", 2 => dirty!(Io, prim_file::make_soft_link_nif_2),
+            "rename_nif", 2 => dirty!(Io, prim_file::rename_nif_2),
+            "read_info_nif", 2 => dirty!(Io, prim_file::read_info_nif_2),
+            "set_permissions_nif", 2 => dirty!(Io, prim_file::set_permissions_nif_2),
+            "set_owner_nif", 3 => dirty!(Io, prim_file::set_owner_nif_3),
+            "set_time_nif", 4 => dirty!(Io, prim_file::set_time_nif_4),
+            "read_link_nif", 1 => dirty!(Io, prim_file::read_link_nif_1),
+            "list_dir_nif", 1 => dirty!(Io, prim_file::list_dir_nif_1),
+            "make_dir_nif", 1 => dirty!(Io, prim_file::make_dir_nif_1),
+            "del_file_nif", 1 => dirty!(Io, prim_file::del_file_nif_1),
+            "del_dir_nif", 1 => dirty!(Io, prim_file::del_dir_nif_1),
+            "get_device_cwd_nif", 1 => dirty!(Io, prim_file::get_device_cwd_nif_1),
+            "get_cwd_nif", 0 => dirty!(Io, prim_file::get_cwd_nif_0),
+            "set_cwd_nif", 1 => dirty!(Io, prim_file::set_cwd_nif_1),
 
             // These operations are equivalent to chained calls of other operations,
             // but have been moved down to avoid excessive rescheduling.
-            "ipread_s32bu_p32bu_nif", 3 => prim_file::ipread_s32bu_p32bu_nif_3,
-            "read_file_nif", 1 => prim_file::read_file_nif_1,
+            "ipread_s32bu_p32bu_nif", 3 => dirty!(Io, prim_file::ipread_s32bu_p32bu_nif_3),
+            "read_file_nif", 1 => dirty!(Io, prim_file::read_file_nif_1),
 
             // internal nifs
             "get_handle_nif", 1 => prim_file::get_handle_nif_1,
@@ -503,32 +567,51 @@ pub fn apply(vm: &Machine, process: &RcProcess, mfa: &module::MFA, args: &[Term]
 
 /// Bif implementations
 fn bif_erlang_spawn_3(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
-    // arg[0] = atom for module
-    // arg[1] = atom for function
-    // arg[2] = arguments for func (well-formed list)
-    // opts, options for spawn
+    spawn_mfa(vm, process, args, process::SpawnOpts::default())
+}
 
-    let module = match args[0].into_variant() {
-        Variant::Atom(module) => module,
-        _ => return Err(badarg!()),
+fn bif_erlang_spawn_link_3(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    let opts = process::SpawnOpts {
+        flags: process::SpawnFlag::LINK,
+        ..Default::default()
     };
+    spawn_mfa(vm, process, args, opts)
+}
 
-    let func = match args[1].into_variant() {
-        Variant::Atom(func) => func,
+/// Spawns a process that the system can't do without, it brings down the VM if it ever exits.
+fn erts_internal_spawn_system_process_3(
+    vm: &Machine,
+    process: &RcProcess,
+    args: &[Term],
+) -> Result {
+    let opts = process::SpawnOpts {
+        flags: process::SpawnFlag::SYSTEM_PROC,
+        ..Default::default()
+    };
+    spawn_mfa(vm, process, args, opts)
+}
+
+fn bif_erlang_spawn_opt_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    // arg 0 is a 4 value tuple
+    let tup: &Tuple = match Tuple::cast_from(&args[0]) {
+        Ok(tup) => {
+            if tup.len() != 4 {
+                return Err(badarg!());
+            }
+            tup
+        }
         _ => return Err(badarg!()),
     };
-    let arglist = args[2];
 
-    let registry = vm.modules.lock();
-    let module = registry.lookup(module).unwrap();
-    process::spawn(vm, process, module, func, arglist, process::SpawnFlag::NONE)
+    let opts = spawn_opts(vm, tup[3])?;
+    spawn_mfa(vm, process, &tup[0..3], opts)
 }
 
-fn bif_erlang_spawn_link_3(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+/// Spawns `args[0]:args[1]` with the arguments in `args[2]`.
+fn spawn_mfa(vm: &Machine, process: &RcProcess, args: &[Term], opts: process::SpawnOpts) -> Result {
     // arg[0] = atom for module
     // arg[1] = atom for function
     // arg[2] = arguments for func (well-formed list)
-    // opts, options for spawn
 
     let module = match args[0].into_variant() {
         Variant::Atom(module) => module,
@@ -543,61 +626,84 @@ fn bif_erlang_spawn_link_3(vm: &Machine, process: &RcProcess, args: &[Term]) ->
 
     let registry = vm.modules.lock();
     let module = registry.lookup(module).unwrap();
-    process::spawn(vm, process, module, func, arglist, process::SpawnFlag::LINK)
+    process::spawn(vm, process, module, func, arglist, opts)
 }
 
-fn bif_erlang_spawn_opt_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+/// Parses the option list given to spawn_opt.
+fn spawn_opts(vm: &Machine, list: Term) -> std::result::Result<process::SpawnOpts, Exception> {
     use process::SpawnFlag;
 
-    // arg 0 is a 4 value tuple
-    let tup: &Tuple = match Tuple::cast_from(&args[0]) {
-        Ok(tup) => {
-            if tup.len() != 4 {
-                return Err(badarg!());
-            }
-            tup
-        }
-        _ => return Err(badarg!()),
-    };
+    let mut opts = process::SpawnOpts::default();
 
-    let module = match tup[0].into_variant() {
-        Variant::Atom(module) => module,
-        _ => return Err(badarg!()),
-    };
+    if list.is_nil() {
+        return Ok(opts);
+    }
 
-    let func = match tup[1].into_variant() {
-        Variant::Atom(func) => func,
-        _ => return Err(badarg!()),
+    let size = |term: Term| match term.into_variant() {
+        Variant::Integer(size) if size >= 0 => Ok(size as usize),
+        _ => Err(badarg!()),
     };
-    let arglist = tup[2];
-
-    let opts = Cons::cast_from(&tup[3])?;
 
-    let flag = opts.iter().fold(SpawnFlag::NONE, |acc, val| {
+    for val in Cons::cast_from(&list)?.iter() {
         match val.into_variant() {
-            Variant::Atom(atom::LINK) => acc | SpawnFlag::LINK,
-            Variant::Atom(atom::MONITOR) => acc | SpawnFlag::MONITOR,
+            Variant::Atom(atom::LINK) => opts.flags |= SpawnFlag::LINK,
+            Variant::Atom(atom::MONITOR) => opts.flags |= SpawnFlag::MONITOR,
             _ => {
-                if let Ok(tup) = Tuple::cast_from(&val) {
-                    if tup.len() != 2 {
-                        unimplemented!("error!");
-                        // return Err(badarg!());
+                let tup = match Tuple::cast_from(&val) {
+                    Ok(tup) if tup.len() == 2 => tup,
+                    _ => return Err(badarg!()),
+                };
+                match tup[0].into_variant() {
+                    Variant::Atom(atom::MONITOR) => {
+                        let (alias, tag) = monitor_opts(tup[1])?;
+                        opts.monitor_alias = alias;
+                        opts.monitor_tag = tag;
+                        opts.flags |= SpawnFlag::MONITOR;
+                    }
+                    Variant::Atom(atom::MESSAGE_QUEUE_DATA) => {
+                        opts.flags -= SpawnFlag::OFF_HEAP_MSGQ | SpawnFlag::ON_HEAP_MSGQ;
+                        match tup[1].into_variant() {
+                            Variant::Atom(atom::OFF_HEAP) => opts.flags |= SpawnFlag::OFF_HEAP_MSGQ,
+                            Variant::Atom(atom::ON_HEAP) => opts.flags |= SpawnFlag::ON_HEAP_MSGQ,
+                            _ => return Err(badarg!()),
+                        }
+                    }
+                    Variant::Atom(atom::MAX_HEAP_SIZE) => {
+                        let max_heap_size =
+                            process::gc::MaxHeapSize::from_term(tup[1], vm.config.min_heap_size)
+                                .ok_or_else(|| badarg!())?;
+                        opts.max_heap_size = Some(max_heap_size);
                     }
-                    match tup[0].into_variant() {
-                        Variant::Atom(atom::MESSAGE_QUEUE_DATA) => acc, // TODO: implement
-                        opt => unimplemented!("Unimplemented spawn_opt for {}", opt),
+                    Variant::Atom(atom::MIN_HEAP_SIZE) => {
+                        opts.min_heap_size = Some(size(tup[1])? * process::gc::WORD_SIZE);
                     }
-                } else {
-                    unimplemented!("Unimplemented spawn_opt for badarg");
-                    // return Err(badarg!());
+                    Variant::Atom(atom::MIN_BIN_VHEAP_SIZE) => {
+                        opts.min_bin_vheap_size = Some(size(tup[1])? * process::gc::WORD_SIZE);
+                    }
+                    Variant::Atom(atom::FULLSWEEP_AFTER) => {
+                        opts.fullsweep_after = Some(size(tup[1])?);
+                    }
+                    Variant::Atom(atom::PRIORITY) => match tup[1].into_variant() {
+                        Variant::Atom(priority) => {
+                            opts.priority =
+                                Some(Priority::from_atom(priority).ok_or_else(|| badarg!())?);
+                        }
+                        _ => return Err(badarg!()),
+                    },
+                    _ => return Err(badarg!()),
                 }
             }
         }
-    });
+    }
 
-    let registry = vm.modules.lock();
-    let module = registry.lookup(module).unwrap();
-    process::spawn(vm, process, module, func, arglist, flag)
+    // the max heap size has to fit the min heap size we end up with
+    if let (Some(max_heap_size), Some(min_heap_size)) = (opts.max_heap_size, opts.min_heap_size) {
+        if max_heap_size.size != 0 && max_heap_size.size * process::gc::WORD_SIZE < min_heap_size {
+            return Err(badarg!());
+        }
+    }
+
+    Ok(opts)
 }
 
 fn bif_erlang_link_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
@@ -666,66 +772,181 @@ fn bif_erlang_unlink_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Resu
 }
 
 fn bif_erlang_monitor_2(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
-    // arg[0] = pid/port
+    monitor(vm, process, args[0], args[1], None, None)
+}
+
+/// monitor(Type, Item, Options) can also turn the monitor reference into an alias, and tag the
+/// `DOWN` message.
+fn bif_erlang_monitor_3(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    let (alias, tag) = monitor_opts(args[2])?;
+    monitor(vm, process, args[0], args[1], alias, tag)
+}
+
+/// Parses the options of monitor/3 and spawn_opt's `{monitor, Options}`.
+fn monitor_opts(
+    list: Term,
+) -> std::result::Result<(Option<process::AliasKind>, Option<Term>), Exception> {
+    use process::AliasKind;
+
+    let mut alias = None;
+    let mut tag = None;
+    if list.is_nil() {
+        return Ok((alias, tag));
+    }
+    for opt in Cons::cast_from(&list)?.iter() {
+        let tup = match Tuple::cast_from(opt) {
+            Ok(tup) if tup.len() == 2 => tup,
+            _ => return Err(badarg!()),
+        };
+        match tup[0].into_variant() {
+            Variant::Atom(atom::ALIAS) => {
+                alias = match tup[1].into_variant() {
+                    Variant::Atom(atom::EXPLICIT_UNALIAS) => Some(AliasKind::ExplicitUnalias),
+                    Variant::Atom(atom::DEMONITOR) => Some(AliasKind::Demonitor),
+                    Variant::Atom(atom::REPLY_DEMONITOR) => Some(AliasKind::ReplyDemonitor),
+                    _ => return Err(badarg!()),
+                };
+            }
+            Variant::Atom(atom::TAG) => tag = Some(tup[1]),
+            _ => return Err(badarg!()),
+        }
+    }
+    Ok((alias, tag))
+}
+
+fn monitor(
+    vm: &Machine,
+    process: &RcProcess,
+    kind: Term,
+    item: Term,
+    alias: Option<process::AliasKind>,
+    tag: Option<Term>,
+) -> Result {
+    use process::Monitored;
+
     let heap = &process.context_mut().heap;
-    let reference = vm.next_ref();
-    let ref_term = Term::reference(heap, reference);
-    // TODO: this will still alloc on badarg ^
 
-    match args[0].into_variant() {
-        Variant::Atom(atom::PROCESS) => {
-            let pid = match args[1].into_variant() {
-                Variant::Pid(pid) => {
-                    if pid == process.pid {
-                        return Ok(ref_term);
-                    }
-                    pid
-                }
-                Variant::Atom(name) => {
-                    if let Some(process) = vm.process_registry.lock().whereis(name) {
-                        process.pid
-                    } else {
-                        println!("registered name {} not found!", args[1]);
-                        return Err(badarg!());
-                    }
-                }
-                // TODO: {atom name, node}
-                Variant::Pointer(_) => unimplemented!("monitor for {}", args[1]),
-                Variant::Port(_) => unimplemented!("monitor for {}", args[1]),
+    // resolve the target, one that doesn't exist gets its DOWN message right away
+    let target = match (kind.into_variant(), item.into_variant()) {
+        (Variant::Atom(atom::PROCESS), Variant::Pid(pid)) => {
+            let alive = vm
+                .process_table
+                .lock()
+                .get(pid)
+                .map_or(false, |target| !target.is_exiting());
+            if alive {
+                Ok(Monitored::Process(pid))
+            } else {
+                Err((item, atom!(NOPROC)))
+            }
+        }
+        (Variant::Atom(atom::PROCESS), Variant::Atom(name)) => {
+            monitor_name(vm, heap, name, atom!(NO_NODE_NO_HOST))
+        }
+        (Variant::Atom(atom::PROCESS), Variant::Pointer(_)) => {
+            let tup = Tuple::cast_from(&item)?;
+            if tup.len() != 2 || !tup[1].is_atom() {
+                return Err(badarg!());
+            }
+            match tup[0].into_variant() {
+                Variant::Atom(name) => monitor_name(vm, heap, name, tup[1]),
                 _ => return Err(badarg!()),
-            };
+            }
+        }
+        (Variant::Atom(atom::PORT), Variant::Port(id)) => {
+            if vm.port_table.read().lookup(id).is_some() {
+                Ok(Monitored::Port(id))
+            } else {
+                Err((item, atom!(NOPROC)))
+            }
+        }
+        // ports can't be registered, so a name never resolves to one
+        (Variant::Atom(atom::PORT), Variant::Atom(_)) => {
+            Err((tup2!(heap, item, atom!(NO_NODE_NO_HOST)), atom!(NOPROC)))
+        }
+        (Variant::Atom(atom::TIME_OFFSET), Variant::Atom(atom::CLOCK_SERVICE)) => {
+            Ok(Monitored::TimeOffset)
+        }
+        _ => return Err(badarg!()),
+    };
 
-            // add the pid to our monitor tree
-            process.local_data_mut().monitors.insert(reference, pid);
+    let reference = match alias {
+        Some(kind) => {
+            let reference = process::alias_ref(vm, process.pid);
+            process.local_data_mut().aliases.insert(reference, kind);
+            reference
+        }
+        None => vm.next_ref(),
+    };
+    if let Some(tag) = tag {
+        process.local_data_mut().monitor_tags.insert(reference, tag);
+    }
+    let ref_term = Term::reference(heap, reference);
 
-            // send MONITOR signal to the other process return true
-            let sent = process::send_signal(
-                vm,
-                pid,
-                process::Signal::Monitor {
-                    from: process.pid,
-                    reference,
-                },
-            );
+    let target = match target {
+        Ok(target) => target,
+        Err((object, reason)) => {
+            process.monitor_down(reference, kind, object, reason);
+            return Ok(ref_term);
+        }
+    };
 
-            if !sent {
+    let sent = match target {
+        Monitored::Port(id) => match vm.port_table.read().lookup(id) {
+            Some(mut port) => {
+                port.monitor(process.pid, reference);
+                true
+            }
+            None => false,
+        },
+        // the offset never changes, so there's nobody to tell
+        Monitored::TimeOffset => true,
+        Monitored::Process(pid) | Monitored::Name(pid, _) => {
+            if pid == process.pid {
+                process
+                    .local_data_mut()
+                    .lt_monitors
+                    .push((process.pid, reference));
+                true
+            } else {
+                // send MONITOR signal to the other process
                 process::send_signal(
                     vm,
-                    process.pid,
-                    process::Signal::MonitorDown {
+                    pid,
+                    process::Signal::Monitor {
                         from: process.pid,
-                        // TODO: could be just reason: term
-                        reason: Exception::with_value(Reason::EXC_ERROR, atom!(NOPROC)),
                         reference,
                     },
-                );
+                )
             }
-
-            Ok(ref_term)
         }
-        Variant::Atom(atom::PORT) => unimplemented!(),
-        Variant::Atom(atom::TIME_OFFSET) => unimplemented!(),
-        _ => Err(badarg!()),
+    };
+
+    if sent {
+        // add the target to our monitor tree
+        process.local_data_mut().monitors.insert(reference, target);
+    } else {
+        let (kind, object) = target.down_object(heap);
+        process.monitor_down(reference, kind, object, atom!(NOPROC));
+    }
+
+    Ok(ref_term)
+}
+
+/// Looks up a registered name to monitor. Names on other nodes can't be reached, as we're never
+/// distributed.
+fn monitor_name(
+    vm: &Machine,
+    heap: &Heap,
+    name: Atom,
+    node: Term,
+) -> std::result::Result<process::Monitored, (Term, Term)> {
+    if node != atom!(NO_NODE_NO_HOST) {
+        return Err((tup2!(heap, Term::atom(name), node), atom!(NOCONNECTION)));
+    }
+    match vm.process_registry.lock().whereis(name) {
+        Some(target) if !target.is_exiting() => Ok(process::Monitored::Name(target.pid, name)),
+        _ => Err((tup2!(heap, Term::atom(name), node), atom!(NOPROC))),
     }
 }
 
@@ -737,24 +958,46 @@ fn demonitor(
     // TODO: inefficient, we do get_boxed_ twice
     if reference.get_boxed_header() == Ok(value::BOXED_REF) {
         let reference = reference.get_boxed_value().unwrap();
-        // remove the pid from our monitor tree
-        if let Some(pid) = process.local_data_mut().monitors.remove(&reference) {
-            // send DEMONITOR signal to the other process return true
-            process::send_signal(
-                vm,
-                pid,
-                process::Signal::Demonitor {
-                    from: process.pid,
-                    reference: *reference,
-                },
-            );
-            return Ok(true);
-        }
-        return Ok(false);
+        return Ok(process.demonitor(vm, *reference));
     }
     Err(badarg!())
 }
 
+/// alias() makes a reference that other processes can send messages to, until it's unaliased.
+fn bif_erlang_alias_0(vm: &Machine, process: &RcProcess, _args: &[Term]) -> Result {
+    bif_erlang_alias_1(vm, process, &[Term::nil()])
+}
+
+/// alias(Options) makes an alias that, with the `reply` option, only lets a single message
+/// through.
+fn bif_erlang_alias_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    use process::AliasKind;
+
+    let mut kind = AliasKind::ExplicitUnalias;
+    if !args[0].is_nil() {
+        for opt in Cons::cast_from(&args[0])?.iter() {
+            kind = match opt.into_variant() {
+                Variant::Atom(atom::EXPLICIT_UNALIAS) => AliasKind::ExplicitUnalias,
+                Variant::Atom(atom::REPLY) => AliasKind::Reply,
+                _ => return Err(badarg!()),
+            };
+        }
+    }
+
+    let reference = process::alias_ref(vm, process.pid);
+    process.local_data_mut().aliases.insert(reference, kind);
+    Ok(Term::reference(&process.context_mut().heap, reference))
+}
+
+/// unalias(Alias) deactivates one of our aliases, returning false if it wasn't active.
+fn bif_erlang_unalias_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    if args[0].get_boxed_header() != Ok(value::BOXED_REF) {
+        return Err(badarg!());
+    }
+    let reference = args[0].get_boxed_value::<process::Ref>().unwrap();
+    Ok(Term::boolean(process.unalias(*reference)))
+}
+
 fn bif_erlang_demonitor_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
     // arg[0] = ref
     demonitor(vm, process, args[0])?;
@@ -805,6 +1048,18 @@ fn bif_erlang_demonitor_2(vm: &Machine, process: &RcProcess, args: &[Term]) -> R
     Ok(res)
 }
 
+/// Charges the process for extra work. The charge is capped to a single time slice.
+fn bif_erlang_bump_reductions_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    let reds = match args[0].into_variant() {
+        Variant::Integer(reds) if reds >= 0 => reds as usize,
+        _ => return Err(badarg!()),
+    };
+    process
+        .context_mut()
+        .bump_reductions(std::cmp::min(reds, process::CONTEXT_REDS));
+    Ok(atom!(TRUE))
+}
+
 fn bif_erlang_self_0(_vm: &Machine, process: &RcProcess, _args: &[Term]) -> Result {
     Ok(Term::pid(process.pid))
 }
@@ -816,7 +1071,12 @@ fn bif_erlang_send_2(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result
 
     match pid.into_variant() {
         Variant::Port(id) => port::send_message(vm, process.pid, id, msg),
-        _ => process::send_message(vm, process.pid, pid, msg),
+        _ => {
+            let size = process::send_message(vm, process.pid, pid, msg)?;
+            let reds = process::send_reductions(size);
+            process.context_mut().bump_reductions(reds);
+            Ok(msg)
+        }
     }
 }
 
@@ -1056,15 +1316,50 @@ fn bif_erlang_error_2(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Resu
     ))
 }
 
-fn bif_erlang_halt_2(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> Result {
-    // arg[0] exit code
-    // arg[1] options
+fn bif_erlang_halt_0(vm: &Machine, process: &RcProcess, _args: &[Term]) -> Result {
+    bif_erlang_halt_2(vm, process, &[Term::int(0), Term::nil()])
+}
+
+fn bif_erlang_halt_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    bif_erlang_halt_2(vm, process, &[args[0], Term::nil()])
+}
+
+/// Status is either a non-negative exit code, `abort` to dump core, or a string that gets
+/// written to a crash dump as the slogan. `{flush, false}` skips flushing pending output.
+fn bif_erlang_halt_2(vm: &Machine, _process: &RcProcess, args: &[Term]) -> Result {
+    let mut flush = true;
 
     if !args[1].is_nil() {
-        unimplemented!()
+        for opt in Cons::cast_from(&args[1])?.iter() {
+            let tup = Tuple::cast_from(opt)?;
+            if tup.len() != 2 {
+                return Err(badarg!());
+            }
+            match (tup[0].into_variant(), tup[1].into_variant()) {
+                (Variant::Atom(atom::FLUSH), Variant::Atom(atom::TRUE)) => flush = true,
+                (Variant::Atom(atom::FLUSH), Variant::Atom(atom::FALSE)) => flush = false,
+                _ => return Err(badarg!()),
+            }
+        }
     }
 
-    unimplemented!()
+    match args[0].into_variant() {
+        Variant::Integer(status) if status >= 0 => vm.halt(status, flush),
+        Variant::Atom(atom::ABORT) => std::process::abort(),
+        _ => {
+            let slogan = match Cons::cast_from(&args[0]) {
+                // longer slogans get truncated rather than rejected
+                Ok(cons) => value::cons::unicode_list_to_buf(cons, std::usize::MAX)?,
+                Err(_) if args[0].is_nil() => String::new(),
+                Err(_) => return Err(badarg!()),
+            };
+            let slogan: String = slogan.chars().take(crate::vm::HALT_SLOGAN_SIZE).collect();
+            vm.crash_dump(&slogan);
+            vm.halt(1, flush);
+        }
+    }
+    // the VM is going down, halt never returns
+    Err(Exception::new(Reason::HALT))
 }
 
 fn bif_erlang_raise_3(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
@@ -1133,7 +1428,7 @@ pub fn bif_erlang_load_nif_2(vm: &Machine, process: &RcProcess, args: &[Term]) -
     // println!("Tried loading nif: {} with args {}", args[0], args[1]);
 
     if let Ok(cons) = args[0].cast_into() {
-        let name = value::cons::unicode_list_to_buf(cons, 2048).unwrap();
+        let name = value::cons::unicode_list_to_buf(cons, std::usize::MAX).unwrap();
         let atom = Atom::from(name);
         let nifs = match NIFS.get(&atom) {
             Some(nifs) => nifs,
@@ -1166,47 +1461,156 @@ pub fn bif_erlang_apply_3(_vm: &Machine, _process: &RcProcess, _args: &[Term]) -
     // maps to i_apply
 }
 
+pub fn bif_erlang_hibernate_3(_vm: &Machine, _process: &RcProcess, _args: &[Term]) -> Result {
+    // module, function (atom), args
+    unreachable!("hibernate/3 called without macro override");
+    // maps to i_hibernate
+}
+
 /// this sets some process info- trapping exits or the error handler
 pub fn bif_erlang_process_flag_2(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
-    match args[0].into_variant() {
+    process_flag(process, process, args[0], args[1])
+}
+
+/// erts_internal:process_flag(Pid, save_calls, Value) sets `save_calls` on another process, the
+/// only flag that can be set from the outside. The other process owns its state, so it gets
+/// asked to do so through a signal: a reference is returned right away, and the old value
+/// arrives later as `{Ref, OldValue}` (`{Ref, badarg}` if it exited in the meantime).
+fn erts_internal_process_flag_3(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) => pid,
+        _ => return Err(badarg!()),
+    };
+    if args[1] != atom!(SAVE_CALLS) {
+        return Err(badarg!());
+    }
+    if pid == process.pid {
+        return process_flag(process, process, args[1], args[2]);
+    }
+    let len = match args[2].into_variant() {
+        Variant::Integer(len) if len >= 0 && len as usize <= process::MAX_SAVED_CALLS => {
+            len as usize
+        }
+        _ => return Err(badarg!()),
+    };
+
+    let reference = vm.next_ref();
+    let signal = process::Signal::SaveCalls {
+        from: process.pid,
+        reference,
+        len,
+    };
+    if !process::send_signal(vm, pid, signal) {
+        return Err(badarg!());
+    }
+    let heap = &process.context_mut().heap;
+    Ok(Term::reference(heap, reference))
+}
+
+/// Sets `flag` on `target`, returning the previous value on the heap of `process`.
+fn process_flag(process: &RcProcess, target: &RcProcess, flag: Term, value: Term) -> Result {
+    let heap = &process.context_mut().heap;
+    let local_data = target.local_data_mut();
+
+    match flag.into_variant() {
         Variant::Atom(atom::TRAP_EXIT) => {
-            let local_data = process.local_data_mut();
             let old_value = local_data.flags.contains(process::Flag::TRAP_EXIT);
-            match args[1].into_variant() {
-                // TODO atom to_bool, then pass that in as 2 arg
-                Variant::Atom(atom::TRUE) => local_data.flags.set(process::Flag::TRAP_EXIT, true),
-                Variant::Atom(atom::FALSE) => local_data.flags.set(process::Flag::TRAP_EXIT, false),
-                _ => return Err(badarg!()),
-            }
+            let value = value.to_bool().ok_or_else(|| badarg!())?;
+            local_data.flags.set(process::Flag::TRAP_EXIT, value);
+            Ok(Term::boolean(old_value))
+        }
+        Variant::Atom(atom::SENSITIVE) => {
+            let old_value = local_data.flags.contains(process::Flag::SENSITIVE);
+            let value = value.to_bool().ok_or_else(|| badarg!())?;
+            local_data.flags.set(process::Flag::SENSITIVE, value);
             Ok(Term::boolean(old_value))
         }
+        Variant::Atom(atom::ERROR_HANDLER) => {
+            let handler = value.to_atom().ok_or_else(|| badarg!())?;
+            let old_value = std::mem::replace(&mut local_data.error_handler, handler);
+            Ok(Term::atom(old_value))
+        }
         Variant::Atom(atom::PRIORITY) => {
-            use process::StateFlag;
-            let flag = match args[1].into_variant() {
-                // TODO atom to_bool, then pass that in as 2 arg
-                Variant::Atom(atom::MAX) => StateFlag::PRQ_MAX,
-                Variant::Atom(atom::HIGH) => StateFlag::PRQ_HIGH,
-                Variant::Atom(atom::MEDIUM) => StateFlag::PRQ_MEDIUM,
-                Variant::Atom(atom::LOW) => StateFlag::PRQ_LOW,
+            let priority = match value.into_variant() {
+                Variant::Atom(atom) => Priority::from_atom(atom).ok_or_else(|| badarg!())?,
+                _ => return Err(badarg!()),
+            };
+            let old_value = local_data.priority;
+            // takes effect the next time the process gets scheduled
+            local_data.priority = priority;
+            Ok(Term::atom(old_value.to_atom()))
+        }
+        Variant::Atom(atom::MESSAGE_QUEUE_DATA) => {
+            let old_value = local_data.flags.contains(process::Flag::OFF_HEAP_MSGQ);
+            match value.into_variant() {
+                Variant::Atom(atom::OFF_HEAP) => {
+                    local_data.flags.set(process::Flag::OFF_HEAP_MSGQ, true)
+                }
+                Variant::Atom(atom::ON_HEAP) => {
+                    local_data.flags.set(process::Flag::OFF_HEAP_MSGQ, false)
+                }
+                _ => return Err(badarg!()),
+            }
+            Ok(if old_value {
+                atom!(OFF_HEAP)
+            } else {
+                atom!(ON_HEAP)
+            })
+        }
+        Variant::Atom(atom::MIN_HEAP_SIZE) => {
+            let gc = &mut local_data.gc;
+            let size = match value.into_variant() {
+                Variant::Integer(size) if size >= 0 => size as usize,
                 _ => return Err(badarg!()),
             };
-            let local_data = process.local_data_mut();
 
-            let old_value = match local_data.state & StateFlag::PRQ_MASK {
-                StateFlag::PRQ_MAX => atom!(MAX),
-                _ => atom!(UNDEFINED),
+            let old_value = Term::uint64(heap, (gc.min_heap_size / process::gc::WORD_SIZE) as u64);
+            gc.set_min_heap_size(size * process::gc::WORD_SIZE);
+            Ok(old_value)
+        }
+        Variant::Atom(atom::MIN_BIN_VHEAP_SIZE) => {
+            let gc = &mut local_data.gc;
+            let size = match value.into_variant() {
+                Variant::Integer(size) if size >= 0 => size as usize,
+                _ => return Err(badarg!()),
             };
-            local_data.state = flag;
+
+            let old_value = Term::uint64(
+                heap,
+                (gc.min_bin_vheap_size / process::gc::WORD_SIZE) as u64,
+            );
+            gc.set_min_bin_vheap_size(size * process::gc::WORD_SIZE);
             Ok(old_value)
         }
-        Variant::Atom(atom::MESSAGE_QUEUE_DATA) => {
-            // TODO: unimplemented
-            Ok(atom!(ON_HEAP))
+        Variant::Atom(atom::FULLSWEEP_AFTER) => {
+            let gc = &mut local_data.gc;
+            let count = match value.into_variant() {
+                Variant::Integer(count) if count >= 0 => count as usize,
+                _ => return Err(badarg!()),
+            };
+            let old_value = std::mem::replace(&mut gc.fullsweep_after, count);
+            Ok(Term::uint64(heap, old_value as u64))
+        }
+        Variant::Atom(atom::SAVE_CALLS) => {
+            let len = match value.into_variant() {
+                Variant::Integer(len) if len >= 0 && len as usize <= process::MAX_SAVED_CALLS => {
+                    len as usize
+                }
+                _ => return Err(badarg!()),
+            };
+            Ok(Term::uint64(heap, target.set_save_calls(len) as u64))
         }
-        Variant::Atom(i) => {
-            unimplemented!("erlang:process_flag/2 not implemented for {:?}", i.to_str())
+        Variant::Atom(atom::MAX_HEAP_SIZE) => {
+            let gc = &mut local_data.gc;
+            let min_heap_size = gc.min_heap_size / process::gc::WORD_SIZE;
+            let max_heap_size = process::gc::MaxHeapSize::from_term(value, min_heap_size)
+                .ok_or_else(|| badarg!())?;
+
+            let old_value = gc.max_heap_size.to_term(heap);
+            gc.set_max_heap_size(max_heap_size);
+            Ok(old_value)
         }
-        _ => unreachable!(),
+        _ => Err(badarg!()),
     }
 }
 
@@ -1243,7 +1647,11 @@ fn bif_erlang_unregister_1(vm: &Machine, _process: &RcProcess, args: &[Term]) ->
 fn bif_erlang_is_process_alive_1(vm: &Machine, _process: &RcProcess, args: &[Term]) -> Result {
     /* (Atom, Pid|Port)   */
     if let Variant::Pid(pid) = args[0].into_variant() {
-        let res = vm.process_table.lock().contains_key(pid);
+        // a recycled number comes with a new serial, so stale pids don't match
+        let res = match vm.process_table.lock().get(pid) {
+            Some(process) => !process.is_exiting(),
+            None => false,
+        };
 
         return Ok(Term::boolean(res));
     }
@@ -1320,13 +1728,25 @@ fn io_printable_range_0(_vm: &Machine, _process: &RcProcess, _args: &[Term]) ->
     Ok(atom!(LATIN1))
 }
 
-fn garbage_collect_1(_vm: &Machine, _process: &RcProcess, _args: &[Term]) -> Result {
-    // TODO: GC unimplemented
+fn garbage_collect_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    // the arguments are the only live X registers during a bif call
+    let live = args.len();
+
+    match args[0].into_variant() {
+        Variant::Atom(atom::MAJOR) => process::gc::collect_major(process, live),
+        Variant::Atom(atom::MINOR) => process::gc::collect(process, live),
+        _ => return Err(badarg!()),
+    }
+    process::gc::check_max_heap_size(vm, process)?;
     Ok(atom!(TRUE))
 }
-fn scheduler_wall_time_1(_vm: &Machine, _process: &RcProcess, _args: &[Term]) -> Result {
-    // TODO: stats unimplemented
-    Ok(atom!(FALSE))
+fn scheduler_wall_time_1(vm: &Machine, _process: &RcProcess, args: &[Term]) -> Result {
+    let enabled = match args[0].into_variant() {
+        Variant::Atom(atom::TRUE) => true,
+        Variant::Atom(atom::FALSE) => false,
+        _ => return Err(badarg!()),
+    };
+    Ok(Term::boolean(vm.stats.set_scheduler_wall_time(enabled)))
 }
 fn inet_open_8(_vm: &Machine, process: &RcProcess, _args: &[Term]) -> Result {
     // TODO: ports unimplemented
@@ -1416,19 +1836,196 @@ fn erts_internal_time_unit_0(_vm: &Machine, process: &RcProcess, _args: &[Term])
 }
 
 fn erts_internal_request_system_task_3(
-    _vm: &Machine,
+    vm: &Machine,
     process: &RcProcess,
-    _args: &[Term],
+    args: &[Term],
 ) -> Result {
-    unimplemented!()
-}
+    // args are pid, priority, request
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) => pid,
+        _ => return Err(badarg!()),
+    };
 
-// FIXME: phash and phash2 are the same, and they don't match the ERTS ones. And they return 64 bit
-// vals instead of max 32 bit ones. *shrug*
+    match args[1].into_variant() {
+        Variant::Atom(atom::INHERIT)
+        | Variant::Atom(atom::MAX)
+        | Variant::Atom(atom::HIGH)
+        | Variant::Atom(atom::NORMAL)
+        | Variant::Atom(atom::LOW) => (),
+        _ => return Err(badarg!()),
+    }
 
-fn phash_2(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+    let request = Tuple::cast_from(&args[2])?;
+    if request.len != 3 {
+        return Err(badarg!());
+    }
+
+    // the reply we send if the process doesn't exist
+    let noproc = match (request[0].into_variant(), request[2].into_variant()) {
+        (Variant::Atom(atom::GARBAGE_COLLECT), Variant::Atom(atom::MAJOR))
+        | (Variant::Atom(atom::GARBAGE_COLLECT), Variant::Atom(atom::MINOR)) => atom!(FALSE),
+        (Variant::Atom(atom::CHECK_PROCESS_CODE), Variant::Atom(_)) => atom!(FALSE),
+        // the third element tells whether the process may collect, we always do.
+        (Variant::Atom(atom::COPY_LITERALS), Variant::Atom(atom::TRUE))
+        | (Variant::Atom(atom::COPY_LITERALS), Variant::Atom(atom::FALSE)) => atom!(OK),
+        _ => return Err(badarg!()),
+    };
+
+    let signal = process::Signal::SystemTask {
+        from: process.pid,
+        request: args[2],
+    };
+
+    if !process::send_signal(vm, pid, signal) {
+        // the process doesn't exist, reply right away
+        let heap = &process.context_mut().heap;
+        let reply = tup3!(heap, request[0], request[1], noproc);
+        process.send_message(process.pid, reply);
+    }
+    Ok(atom!(OK))
+}
+
+/// Suspends a process on behalf of the caller. Errors are returned rather than raised, for
+/// `erlang:suspend_process/1,2` to raise them with its own arguments. A synchronous suspend
+/// returns a reference, the caller waits for `{Ref, Result}` once the process suspended.
+fn erts_internal_suspend_process_2(
+    vm: &Machine,
+    process: &RcProcess,
+    args: &[Term],
+) -> Result {
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) if pid != process.pid => pid,
+        _ => return Ok(atom!(BADARG)),
+    };
+
+    let mut unless_suspending = false;
+    let mut asynchronous = false;
+    let mut reply_tag = None;
+    if !args[1].is_nil() {
+        let opts = match Cons::cast_from(&args[1]) {
+            Ok(opts) => opts,
+            Err(_) => return Ok(atom!(BADARG)),
+        };
+        for opt in opts.iter() {
+            match opt.into_variant() {
+                Variant::Atom(atom::UNLESS_SUSPENDING) => unless_suspending = true,
+                Variant::Atom(atom::ASYNCHRONOUS) => asynchronous = true,
+                _ => match Tuple::cast_from(opt) {
+                    Ok(tup) if tup.len() == 2 && tup[0] == atom!(ASYNCHRONOUS) => {
+                        asynchronous = true;
+                        reply_tag = Some(tup[1]);
+                    }
+                    _ => return Ok(atom!(BADARG)),
+                },
+            }
+        }
+    }
+
+    let suspending = &mut process.local_data_mut().suspending;
+    if vm.process_table.lock().get(pid).is_none() {
+        // our suspends went away with the process
+        suspending.remove(&pid);
+        return Ok(atom!(BADARG));
+    }
+    let count = suspending.get(&pid).copied().unwrap_or(0);
+    if unless_suspending && count > 0 {
+        return Ok(atom!(FALSE));
+    }
+    let count = match count.checked_add(1) {
+        Some(count) => count,
+        None => return Ok(atom!(SYSTEM_LIMIT)),
+    };
+
+    let heap = &process.context_mut().heap;
+    let (reply, res) = if asynchronous {
+        let reply = reply_tag.map(|tag| process::SuspendReply { tag, sync: false });
+        (reply, atom!(TRUE))
+    } else {
+        let reference = Term::reference(heap, vm.next_ref());
+        let reply = process::SuspendReply {
+            tag: reference,
+            sync: true,
+        };
+        (Some(reply), reference)
+    };
+
+    let signal = process::Signal::Suspend {
+        from: process.pid,
+        reply,
+    };
+    if !process::send_signal(vm, pid, signal) {
+        return Ok(atom!(BADARG));
+    }
+    suspending.insert(pid, count);
+    Ok(res)
+}
+
+/// Takes back one suspend the caller made with `erlang:suspend_process/1,2`.
+fn resume_process_1(vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) => pid,
+        _ => return Err(badarg!()),
+    };
+
+    let suspending = &mut process.local_data_mut().suspending;
+    match suspending.get_mut(&pid) {
+        Some(count) if *count > 1 => *count -= 1,
+        Some(_) => {
+            suspending.remove(&pid);
+        }
+        None => return Err(badarg!()),
+    }
+
+    let signal = process::Signal::Resume {
+        from: process.pid,
+        count: 1,
+    };
+    if !process::send_signal(vm, pid, signal) {
+        // it's gone, so are its suspends
+        suspending.remove(&pid);
+        return Err(badarg!());
+    }
+    Ok(atom!(TRUE))
+}
+
+/// Handles signals on behalf of a process that's running on a dirty scheduler. Only the
+/// erts_dirty_process_signal_handler process may call this.
+fn erts_internal_dirty_process_handle_signals_1(
+    vm: &Machine,
+    process: &RcProcess,
+    args: &[Term],
+) -> Result {
+    use std::sync::atomic::Ordering;
+
+    if process.pid as usize != vm.dirty_signal_handler.load(Ordering::Relaxed) {
+        return Ok(atom!(NOTSUP));
+    }
+
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) => pid,
+        _ => return Ok(atom!(FALSE)),
+    };
+
+    let target = match vm.process_table.lock().get(pid) {
+        Some(target) => target,
+        None => return Ok(atom!(NOPROC)),
+    };
+
+    // back on a normal scheduler, it handles its own signals
+    if !target.is_running_dirty() {
+        return Ok(atom!(NORMAL));
+    }
+
+    target.handle_dirty_signals();
+    Ok(atom!(TRUE))
+}
+
+// FIXME: phash and phash2 are the same, and they don't match the ERTS ones. And they return 64 bit
+// vals instead of max 32 bit ones. *shrug*
+
+fn phash_2(_vm: &Machine, process: &RcProcess, args: &[Term]) -> Result {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
     let heap = &process.context_mut().heap;
     let mut hasher = DefaultHasher::new();
     args[0].hash(&mut hasher);
@@ -1733,4 +2330,863 @@ mod tests {
 
         assert_eq!(res, Ok(Term::int(2)));
     }
+
+    #[test]
+    fn test_garbage_collect_1() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(MAJOR)];
+        let res = garbage_collect_1(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert_eq!(process.local_data().gc.collections, 1);
+
+        let args = vec![atom!(VALUE)];
+        let res = garbage_collect_1(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_erts_internal_request_system_task_3() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context_mut().heap;
+
+        // the process doesn't exist, so we get an answer right away
+        let request = tup3!(heap, atom!(GARBAGE_COLLECT), Term::int(1), atom!(MAJOR));
+        let args = vec![Term::pid(9999), atom!(INHERIT), request];
+        let res = erts_internal_request_system_task_3(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(OK)));
+
+        let msg = process.local_data_mut().mailbox.receive().unwrap();
+        assert_eq!(
+            msg,
+            tup3!(heap, atom!(GARBAGE_COLLECT), Term::int(1), atom!(FALSE))
+        );
+
+        let request = tup3!(heap, atom!(GARBAGE_COLLECT), Term::int(1), atom!(VALUE));
+        let args = vec![Term::pid(process.pid), atom!(INHERIT), request];
+        let res = erts_internal_request_system_task_3(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_bif_erlang_halt_2_badarg() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context_mut().heap;
+
+        let opts = cons!(heap, tup2!(heap, atom!(FLUSH), atom!(OK)), Term::nil());
+        let res = bif_erlang_halt_2(&vm, &process, &[Term::int(0), opts]);
+        assert_eq!(res, Err(badarg!()));
+
+        let opts = cons!(heap, atom!(FLUSH), Term::nil());
+        let res = bif_erlang_halt_2(&vm, &process, &[Term::int(0), opts]);
+        assert_eq!(res, Err(badarg!()));
+
+        let res = bif_erlang_halt_1(&vm, &process, &[Term::int(-1)]);
+        assert_eq!(res, Err(badarg!()));
+
+        let res = bif_erlang_halt_1(&vm, &process, &[atom!(OK)]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_suspend_and_resume_process() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context_mut().heap;
+        let pid = Term::pid(target.pid);
+
+        let args = [Term::pid(process.pid), Term::nil()];
+        let res = erts_internal_suspend_process_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(BADARG)));
+        let opts = cons!(heap, atom!(OK), Term::nil());
+        let res = erts_internal_suspend_process_2(&vm, &process, &[pid, opts]);
+        assert_eq!(res, Ok(atom!(BADARG)));
+
+        let opts = cons!(heap, atom!(ASYNCHRONOUS), Term::nil());
+        let res = erts_internal_suspend_process_2(&vm, &process, &[pid, opts]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        let opts = cons!(heap, atom!(UNLESS_SUSPENDING), opts);
+        let res = erts_internal_suspend_process_2(&vm, &process, &[pid, opts]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        target.process_incoming().unwrap();
+        assert!(target.is_suspended());
+
+        assert_eq!(resume_process_1(&vm, &process, &[pid]), Ok(atom!(TRUE)));
+        target.process_incoming().unwrap();
+        assert!(!target.is_suspended());
+
+        // we're not suspending it anymore
+        assert_eq!(resume_process_1(&vm, &process, &[pid]), Err(badarg!()));
+    }
+
+    #[test]
+    fn test_synchronous_suspend_reply() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+        let pid = Term::pid(target.pid);
+
+        let args = [pid, Term::nil()];
+        let reference = erts_internal_suspend_process_2(&vm, &process, &args).unwrap();
+        assert!(reference.is_ref());
+
+        // no reply until the suspend takes effect
+        target.process_incoming().unwrap();
+        assert_eq!(process.receive(), Ok(None));
+
+        target.reply_suspended(&vm);
+        let reply = process.receive().unwrap().unwrap();
+        let reply = Tuple::cast_from(&reply).unwrap();
+        assert_eq!(reply[0], reference);
+        assert_eq!(reply[1], atom!(TRUE));
+    }
+
+    #[test]
+    fn test_erts_internal_scheduler_wall_time_1() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let res = scheduler_wall_time_1(&vm, &process, &[Term::int(1)]);
+        assert_eq!(res, Err(badarg!()));
+
+        let res = scheduler_wall_time_1(&vm, &process, &[atom!(TRUE)]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+        assert!(vm.stats.scheduler_wall_time(false).is_some());
+
+        let res = scheduler_wall_time_1(&vm, &process, &[atom!(FALSE)]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert_eq!(vm.stats.scheduler_wall_time(false), None);
+    }
+
+    #[test]
+    fn test_dirty_bif_schedules_trap() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let bif = dirty!(Io, erts_debug::dirty_io_2);
+        let args = vec![atom!(SCHEDULER), atom!(TYPE)];
+        let res = bif(&vm, &process, &args);
+        assert_eq!(res, Err(Exception::new(Reason::YIELD)));
+
+        let context = process.context();
+        let trap = context.trap.as_ref().unwrap();
+        assert_eq!(trap.dirty, Some(crate::scheduler::Dirty::Io));
+        assert_eq!(trap.arity, 2);
+        assert_eq!(&context.x[0..2], &args[..]);
+
+        // run inline, we're on a normal scheduler
+        let res = (trap.bif)(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(NORMAL)));
+    }
+
+    #[test]
+    fn test_erts_internal_dirty_process_handle_signals_1() {
+        use std::sync::atomic::Ordering;
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let handler = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+        let args = vec![Term::pid(target.pid)];
+
+        // only the signal handler may call it
+        let res = erts_internal_dirty_process_handle_signals_1(&vm, &target, &args);
+        assert_eq!(res, Ok(atom!(NOTSUP)));
+
+        vm.dirty_signal_handler
+            .store(handler.pid as usize, Ordering::Relaxed);
+        let res = erts_internal_dirty_process_handle_signals_1(&vm, &handler, &args);
+        assert_eq!(res, Ok(atom!(NORMAL)));
+
+        let noproc = vec![Term::pid(9999)];
+        let res = erts_internal_dirty_process_handle_signals_1(&vm, &handler, &noproc);
+        assert_eq!(res, Ok(atom!(NOPROC)));
+
+        target.running_dirty.store(true, Ordering::Release);
+        process::send_signal(
+            &vm,
+            target.pid,
+            process::Signal::Exit {
+                from: handler.pid,
+                reason: Exception::with_value(Reason::EXC_EXIT, atom!(KILL)),
+                kind: process::ExitKind::Exit,
+            },
+        );
+        // the handler got asked to take care of the signal
+        assert!(handler.local_data_mut().signal_queue.has_messages());
+
+        let res = erts_internal_dirty_process_handle_signals_1(&vm, &handler, &args);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert!(target.has_pending_exit());
+        assert!(!target.is_exiting());
+    }
+
+    #[test]
+    fn test_process_flag_min_bin_vheap_size() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(MIN_BIN_VHEAP_SIZE), Term::int(100_000)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(Term::int(46422)));
+
+        let gc = &process.local_data().gc;
+        assert_eq!(gc.min_bin_vheap_size, 100_000 * process::gc::WORD_SIZE);
+        assert!(gc.bin_vheap_size >= gc.min_bin_vheap_size);
+
+        let args = vec![atom!(MIN_BIN_VHEAP_SIZE), Term::int(-1)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_memory() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context_mut().heap;
+
+        let total = info::memory_1(&vm, &process, &[atom!(TOTAL)]).unwrap();
+        let processes = info::memory_1(&vm, &process, &[atom!(PROCESSES)]).unwrap();
+        let system = info::memory_1(&vm, &process, &[atom!(SYSTEM)]).unwrap();
+        assert!(processes.to_int().unwrap() > 0);
+        assert!(system.to_int().unwrap() > 0);
+        assert_eq!(
+            total.to_int().unwrap(),
+            processes.to_int().unwrap() + system.to_int().unwrap()
+        );
+
+        let list = cons!(heap, atom!(ATOM), cons!(heap, atom!(ATOM_USED), Term::nil()));
+        let res = info::memory_1(&vm, &process, &[list]).unwrap();
+        let items = Cons::cast_from(&res).unwrap();
+        assert_eq!(items.iter().count(), 2);
+
+        let res = info::memory_0(&vm, &process, &[]).unwrap();
+        let items = Cons::cast_from(&res).unwrap();
+        assert_eq!(items.iter().count(), 9);
+
+        let res = info::memory_1(&vm, &process, &[atom!(OK)]);
+        assert_eq!(res, Err(badarg!()));
+        let list = cons!(heap, atom!(ATOM), cons!(heap, Term::int(1), Term::nil()));
+        let res = info::memory_1(&vm, &process, &[list]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_spawn_opts() {
+        use process::SpawnFlag;
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        let list = Cons::from_iter(
+            vec![
+                atom!(LINK),
+                tup2!(heap, atom!(MONITOR), Term::nil()),
+                tup2!(heap, atom!(PRIORITY), atom!(HIGH)),
+                tup2!(heap, atom!(MIN_HEAP_SIZE), Term::int(1000)),
+                tup2!(heap, atom!(FULLSWEEP_AFTER), Term::int(0)),
+                tup2!(heap, atom!(MESSAGE_QUEUE_DATA), atom!(OFF_HEAP)),
+            ]
+            .into_iter(),
+            heap,
+        );
+        let opts = spawn_opts(&vm, list).unwrap();
+        assert_eq!(
+            opts.flags,
+            SpawnFlag::LINK | SpawnFlag::MONITOR | SpawnFlag::OFF_HEAP_MSGQ
+        );
+        assert_eq!(opts.priority, Some(Priority::High));
+        assert_eq!(opts.min_heap_size, Some(1000 * process::gc::WORD_SIZE));
+        assert_eq!(opts.fullsweep_after, Some(0));
+        assert_eq!(opts.monitor_alias, None);
+        assert_eq!(opts.monitor_tag, None);
+
+        let monitor_opts = cons!(
+            heap,
+            tup2!(heap, atom!(ALIAS), atom!(DEMONITOR)),
+            cons!(heap, tup2!(heap, atom!(TAG), atom!(OK)), Term::nil())
+        );
+        let list = cons!(heap, tup2!(heap, atom!(MONITOR), monitor_opts), Term::nil());
+        let opts = spawn_opts(&vm, list).unwrap();
+        assert_eq!(opts.flags, SpawnFlag::MONITOR);
+        assert_eq!(opts.monitor_alias, Some(process::AliasKind::Demonitor));
+        assert_eq!(opts.monitor_tag, Some(atom!(OK)));
+
+        // the max heap size can't be smaller than the min heap size
+        let list = Cons::from_iter(
+            vec![
+                tup2!(heap, atom!(MIN_HEAP_SIZE), Term::int(1000)),
+                tup2!(heap, atom!(MAX_HEAP_SIZE), Term::int(100)),
+            ]
+            .into_iter(),
+            heap,
+        );
+        assert!(spawn_opts(&vm, list).is_err());
+
+        for opt in vec![
+            atom!(OK),
+            tup2!(heap, atom!(MONITOR), atom!(OK)),
+            tup2!(
+                heap,
+                atom!(MONITOR),
+                cons!(heap, tup2!(heap, atom!(ALIAS), atom!(REPLY)), Term::nil())
+            ),
+            tup2!(heap, atom!(PRIORITY), atom!(MEDIUM)),
+            tup2!(heap, atom!(FULLSWEEP_AFTER), Term::int(-1)),
+            tup2!(heap, atom!(OK), Term::int(1)),
+        ] {
+            let list = cons!(heap, opt, Term::nil());
+            assert!(spawn_opts(&vm, list).is_err());
+        }
+        assert!(spawn_opts(&vm, atom!(OK)).is_err());
+    }
+
+    #[test]
+    fn test_spawn_improper_args() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        let args = cons!(heap, Term::int(1), Term::int(2));
+        let opts = process::SpawnOpts::default();
+        let res = process::spawn(&vm, &process, module, atom::START, args, opts);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_process_flag_message_queue_data() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(MESSAGE_QUEUE_DATA), atom!(OFF_HEAP)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(ON_HEAP)));
+
+        let args = vec![atom!(MESSAGE_QUEUE_DATA), atom!(ON_HEAP)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(OFF_HEAP)));
+
+        let args = vec![atom!(MESSAGE_QUEUE_DATA), atom!(OK)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_process_flag_priority() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(PRIORITY), atom!(HIGH)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(NORMAL)));
+        assert_eq!(process.priority(), Priority::High);
+
+        let args = vec![atom!(PRIORITY), atom!(LOW)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(HIGH)));
+
+        let args = vec![atom!(PRIORITY), atom!(MEDIUM)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+        assert_eq!(process.priority(), Priority::Low);
+    }
+
+    #[test]
+    fn test_process_flag_save_calls() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(SAVE_CALLS), Term::int(2)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(Term::int(0)));
+
+        let mfa = module::MFA(atom::ERLANG, atom::APPLY, 3);
+        process.save_call(process::SavedCall::Send);
+        process.save_call(process::SavedCall::Call(mfa));
+        process.save_call(process::SavedCall::Receive);
+
+        let heap = &process.context().heap;
+        let args = vec![Term::pid(process.pid), atom!(LAST_CALLS)];
+        let res = info::process_info_2(&vm, &process, &args);
+        let call = tup3!(heap, atom!(ERLANG), atom!(APPLY), Term::int(3));
+        let calls = cons!(heap, call, cons!(heap, atom!(RECEIVE), Term::nil()));
+        assert_eq!(res, Ok(tup2!(heap, atom!(LAST_CALLS), calls)));
+
+        let args = vec![atom!(SAVE_CALLS), Term::int(0)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(Term::int(2)));
+        let args = vec![Term::pid(process.pid), atom!(LAST_CALLS)];
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(LAST_CALLS), atom!(FALSE))));
+    }
+
+    #[test]
+    fn test_process_flag_sensitive() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        process.set_save_calls(10);
+        process.local_data_mut().mailbox.send(Term::int(1));
+
+        let args = vec![atom!(SENSITIVE), atom!(TRUE)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        process.save_call(process::SavedCall::Send);
+        assert!(process
+            .local_data()
+            .saved_calls
+            .as_ref()
+            .unwrap()
+            .calls
+            .is_empty());
+
+        let heap = &process.context().heap;
+        let args = vec![Term::pid(process.pid), atom!(MESSAGES)];
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(MESSAGES), Term::nil())));
+
+        let args = vec![atom!(SENSITIVE), atom!(OK)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_process_flag_gc() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(MIN_HEAP_SIZE), Term::int(10_000)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(Term::int(vm.config.min_heap_size as i32)));
+        let gc = &process.local_data().gc;
+        assert_eq!(gc.min_heap_size, 10_000 * process::gc::WORD_SIZE);
+        assert!(gc.threshold >= gc.min_heap_size);
+
+        let args = vec![atom!(FULLSWEEP_AFTER), Term::int(10)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(
+            res,
+            Ok(Term::int(process::gc::DEFAULT_FULLSWEEP_AFTER as i32))
+        );
+        assert_eq!(process.local_data().gc.fullsweep_after, 10);
+
+        let args = vec![atom!(MIN_HEAP_SIZE), Term::int(-1)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_process_flag_error_handler() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let args = vec![atom!(ERROR_HANDLER), atom!(OK)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(ERROR_HANDLER)));
+        assert_eq!(process.local_data().error_handler, atom::OK);
+
+        let args = vec![atom!(ERROR_HANDLER), Term::int(1)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+
+        let args = vec![atom!(OK), atom!(TRUE)];
+        let res = bif_erlang_process_flag_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_alias() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let sender = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let alias = bif_erlang_alias_0(&vm, &process, &[]).unwrap();
+        process::send_message(&vm, sender.pid, alias, atom!(OK)).unwrap();
+        process.process_incoming().unwrap();
+        assert_eq!(process.local_data().mailbox.len(), 1);
+
+        // messages to inactive aliases get dropped
+        let res = bif_erlang_unalias_1(&vm, &process, &[alias]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        process::send_message(&vm, sender.pid, alias, atom!(OK)).unwrap();
+        process.process_incoming().unwrap();
+        assert_eq!(process.local_data().mailbox.len(), 1);
+
+        let res = bif_erlang_unalias_1(&vm, &process, &[alias]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+        let res = bif_erlang_unalias_1(&vm, &process, &[atom!(OK)]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_alias_reply() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let sender = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        // only the reply gets through
+        let opts = cons!(heap, atom!(REPLY), Term::nil());
+        let alias = bif_erlang_alias_1(&vm, &process, &[opts]).unwrap();
+        process::send_message(&vm, sender.pid, alias, atom!(OK)).unwrap();
+        process::send_message(&vm, sender.pid, alias, atom!(OK)).unwrap();
+        process.process_incoming().unwrap();
+        assert_eq!(process.local_data().mailbox.len(), 1);
+        assert!(process.local_data().aliases.is_empty());
+
+        let opts = cons!(heap, atom!(OK), Term::nil());
+        let res = bif_erlang_alias_1(&vm, &process, &[opts]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_monitor_alias() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        let opts = cons!(
+            heap,
+            tup2!(heap, atom!(ALIAS), atom!(DEMONITOR)),
+            Term::nil()
+        );
+        let args = vec![atom!(PROCESS), Term::pid(target.pid), opts];
+        let reference = bif_erlang_monitor_3(&vm, &process, &args).unwrap();
+        assert_eq!(process.local_data().aliases.len(), 1);
+
+        // demonitoring takes the alias with it
+        let res = bif_erlang_demonitor_1(&vm, &process, &[reference]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert!(process.local_data().aliases.is_empty());
+
+        // and so does the monitor triggering
+        let args = vec![atom!(PROCESS), Term::pid(9999), opts];
+        let reference = bif_erlang_monitor_3(&vm, &process, &args).unwrap();
+        process.process_incoming().unwrap();
+        assert_eq!(process.local_data().mailbox.len(), 1);
+        assert!(process.local_data().aliases.is_empty());
+        let res = bif_erlang_unalias_1(&vm, &process, &[reference]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        let opts = cons!(heap, tup2!(heap, atom!(ALIAS), atom!(REPLY)), Term::nil());
+        let args = vec![atom!(PROCESS), Term::pid(target.pid), opts];
+        let res = bif_erlang_monitor_3(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+
+        // a tag replaces 'DOWN'
+        let opts = cons!(heap, tup2!(heap, atom!(TAG), atom!(OK)), Term::nil());
+        let args = vec![atom!(PROCESS), Term::pid(9999), opts];
+        let reference = bif_erlang_monitor_3(&vm, &process, &args).unwrap();
+        let down = tup!(
+            heap,
+            atom!(OK),
+            reference,
+            atom!(PROCESS),
+            Term::pid(9999),
+            atom!(NOPROC)
+        );
+        assert_eq!(process.local_data().mailbox.iter().last(), Some(&down));
+        assert!(process.local_data().monitor_tags.is_empty());
+    }
+
+    #[test]
+    fn test_monitor() {
+        use process::{Monitored, Signal};
+
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+        let name = Atom::from("monitor_target");
+        let last = |process: &RcProcess| *process.local_data().mailbox.iter().last().unwrap();
+        let down = |reference: Term, kind: Term, object: Term, reason: Term| {
+            tup!(heap, atom!(DOWN_U), reference, kind, object, reason)
+        };
+
+        // targets that don't exist are reported right away
+        let args = vec![atom!(PROCESS), Term::pid(9999)];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        let expected = down(reference, atom!(PROCESS), Term::pid(9999), atom!(NOPROC));
+        assert_eq!(last(&process), expected);
+
+        let args = vec![atom!(PROCESS), Term::atom(name)];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        let object = tup2!(heap, Term::atom(name), atom!(NO_NODE_NO_HOST));
+        let expected = down(reference, atom!(PROCESS), object, atom!(NOPROC));
+        assert_eq!(last(&process), expected);
+
+        let object = tup2!(heap, Term::atom(name), Term::atom(Atom::from("other@host")));
+        let args = vec![atom!(PROCESS), object];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        let expected = down(reference, atom!(PROCESS), object, atom!(NOCONNECTION));
+        assert_eq!(last(&process), expected);
+
+        let args = vec![atom!(PORT), Term::port(9999)];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        let expected = down(reference, atom!(PORT), Term::port(9999), atom!(NOPROC));
+        assert_eq!(last(&process), expected);
+        assert_eq!(process.local_data().mailbox.len(), 4);
+        assert!(process.local_data().monitors.is_empty());
+
+        // and so there's nothing left to demonitor
+        let info = cons!(heap, atom!(INFO), Term::nil());
+        let res = bif_erlang_demonitor_2(&vm, &process, &[reference, info]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        // monitoring by name remembers the name
+        vm.process_registry.lock().register(name, target.clone());
+        let object = tup2!(heap, Term::atom(name), atom!(NO_NODE_NO_HOST));
+        let args = vec![atom!(PROCESS), object];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        let id = *reference.get_boxed_value::<process::Ref>().unwrap();
+        assert_eq!(
+            process.local_data().monitors.get(&id),
+            Some(&Monitored::Name(target.pid, name))
+        );
+        target.process_incoming().unwrap();
+        assert_eq!(target.local_data().lt_monitors, vec![(process.pid, id)]);
+
+        let args = vec![Term::pid(process.pid), atom!(MONITORS)];
+        let res = info::process_info_2(&vm, &process, &args);
+        let expected = tup2!(
+            heap,
+            atom!(MONITORS),
+            cons!(heap, tup2!(heap, atom!(PROCESS), object), Term::nil())
+        );
+        assert_eq!(res, Ok(expected));
+
+        // a DOWN message that was already on its way is dropped after demonitoring
+        let res = bif_erlang_demonitor_2(&vm, &process, &[reference, info]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        process::send_signal(
+            &vm,
+            process.pid,
+            Signal::MonitorDown {
+                from: target.pid,
+                reason: Exception::with_value(Reason::EXC_EXIT, atom!(NORMAL)),
+                reference: id,
+            },
+        );
+        process.process_incoming().unwrap();
+        assert_eq!(process.local_data().mailbox.len(), 4);
+        target.process_incoming().unwrap();
+        assert!(target.local_data().lt_monitors.is_empty());
+
+        let res = bif_erlang_monitor_2(&vm, &process, &[atom!(PORT), Term::pid(target.pid)]);
+        assert_eq!(res, Err(badarg!()));
+
+        // the time offset never changes, the monitor just sits there until it's demonitored
+        let args = vec![atom!(TIME_OFFSET), atom!(CLOCK_SERVICE)];
+        let reference = bif_erlang_monitor_2(&vm, &process, &args).unwrap();
+        assert!(reference.is_ref());
+        let res = bif_erlang_demonitor_2(&vm, &process, &[reference, info]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert_eq!(process.local_data().mailbox.len(), 4);
+
+        let args = vec![atom!(TIME_OFFSET), Term::pid(target.pid)];
+        let res = bif_erlang_monitor_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_erts_internal_process_flag() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let target = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let heap = &process.context().heap;
+        // the target replies through the current machine
+        Machine::set_current(vm.machine().clone());
+
+        // the target sets the flag itself once it handles the signal, and replies
+        let args = vec![Term::pid(target.pid), atom!(SAVE_CALLS), Term::int(5)];
+        let reference = erts_internal_process_flag_3(&vm, &process, &args).unwrap();
+        assert!(reference.is_ref());
+        assert!(target.local_data().saved_calls.is_none());
+        target.process_incoming().unwrap();
+        assert_eq!(target.local_data().saved_calls.as_ref().unwrap().len, 5);
+        assert!(process.local_data().saved_calls.is_none());
+        process.process_incoming().unwrap();
+        let reply = process.local_data_mut().mailbox.receive();
+        assert_eq!(reply, Some(tup2!(heap, reference, Term::int(0))));
+
+        // setting it on ourselves replies right away
+        let args = vec![Term::pid(process.pid), atom!(SAVE_CALLS), Term::int(3)];
+        let res = erts_internal_process_flag_3(&vm, &process, &args);
+        assert_eq!(res, Ok(Term::int(0)));
+        assert_eq!(process.local_data().saved_calls.as_ref().unwrap().len, 3);
+
+        // no other flag can be set from the outside
+        let args = vec![Term::pid(target.pid), atom!(TRAP_EXIT), atom!(TRUE)];
+        let res = erts_internal_process_flag_3(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+        assert!(!target.local_data().flags.contains(process::Flag::TRAP_EXIT));
+
+        let args = vec![Term::pid(9999), atom!(SAVE_CALLS), Term::int(1)];
+        let res = erts_internal_process_flag_3(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_process_info() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let other = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        other.local_data_mut().lt_monitors.push((process.pid, 1));
+        other.send_message(process.pid, atom!(OK));
+        other.process_incoming().unwrap();
+
+        let items = cons!(
+            heap,
+            atom!(MESSAGES),
+            cons!(
+                heap,
+                atom!(MONITORED_BY),
+                cons!(heap, atom!(GROUP_LEADER), Term::nil())
+            )
+        );
+        let args = vec![Term::pid(other.pid), items];
+        let res = info::process_info_2(&vm, &process, &args);
+        let expected = cons!(
+            heap,
+            tup2!(heap, atom!(MESSAGES), cons!(heap, atom!(OK), Term::nil())),
+            cons!(
+                heap,
+                tup2!(
+                    heap,
+                    atom!(MONITORED_BY),
+                    cons!(heap, Term::pid(process.pid), Term::nil())
+                ),
+                cons!(heap, tup2!(heap, atom!(GROUP_LEADER), Term::pid(0)), Term::nil())
+            )
+        );
+        assert_eq!(res, Ok(expected));
+
+        // the magic ref sticks to the process
+        let args = vec![Term::pid(other.pid), atom!(MAGIC_REF)];
+        let first = info::process_info_2(&vm, &process, &args);
+        assert_eq!(first, info::process_info_2(&vm, &process, &args));
+
+        // a process that isn't running any code yet doesn't have a location
+        let args = vec![Term::pid(other.pid), atom!(CURRENT_LOCATION)];
+        let res = info::process_info_2(&vm, &process, &args);
+        let expected = tup2!(heap, atom!(CURRENT_LOCATION), atom!(UNDEFINED));
+        assert_eq!(res, Ok(expected));
+        let args = vec![Term::pid(other.pid), atom!(CURRENT_STACKTRACE)];
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(CURRENT_STACKTRACE), Term::nil())));
+
+        // status comes from the scheduler
+        let args = vec![Term::pid(other.pid), atom!(STATUS)];
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(STATUS), atom!(RUNNABLE))));
+        other.set_status(crate::scheduler::Status::Waiting);
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(STATUS), atom!(WAITING))));
+        other.set_status(crate::scheduler::Status::Running);
+        let res = info::process_info_2(&vm, &process, &args);
+        assert_eq!(res, Ok(tup2!(heap, atom!(STATUS), atom!(RUNNING))));
+
+        // unregistered processes don't have a registered_name entry
+        let args = vec![Term::pid(other.pid)];
+        let res = info::process_info_1(&vm, &process, &args).unwrap();
+        let items = Cons::cast_from(&res).unwrap().iter().count();
+        assert_eq!(items, 16);
+
+        let args = vec![Term::pid(process.pid + 100)];
+        let res = info::process_info_1(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(UNDEFINED)));
+    }
+
+    #[test]
+    fn test_bump_reductions_1() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+        context.reds = process::CONTEXT_REDS;
+
+        let res = bif_erlang_bump_reductions_1(&vm, &process, &[Term::int(100)]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert_eq!(context.reductions, 100);
+        assert_eq!(context.reds, process::CONTEXT_REDS - 100);
+
+        // capped to a single time slice
+        let res = bif_erlang_bump_reductions_1(&vm, &process, &[Term::int(1_000_000)]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert_eq!(context.reductions, 100 + process::CONTEXT_REDS);
+        assert_eq!(context.reds, 0);
+
+        let res = bif_erlang_bump_reductions_1(&vm, &process, &[Term::int(-1)]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_erts_internal_purge_module_2() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        // there's no old code to purge
+        let args = vec![atom!(ERLANG), atom!(PREPARE)];
+        let res = load::erts_internal_purge_module_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(FALSE)));
+        let args = vec![atom!(ERLANG), atom!(COMPLETE)];
+        let res = load::erts_internal_purge_module_2(&vm, &process, &args);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        let args = vec![atom!(ERLANG), atom!(OK)];
+        let res = load::erts_internal_purge_module_2(&vm, &process, &args);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_erts_internal_release_literal_area_switch_0() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let res = load::erts_internal_release_literal_area_switch_0(&vm, &process, &[]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+
+        vm.literal_areas.lock().queue.push_back(Heap::new());
+        let res = load::erts_internal_release_literal_area_switch_0(&vm, &process, &[]);
+        assert_eq!(res, Ok(atom!(TRUE)));
+        assert!(vm.literal_areas.lock().active.is_some());
+
+        // the area is done with, release it
+        let res = load::erts_internal_release_literal_area_switch_0(&vm, &process, &[]);
+        assert_eq!(res, Ok(atom!(FALSE)));
+        assert!(vm.literal_areas.lock().active.is_none());
+    }
 }
@@ -5,6 +5,7 @@ use crate::exception::Exception;
 use crate::process::RcProcess;
 use crate::value::{self, CastFrom, CastInto, Cons, Term, Tuple, Variant};
 use crate::vm;
+use std::cmp;
 
 pub fn split_binary_2(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     // bin, pos
@@ -207,26 +208,7 @@ pub fn split_3(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Re
     // efficient one.
 
     // subject = binary
-    let (bin, offs, bitoffs, size, bitsize) = match args[0].get_boxed_header() {
-        Ok(value::BOXED_BINARY) => {
-            let value = &args[0].get_boxed_value::<RcBinary>().unwrap();
-            (*value, 0, 0, value.data.len(), 0)
-        }
-        Ok(value::BOXED_SUBBINARY) => {
-            let value = &args[0].get_boxed_value::<SubBinary>().unwrap();
-            (
-                &value.original,
-                value.offset,
-                value.bit_offset,
-                value.size,
-                value.bitsize,
-            )
-        }
-        _ => unreachable!(),
-    };
-    if bitoffs > 0 {
-        unimplemented!("Unaligned bitoffs not implemented");
-    }
+    let (bin, offs, size) = subject_parts(&args[0]);
     let subject = &bin.data[offs..offs + size];
 
     // pattern = binary | [binary] | compiled
@@ -300,44 +282,15 @@ pub fn split_3(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Re
     }
 
     if global {
-        let mut finder = regex.find_iter(subject);
-        let mut last = 0;
-        let mut acc = Vec::new();
-
-        loop {
-            // based on regex split code, but we needed offsets instead of slices
-            match finder.next() {
-                None => {
-                    if last >= subject.len() {
-                        break;
-                    } else {
-                        acc.push(SubBinary::new(
-                            bin.clone(),
-                            (subject.len() - last) * 8,
-                            (offs + last) * 8,
-                            false,
-                        ));
-
-                        last = subject.len();
-                    }
-                }
-                Some(m) => {
-                    acc.push(SubBinary::new(
-                        bin.clone(),
-                        (m.start() - last) * 8,
-                        (offs + last) * 8,
-                        false,
-                    ));
-                    last = m.end();
-                }
-            }
-        }
-
-        let res = acc.into_iter().rev().fold(Term::nil(), |acc, val| {
-            cons!(heap, Term::subbinary(heap, val), acc)
-        });
-        Ok(res)
+        let pattern = match regex {
+            Cow::Borrowed(..) => args[1],
+            Cow::Owned(regex) => Term::regex(heap, regex),
+        };
+        split_global(process, args[0], pattern, 0, Term::nil())
     } else {
+        process
+            .context_mut()
+            .bump_reductions(size / bitstring::BYTES_PER_RED);
         let res = match regex.find(subject) {
             None => cons!(heap, args[0], Term::nil()),
             Some(m) => {
@@ -359,6 +312,91 @@ pub fn split_3(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Re
     }
 }
 
+/// Returns the binary backing `subject`, along with the offset and size of the subject in bytes.
+fn subject_parts(subject: &Term) -> (&RcBinary, usize, usize) {
+    let (bin, offs, bitoffs, size) = match subject.get_boxed_header() {
+        Ok(value::BOXED_BINARY) => {
+            let value = subject.get_boxed_value::<RcBinary>().unwrap();
+            (value, 0, 0, value.data.len())
+        }
+        Ok(value::BOXED_SUBBINARY) => {
+            let value = subject.get_boxed_value::<SubBinary>().unwrap();
+            (&value.original, value.offset, value.bit_offset, value.size)
+        }
+        _ => unreachable!(),
+    };
+    if bitoffs > 0 {
+        unimplemented!("Unaligned bitoffs not implemented");
+    }
+    (bin, offs, size)
+}
+
+/// Splits `subject` on all the matches of the compiled `pattern` past `start`, prepending the
+/// parts to `acc`. Yields once the reductions run out.
+fn split_global(
+    process: &RcProcess,
+    subject: Term,
+    pattern: Term,
+    start: usize,
+    mut acc: Term,
+) -> bif::Result {
+    let context = process.context_mut();
+    let heap = &context.heap;
+    let regex = regex::bytes::Regex::cast_from(&pattern)?;
+    let (bin, offs, size) = subject_parts(&subject);
+    let data = &bin.data[offs..offs + size];
+
+    let max_bytes = cmp::max(context.reds, 1) * bitstring::BYTES_PER_RED;
+    let mut last = start;
+
+    loop {
+        if last - start >= max_bytes {
+            // out of reductions, continue from here on the next run
+            let last = Term::uint(heap, last as u32);
+            bif_yield!(context, split_global_4, subject, pattern, last, acc);
+        }
+
+        // based on regex split code, but we needed offsets instead of slices
+        match regex.find_at(data, last) {
+            None => {
+                if last < size {
+                    let part =
+                        SubBinary::new(bin.clone(), (size - last) * 8, (offs + last) * 8, false);
+                    acc = cons!(heap, Term::subbinary(heap, part), acc);
+                }
+                break;
+            }
+            // splitting on an empty pattern is invalid
+            Some(m) if m.start() == m.end() => return Err(badarg!()),
+            Some(m) => {
+                let part = SubBinary::new(
+                    bin.clone(),
+                    (m.start() - last) * 8,
+                    (offs + last) * 8,
+                    false,
+                );
+                acc = cons!(heap, Term::subbinary(heap, part), acc);
+                last = m.end();
+            }
+        }
+    }
+    // the parts were accumulated in reverse
+    let parts = Cons::cast_from(&acc).map(|cons| cons.iter().copied().collect::<Vec<_>>());
+    let res = parts
+        .unwrap_or_default()
+        .into_iter()
+        .fold(Term::nil(), |res, part| cons!(heap, part, res));
+
+    context.bump_reductions((last - start) / bitstring::BYTES_PER_RED);
+    Ok(res)
+}
+
+/// Continuation of a global `split` that ran out of reductions.
+fn split_global_4(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let start = args[2].to_uint().ok_or_else(|| badarg!())? as usize;
+    split_global(process, args[0], args[1], start, args[3])
+}
+
 pub fn match_2(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     match_3(vm, process, &[args[0], args[1], Term::nil()])
 }
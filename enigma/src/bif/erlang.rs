@@ -2,7 +2,7 @@ use crate::atom;
 use crate::bif;
 use crate::bitstring;
 use crate::exception::{Exception, Reason};
-use crate::process::RcProcess;
+use crate::process::{self, RcProcess};
 use crate::value::{self, CastFrom, CastInto, Cons, Term, Tuple, Variant};
 use crate::vm::Machine;
 use lexical;
@@ -164,8 +164,10 @@ pub fn list_to_atom_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif
     // BIF_RET(res);
     let list = Cons::cast_from(&args[0])?;
     let string = value::cons::unicode_list_to_buf(list, atom::MAX_ATOM_CHARS)?;
-    let atom = atom::Atom::from(string);
-    Ok(Term::atom(atom))
+    match atom::Atom::try_from_str(&string) {
+        Some(atom) => Ok(Term::atom(atom)),
+        None => Err(Exception::new(Reason::EXC_SYSTEM_LIMIT)),
+    }
 }
 
 /// conditionally convert a list of ascii integers to an atom
@@ -581,10 +583,10 @@ pub fn binary_to_atom_2(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> b
 
     if let Some(bytes) = args[0].to_bytes() {
         return match std::str::from_utf8(bytes) {
-            Ok(string) => {
-                let atom = atom::Atom::from(string);
-                Ok(Term::atom(atom))
-            }
+            Ok(string) => match atom::Atom::try_from_str(string) {
+                Some(atom) => Ok(Term::atom(atom)),
+                None => Err(Exception::new(Reason::EXC_SYSTEM_LIMIT)),
+            },
             Err(_) => Err(badarg!()),
         };
     }
@@ -626,17 +628,69 @@ pub fn atom_to_binary_2(_vm: &Machine, process: &RcProcess, args: &[Term]) -> bi
 }
 
 pub fn pid_to_list_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
-    match args[0].into_variant() {
-        Variant::Pid(i) => {
-            let string = format!("<0.0.{}>", i); // TODO: proper format
-            let heap = &process.context_mut().heap;
+    if !args[0].is_pid() {
+        return Err(badarg!());
+    }
 
-            Ok(bitstring!(heap, string))
-        }
+    let string = format!("{}", args[0]);
+    let heap = &process.context_mut().heap;
+
+    Ok(bitstring!(heap, string))
+}
+
+pub fn port_to_list_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    if !args[0].is_port() {
+        return Err(badarg!());
+    }
+
+    let string = format!("{}", args[0]);
+    let heap = &process.context_mut().heap;
+
+    Ok(bitstring!(heap, string))
+}
+
+/// Parses the dot separated numbers out of a `<prefix>0.N.N>` list, as printed by pid_to_list and
+/// friends. The leading 0 is the node, only local identifiers can be parsed.
+fn parse_id(term: Term, prefix: &str, parts: usize) -> Result<Vec<u32>, Exception> {
+    let cons = Cons::cast_from(&term)?;
+    let string = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
+
+    if !string.starts_with(prefix) || !string.ends_with('>') {
+        return Err(badarg!());
+    }
+    let numbers = string[prefix.len()..string.len() - 1]
+        .split('.')
+        .map(|number| number.parse::<u32>().map_err(|_| badarg!()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match numbers.split_first() {
+        Some((0, rest)) if rest.len() == parts => Ok(rest.to_vec()),
         _ => Err(badarg!()),
     }
 }
 
+pub fn list_to_pid_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
+    let parts = parse_id(args[0], "<", 2)?;
+    let pid = process::table::from_parts(parts[0], parts[1]).ok_or_else(|| badarg!())?;
+    Ok(Term::pid(pid))
+}
+
+pub fn list_to_port_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
+    let parts = parse_id(args[0], "#Port<", 1)?;
+    Ok(Term::port(parts[0]))
+}
+
+pub fn list_to_ref_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let parts = parse_id(args[0], "#Ref<", 3)?;
+    // references are 64 bit wide, so the top word has to be empty
+    if parts[0] != 0 {
+        return Err(badarg!());
+    }
+    let reference = (u64::from(parts[1]) << 32) | u64::from(parts[2]);
+    let heap = &process.context_mut().heap;
+    Ok(Term::reference(heap, reference as process::Ref))
+}
+
 pub fn integer_to_list_1(_vm: &Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     match args[0].into_number() {
         Ok(value::Num::Integer(i)) => {
@@ -782,7 +836,7 @@ pub fn binary_to_integer_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -
 pub fn list_to_integer_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     // list to string
     let cons = Cons::cast_from(&args[0])?;
-    let string = value::cons::unicode_list_to_buf(cons, 2048)?;
+    let string = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
     match lexical::parse::<i32, _>(string) {
         Ok(i) => Ok(Term::int(i)),
         Err(err) => panic!("errored with {:?}", err), //TODO
@@ -798,7 +852,7 @@ pub fn string_list_to_integer_1(_vm: &Machine, process: &RcProcess, args: &[Term
         args[0].to_bytes().unwrap().to_owned()
     } else {
         let cons = Cons::cast_from(&args[0])?;
-        value::cons::unicode_list_to_buf(cons, 2048)?
+        value::cons::unicode_list_to_buf(cons, std::usize::MAX)?
             .as_bytes()
             .to_owned()
     };
@@ -836,7 +890,7 @@ pub fn string_list_to_integer_1(_vm: &Machine, process: &RcProcess, args: &[Term
 pub fn list_to_float_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     // list to string
     let cons = Cons::cast_from(&args[0])?;
-    let string = value::cons::unicode_list_to_buf(cons, 2048)?;
+    let string = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
     match lexical::parse::<f64, _>(string) {
         Ok(f) => Ok(Term::from(f)),
         Err(err) => panic!("errored with {:?}", err), //TODO
@@ -882,7 +936,7 @@ pub fn display_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Res
 
 pub fn display_string_1(_vm: &Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     let cons = Cons::cast_from(&args[0])?;
-    let string = value::cons::unicode_list_to_buf(cons, 2048)?;
+    let string = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
     print!("{}", string);
     Ok(atom!(TRUE))
 }
@@ -1630,4 +1684,80 @@ mod tests {
         let res = list_to_iodata(list);
         assert_eq!(Ok(vec![1, 2, 3, 0xAB, 0xCD, 0xEF]), res)
     }
+
+    #[test]
+    fn test_pid_to_list() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        let pid = Term::pid(process::table::from_parts(5, 2).unwrap());
+        let list = pid_to_list_1(&vm, &process, &[pid]).unwrap();
+        assert_eq!(list, bitstring!(heap, "<0.5.2>"));
+        assert_eq!(list_to_pid_1(&vm, &process, &[list]), Ok(pid));
+
+        for bad in &[
+            "<0.5>",
+            "<1.5.2>",
+            "<0.5.2",
+            "<0.32768.0>",
+            "<0.a.2>",
+            "0.5.2",
+        ] {
+            let res = list_to_pid_1(&vm, &process, &[bitstring!(heap, bad)]);
+            assert_eq!(res, Err(badarg!()));
+        }
+        assert_eq!(list_to_pid_1(&vm, &process, &[pid]), Err(badarg!()));
+    }
+
+    #[test]
+    fn test_port_and_ref_to_list() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        let port = Term::port(7);
+        let list = port_to_list_1(&vm, &process, &[port]).unwrap();
+        assert_eq!(list, bitstring!(heap, "#Port<0.7>"));
+        assert_eq!(list_to_port_1(&vm, &process, &[list]), Ok(port));
+
+        let reference = Term::reference(heap, (1 << 32) + 3);
+        let list = ref_to_list_1(&vm, &process, &[reference]).unwrap();
+        assert_eq!(list, bitstring!(heap, "#Ref<0.0.1.3>"));
+        assert_eq!(list_to_ref_1(&vm, &process, &[list]), Ok(reference));
+
+        let res = list_to_ref_1(&vm, &process, &[bitstring!(heap, "#Ref<0.1.1.3>")]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_too_long_atom_is_system_limit() {
+        let vm = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context().heap;
+
+        // 255 characters, but more than 255 bytes
+        let name = "é".repeat(atom::MAX_ATOM_CHARS);
+        let chars: Vec<_> = name.chars().map(|c| Term::int(c as i32)).collect();
+        let list = Cons::from_iter(chars.into_iter(), heap);
+        let res = list_to_atom_1(&vm, &process, &[list]);
+        assert_eq!(res, Ok(str_to_atom!(name.as_str())));
+        let binary = crate::bitstring::Binary::from(name.as_bytes().to_vec());
+        let binary = Term::binary(heap, binary);
+        let res = binary_to_atom_2(&vm, &process, &[binary, atom!(UTF8)]);
+        assert_eq!(res, Ok(str_to_atom!(name.as_str())));
+
+        let name = "a".repeat(atom::MAX_ATOM_CHARS + 1);
+        let chars: Vec<_> = name.chars().map(|c| Term::int(c as i32)).collect();
+        let list = Cons::from_iter(chars.into_iter(), heap);
+        let res = list_to_atom_1(&vm, &process, &[list]);
+        assert_eq!(res, Err(Exception::new(Reason::EXC_SYSTEM_LIMIT)));
+        let binary = crate::bitstring::Binary::from(name.as_bytes().to_vec());
+        let binary = Term::binary(heap, binary);
+        let res = binary_to_atom_2(&vm, &process, &[binary, atom!(UTF8)]);
+        assert_eq!(res, Err(Exception::new(Reason::EXC_SYSTEM_LIMIT)));
+    }
 }
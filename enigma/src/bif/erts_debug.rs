@@ -0,0 +1,163 @@
+//! Dirty scheduler test BIFs, used by the dirty BIF test suites.
+use crate::atom::{self, Atom};
+use crate::bif;
+use crate::exception::{Exception, Reason};
+use crate::process::{self, RcProcess};
+use crate::scheduler::{self, Dirty};
+use crate::value::{BigInt, Term, Variant};
+use crate::vm;
+use std::time::Duration;
+
+pub fn dirty_cpu_2(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    dirty_test(vm, process, atom::DIRTY_CPU, args[0], args[1])
+}
+
+pub fn dirty_io_2(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    dirty_test(vm, process, atom::DIRTY_IO, args[0], args[1])
+}
+
+pub fn dirty_3(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    match args[0].into_variant() {
+        Variant::Atom(atom::NORMAL) => dirty_test(vm, process, atom::NORMAL, args[1], args[2]),
+        Variant::Atom(atom::DIRTY_CPU) => {
+            scheduler::schedule(process, Some(Dirty::Cpu), dirty_cpu_2, &args[1..])
+        }
+        Variant::Atom(atom::DIRTY_IO) => {
+            scheduler::schedule(process, Some(Dirty::Io), dirty_io_2, &args[1..])
+        }
+        _ => Err(badarg!()),
+    }
+}
+
+/// Sends `{tag, self()}` to `to`.
+fn send_tagged(
+    vm: &vm::Machine,
+    process: &RcProcess,
+    to: Term,
+    tag: Atom,
+) -> Result<(), Exception> {
+    let heap = &process.context_mut().heap;
+    let msg = tup2!(heap, Term::atom(tag), Term::pid(process.pid));
+    process::send_message(vm, process.pid, to, msg)?;
+    Ok(())
+}
+
+fn ms_wait(ms: Term) -> Result<(), Exception> {
+    match ms.into_variant() {
+        Variant::Integer(ms) if ms >= 0 => {
+            std::thread::sleep(Duration::from_millis(ms as u64));
+            Ok(())
+        }
+        _ => Err(badarg!()),
+    }
+}
+
+/// `ty` is the type of scheduler the test was meant to run on: `normal`, `dirty_cpu` or
+/// `dirty_io`.
+fn dirty_test(
+    vm: &vm::Machine,
+    process: &RcProcess,
+    ty: Atom,
+    arg1: Term,
+    arg2: Term,
+) -> bif::Result {
+    let heap = &process.context_mut().heap;
+
+    match arg1.into_variant() {
+        Variant::Atom(atom::SCHEDULER) => {
+            if arg2 != atom!(TYPE) {
+                return Err(badarg!());
+            }
+            Ok(match scheduler::current() {
+                Some(dirty) => Term::atom(dirty.to_atom()),
+                None => atom!(NORMAL),
+            })
+        }
+        Variant::Atom(atom::ERROR) => {
+            let reason = match arg2.into_variant() {
+                Variant::Atom(atom::NOTSUP) => Reason::EXC_NOTSUP,
+                Variant::Atom(atom::UNDEF) => Reason::EXC_UNDEF,
+                Variant::Atom(atom::BADARITH) => Reason::EXC_BADARITH,
+                Variant::Atom(atom::NOPROC) => Reason::EXC_NOPROC,
+                Variant::Atom(atom::SYSTEM_LIMIT) => Reason::EXC_SYSTEM_LIMIT,
+                _ => Reason::EXC_BADARG,
+            };
+            Err(Exception::new(reason))
+        }
+        Variant::Atom(atom::COPY) => {
+            // we want this to be slow, not optimized away
+            let res = (0..1000).fold(Term::nil(), |acc, _| {
+                cons!(heap, arg2.deep_clone(heap), acc)
+            });
+            Ok(res)
+        }
+        Variant::Atom(atom::SEND) => {
+            send_tagged(vm, process, arg2, atom::OK)?;
+            Ok(atom!(OK))
+        }
+        Variant::Atom(atom::WAIT) => {
+            ms_wait(arg2)?;
+            Ok(atom!(OK))
+        }
+        Variant::Atom(atom::RESCHEDULE) => {
+            // Reschedule after decrementing the counter until we reach zero, switching scheduler
+            // types every time it's evenly divisible by 4. Odd counts are a badarg.
+            let n = match arg2.into_variant() {
+                Variant::Integer(n) if n >= 0 => n,
+                _ => return Err(badarg!()),
+            };
+            if n == 0 {
+                return Ok(atom!(OK));
+            }
+            if n % 2 != 0 {
+                return Err(badarg!());
+            }
+            let next = if n % 4 != 0 {
+                ty
+            } else {
+                match ty {
+                    atom::DIRTY_CPU => atom::DIRTY_IO,
+                    atom::DIRTY_IO => atom::NORMAL,
+                    _ => atom::DIRTY_CPU,
+                }
+            };
+            let n = Term::int(n - 1);
+            match next {
+                atom::DIRTY_CPU => {
+                    scheduler::schedule(process, Some(Dirty::Cpu), dirty_cpu_2, &[arg1, n])
+                }
+                atom::DIRTY_IO => {
+                    scheduler::schedule(process, Some(Dirty::Io), dirty_io_2, &[arg1, n])
+                }
+                _ => scheduler::schedule(process, None, dirty_3, &[atom!(NORMAL), arg1, n]),
+            }
+        }
+        Variant::Atom(atom::READY_WAIT6_DONE) => {
+            send_tagged(vm, process, arg2, atom::READY)?;
+            std::thread::sleep(Duration::from_millis(6000));
+            send_tagged(vm, process, arg2, atom::DONE)?;
+            Ok(atom!(OK))
+        }
+        Variant::Atom(atom::ALIVE_WAITEXITING) => {
+            if process.has_pending_exit() {
+                return Err(badarg!());
+            }
+            send_tagged(vm, process, arg2, atom::ALIVE)?;
+
+            // wait until we're killed
+            while !process.has_pending_exit() {
+                if ty == atom::DIRTY_IO {
+                    std::thread::sleep(Duration::from_millis(100));
+                } else {
+                    std::thread::yield_now();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(1000));
+
+            // should still be able to allocate memory
+            let big = BigInt::from(4711) << (64 * 10000);
+            Ok(tup2!(heap, atom!(OK), Term::bigint(heap, big)))
+        }
+        _ => Err(badarg!()),
+    }
+}
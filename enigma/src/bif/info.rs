@@ -1,18 +1,96 @@
 use crate::atom::{self, Atom};
 use crate::bif;
-use crate::process::RcProcess;
+use crate::bitstring;
+use crate::immix::Heap;
+use crate::instruction::Ptr;
+use crate::loader::FuncInfo;
+use crate::module::MFA;
+use crate::process::{gc, RcProcess};
+use crate::servo_arc::Arc;
 use crate::value::{self, CastFrom, Cons, Term, Variant};
 use crate::vm;
-use crate::Itertools;
+use std::fmt::Write;
 
+/// Items returned by `process_info/1`, in order.
+const PROCESS_INFO_1_ITEMS: &[Atom] = &[
+    atom::REGISTERED_NAME,
+    atom::CURRENT_FUNCTION,
+    atom::INITIAL_CALL,
+    atom::STATUS,
+    atom::MESSAGE_QUEUE_LEN,
+    atom::LINKS,
+    atom::DICTIONARY,
+    atom::TRAP_EXIT,
+    atom::ERROR_HANDLER,
+    atom::PRIORITY,
+    atom::GROUP_LEADER,
+    atom::TOTAL_HEAP_SIZE,
+    atom::HEAP_SIZE,
+    atom::STACK_SIZE,
+    atom::REDUCTIONS,
+    atom::GARBAGE_COLLECTION,
+    atom::SUSPENDING,
+];
+
+/// Looks up the function `ptr` points into. Processes that haven't been set up to run any code
+/// yet don't have one.
+fn func_info(ptr: &Ptr) -> Option<(MFA, Option<FuncInfo>)> {
+    if ptr.module.is_null() {
+        return None;
+    }
+    ptr.lookup_func_info()
+}
+
+fn describe(ptr: &Ptr) -> String {
+    match func_info(ptr) {
+        Some((mfa, _)) => format!("{} + {}", mfa, ptr.ptr),
+        None => "unknown function".to_string(),
+    }
+}
+
+/// Dumps the call stack of `process` in a human readable form, along with the contents of each
+/// stack frame.
+fn backtrace(process: &RcProcess) -> String {
+    let context = process.context();
+    let mut out = String::new();
+    let _ = writeln!(out, "Program counter: ({})", describe(&context.ip));
+    match &context.cp {
+        Some(cp) => {
+            let _ = writeln!(out, "CP: ({})", describe(cp));
+        }
+        None => out.push_str("CP: (invalid)\n"),
+    }
+    let _ = writeln!(out, "Catch level: {}", context.catches);
+
+    // frames are pushed onto the end of the stack, y(0) is the last slot of a frame
+    let mut end = context.stack.len();
+    for (size, cp) in context.callstack.iter().rev() {
+        out.push('\n');
+        match cp {
+            Some(cp) => {
+                let _ = writeln!(out, "Return addr ({})", describe(cp));
+            }
+            None => out.push_str("Return addr (invalid)\n"),
+        }
+        let size = *size as usize;
+        for y in 0..size {
+            let _ = writeln!(out, "y({})\t{}", y, context.stack[end - y - 1]);
+        }
+        end -= size;
+    }
+    out
+}
+
+/// Looks up `item` for `process`. The result is built on `heap`, which belongs to the caller.
 pub fn process_info_aux(
-    _vm: &vm::Machine,
+    vm: &vm::Machine,
+    heap: &Heap,
     process: &RcProcess,
     item: Term,
     always_wrap: bool,
 ) -> bif::Result {
-    use crate::process::Flag;
-    let heap = &process.context_mut().heap;
+    use crate::process::{Flag, StateFlag};
+    let words = |bytes: usize| Term::uint64(heap, (bytes / gc::WORD_SIZE) as u64);
 
     // TODO: bump process regs
     // (*reds)++;
@@ -56,13 +134,24 @@ pub fn process_info_aux(
             }
         }
         atom::CURRENT_FUNCTION => {
-            //process.context_mut().ip.lookup_func_info()
-            unimplemented!()
-        }
-        atom::CURRENT_LOCATION => {
-            let fi = process.context_mut().ip.lookup_func_info().unwrap();
-            crate::exception::erts_build_mfa_item(&fi, heap, Term::nil())
+            if local_data.state.contains(StateFlag::HIBERNATED) {
+                tup3!(heap, atom!(ERLANG), atom!(HIBERNATE), Term::int(3))
+            } else {
+                match func_info(&process.context().ip) {
+                    Some((MFA(module, function, arity), _)) => tup3!(
+                        heap,
+                        Term::atom(module),
+                        Term::atom(function),
+                        Term::uint(heap, arity)
+                    ),
+                    None => atom!(UNDEFINED),
+                }
+            }
         }
+        atom::CURRENT_LOCATION => match func_info(&process.context().ip) {
+            Some(fi) => crate::exception::erts_build_mfa_item(&fi, heap, Term::nil()),
+            None => atom!(UNDEFINED),
+        },
         atom::CURRENT_STACKTRACE => {
             let mut trace = Vec::with_capacity(crate::exception::DEFAULT_BACKTRACE_SIZE as usize);
             crate::exception::erts_save_stacktrace(
@@ -70,14 +159,18 @@ pub fn process_info_aux(
                 &mut trace,
                 crate::exception::DEFAULT_BACKTRACE_SIZE,
             );
-            trace.into_iter().rev().fold(Term::nil(), |acc, ptr| {
-                let func_info = ptr.lookup_func_info().unwrap();
-                cons!(
-                    heap,
-                    crate::exception::erts_build_mfa_item(&func_info, heap, Term::nil()),
-                    acc
-                )
-            })
+            // frames we can't place (e.g. in purged code) are left out
+            trace
+                .into_iter()
+                .rev()
+                .filter_map(|ptr| func_info(&ptr))
+                .fold(Term::nil(), |acc, fi| {
+                    cons!(
+                        heap,
+                        crate::exception::erts_build_mfa_item(&fi, heap, Term::nil()),
+                        acc
+                    )
+                })
         }
         atom::INITIAL_CALL => {
             let call = local_data.initial_call;
@@ -89,64 +182,134 @@ pub fn process_info_aux(
             )
         }
         atom::STATUS => {
-            // TODO: quick cheat
-            atom!(RUNNING)
+            if process.is_suspended() {
+                atom!(SUSPENDED)
+            } else if process.is_running_dirty() {
+                atom!(RUNNING)
+            } else {
+                Term::atom(process.status().to_atom())
+            }
         }
-        atom::MESSAGES => {
-            // TODO: quick cheat
+        // sensitive processes don't reveal their data
+        atom::MESSAGES | atom::DICTIONARY if local_data.flags.contains(Flag::SENSITIVE) => {
             Term::nil()
         }
+        atom::BACKTRACE if local_data.flags.contains(Flag::SENSITIVE) => {
+            Term::binary(heap, bitstring::Binary::new())
+        }
+        atom::MESSAGES => {
+            let messages = local_data.mailbox.iter().map(|message| message.deep_clone(heap));
+            Cons::from_iter(messages, heap)
+        }
         atom::MESSAGE_QUEUE_LEN => Term::uint(heap, local_data.mailbox.len() as u32),
-        atom::MESSAGE_QUEUE_DATA => unimplemented!(),
+        atom::MESSAGE_QUEUE_DATA => {
+            if local_data.flags.contains(Flag::OFF_HEAP_MSGQ) {
+                atom!(OFF_HEAP)
+            } else {
+                atom!(ON_HEAP)
+            }
+        }
         atom::LINKS => local_data
             .links
             .iter()
             .fold(Term::nil(), |acc, pid| cons!(heap, Term::pid(*pid), acc)),
-        atom::MONITORED_BY => unimplemented!(),
+        atom::MONITORS => local_data
+            .monitors
+            .values()
+            .fold(Term::nil(), |acc, monitored| {
+                let (kind, object) = monitored.down_object(heap);
+                cons!(heap, tup2!(heap, kind, object), acc)
+            }),
+        atom::MONITORED_BY => local_data
+            .lt_monitors
+            .iter()
+            .fold(Term::nil(), |acc, (pid, _)| cons!(heap, Term::pid(*pid), acc)),
         atom::DICTIONARY => {
-            let pdict = &process.local_data_mut().dictionary;
-            let heap = &process.context_mut().heap;
+            let pdict = &local_data.dictionary;
 
             pdict.iter().fold(Term::nil(), |res, (key, val)| {
-                let tuple = tup2!(heap, *key, *val);
+                let tuple = tup2!(heap, key.deep_clone(heap), val.deep_clone(heap));
                 cons!(heap, tuple, res)
             })
         }
         atom::TRAP_EXIT => Term::boolean(local_data.flags.contains(Flag::TRAP_EXIT)),
-        atom::ERROR_HANDLER => unimplemented!(),
-        atom::HEAP_SIZE => {
-            // TODO: temporary
-            Term::int(512)
-        }
-        atom::STACK_SIZE => {
-            // TODO: temporary
-            Term::int(512)
-        }
-        atom::MEMORY => {
-            // TODO: temporary
-            Term::int(1024)
-        }
-        atom::GARBAGE_COLLECTION => unimplemented!(),
-        atom::GARBAGE_COLLECTION_INFO => unimplemented!(),
-        atom::GROUP_LEADER => unimplemented!(),
-        atom::REDUCTIONS => Term::uint(heap, process.context().reds as u32),
-        atom::PRIORITY => {
-            // TODO: temporary
-            atom!(NORMAL)
-        }
-        atom::TRACE => unimplemented!(),
-        atom::BINARY => unimplemented!(),
-        atom::SEQUENTIAL_TRACE_TOKEN => unimplemented!(),
-        atom::CATCH_LEVEL => unimplemented!(),
-        atom::BACKTRACE => unimplemented!(),
-        atom::LAST_CALLS => unimplemented!(),
-        atom::TOTAL_HEAP_SIZE => unimplemented!(),
-        atom::SUSPENDING => unimplemented!(),
-        atom::MIN_HEAP_SIZE => unimplemented!(),
-        atom::MIN_BIN_VHEAP_SIZE => unimplemented!(),
-        atom::MAX_HEAP_SIZE => unimplemented!(),
-        atom::MAGIC_REF => unimplemented!(),
-        atom::FULLSWEEP_AFTER => unimplemented!(),
+        atom::ERROR_HANDLER => Term::atom(local_data.error_handler),
+        atom::HEAP_SIZE => words(process.context().heap.size()),
+        atom::STACK_SIZE => Term::uint(heap, process.context().stack.len() as u32),
+        atom::MEMORY => Term::uint64(heap, process.memory().0 as u64),
+        atom::GARBAGE_COLLECTION => {
+            let gc = &local_data.gc;
+            let items = vec![
+                tup2!(heap, atom!(MAX_HEAP_SIZE), gc.max_heap_size.to_term(heap)),
+                tup2!(heap, atom!(MIN_BIN_VHEAP_SIZE), words(gc.min_bin_vheap_size)),
+                tup2!(heap, atom!(MIN_HEAP_SIZE), words(gc.min_heap_size)),
+                tup2!(
+                    heap,
+                    atom!(FULLSWEEP_AFTER),
+                    Term::uint64(heap, gc.fullsweep_after as u64)
+                ),
+                tup2!(heap, atom!(MINOR_GCS), Term::uint64(heap, gc.minor_gcs as u64)),
+            ];
+            Cons::from_iter(items.into_iter(), heap)
+        }
+        atom::GARBAGE_COLLECTION_INFO => gc::info(process, heap),
+        atom::GROUP_LEADER => Term::pid(local_data.group_leader),
+        atom::REDUCTIONS => Term::uint64(heap, process.context().reductions as u64),
+        atom::PRIORITY => Term::atom(local_data.priority.to_atom()),
+        // there's no tracing support, so no trace flags are ever set
+        atom::TRACE => Term::int(0),
+        atom::BINARY => {
+            let context = process.context();
+            let mut seen = hashbrown::HashSet::new();
+            let binaries = std::iter::once(&context.heap)
+                .chain(context.fragments.iter())
+                .flat_map(Heap::binaries)
+                .filter(|binary| seen.insert(binary.heap_ptr() as usize))
+                .map(|binary| {
+                    tup3!(
+                        heap,
+                        Term::uint64(heap, binary.heap_ptr() as usize as u64),
+                        Term::uint64(heap, binary.data.len() as u64),
+                        Term::uint64(heap, Arc::strong_count(&binary) as u64)
+                    )
+                })
+                .collect::<Vec<_>>();
+            Cons::from_iter(binaries.into_iter(), heap)
+        }
+        atom::SEQUENTIAL_TRACE_TOKEN => Term::nil(),
+        atom::CATCH_LEVEL => Term::uint64(heap, process.context().catches as u64),
+        atom::BACKTRACE => {
+            Term::binary(heap, bitstring::Binary::from(backtrace(process).into_bytes()))
+        }
+        atom::LAST_CALLS => match &local_data.saved_calls {
+            Some(saved) => {
+                let calls = saved.calls.iter().map(|call| call.to_term(heap));
+                Cons::from_iter(calls, heap)
+            }
+            None => atom!(FALSE),
+        },
+        atom::TOTAL_HEAP_SIZE => words(gc::heap_size(process.context())),
+        atom::SUSPENDING => {
+            // we don't know whether our suspends took effect yet, count them all as active
+            let suspending = local_data
+                .suspending
+                .iter()
+                .map(|(pid, count)| {
+                    let count = Term::uint64(heap, *count as u64);
+                    tup3!(heap, Term::pid(*pid), count, Term::int(0))
+                })
+                .collect::<Vec<_>>();
+            Cons::from_iter(suspending.into_iter(), heap)
+        }
+        atom::MIN_HEAP_SIZE => words(local_data.gc.min_heap_size),
+        atom::MIN_BIN_VHEAP_SIZE => words(local_data.gc.min_bin_vheap_size),
+        atom::MAX_HEAP_SIZE => local_data.gc.max_heap_size.to_term(heap),
+        atom::MAGIC_REF => {
+            let local_data = process.local_data_mut();
+            let reference = *local_data.magic_ref.get_or_insert_with(|| vm.next_ref());
+            Term::reference(heap, reference)
+        }
+        atom::FULLSWEEP_AFTER => Term::uint64(heap, local_data.gc.fullsweep_after as u64),
         _ => return Err(badarg!()),
     };
 
@@ -169,20 +332,49 @@ pub fn process_info_2(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> b
     };
 
     if let Some(proc) = proc {
+        let heap = &process.context_mut().heap;
+        if args[1].is_nil() {
+            return Ok(Term::nil());
+        }
         match Cons::cast_from(&args[1]) {
             Ok(cons) => {
-                let heap = &process.context_mut().heap;
-                cons.iter()
-                    .map(|val| process_info_aux(vm, &proc, *val, true))
-                    .fold_results(Term::nil(), |acc, val| cons!(heap, val, acc))
+                let items = cons
+                    .iter()
+                    .map(|val| process_info_aux(vm, heap, &proc, *val, true))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Cons::from_iter(items.into_iter(), heap))
             }
-            _ => process_info_aux(vm, &proc, args[1], false),
+            _ => process_info_aux(vm, heap, &proc, args[1], false),
         }
     } else {
         return Ok(atom!(UNDEFINED));
     }
 }
 
+pub fn process_info_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let pid = match args[0].into_variant() {
+        Variant::Pid(pid) => pid,
+        _ => return Err(badarg!()),
+    };
+
+    let proc = match vm.process_table.lock().get(pid) {
+        Some(proc) => proc,
+        None => return Ok(atom!(UNDEFINED)),
+    };
+
+    let heap = &process.context_mut().heap;
+    let items = PROCESS_INFO_1_ITEMS
+        .iter()
+        .map(|item| process_info_aux(vm, heap, &proc, Term::atom(*item), false))
+        .collect::<Result<Vec<_>, _>>()?;
+    // registered_name is left out unless the process has a name
+    let items = items
+        .into_iter()
+        .filter(|item| !item.is_nil())
+        .collect::<Vec<_>>();
+    Ok(Cons::from_iter(items.into_iter(), heap))
+}
+
 pub fn fun_info_2(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     let heap = &process.context_mut().heap;
     if let Ok(closure) = value::Closure::cast_from(&args[0]) {
@@ -245,7 +437,7 @@ pub fn system_info_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bi
     match args[0].into_variant() {
         Variant::Atom(atom::OS_TYPE) => Ok(tup2!(heap, Term::atom(OS_FAMILY), atom!(TRUE))), // TODO: true should be :darwin
         Variant::Atom(atom::HIPE_ARCHITECTURE) => Ok(atom!(UNDEFINED)),
-        Variant::Atom(atom::SYSTEM_VERSION) => Ok(bitstring!(heap, "Erlang/OTP 22 [erts-10.3.4] [source] [64-bit] [smp:8:8] [ds:8:8:10] [async-threads:1] [enigma]\n")),
+        Variant::Atom(atom::SYSTEM_VERSION) => Ok(bitstring!(heap, vm::SYSTEM_VERSION)),
         Variant::Atom(atom::SYSTEM_LOGGER) => {
             Ok(Term::pid(vm.system_logger.load(Ordering::Relaxed) as u32)) // TODO: unsafe
         }
@@ -261,6 +453,24 @@ pub fn system_info_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bi
         Variant::Atom(atom::ENDIAN) => {
             Ok(Term::atom(ENDIAN))
         }
+        Variant::Atom(atom::SCHEDULERS) | Variant::Atom(atom::SCHEDULERS_ONLINE) => {
+            Ok(Term::uint(heap, vm.config.schedulers as u32))
+        }
+        Variant::Atom(atom::DIRTY_CPU_SCHEDULERS)
+        | Variant::Atom(atom::DIRTY_CPU_SCHEDULERS_ONLINE) => {
+            Ok(Term::uint(heap, vm.config.dirty_cpu_schedulers as u32))
+        }
+        Variant::Atom(atom::DIRTY_IO_SCHEDULERS) => {
+            Ok(Term::uint(heap, vm.config.dirty_io_schedulers as u32))
+        }
+        Variant::Atom(atom::PROCESS_LIMIT) => {
+            Ok(Term::uint64(heap, vm.process_table.lock().limit() as u64))
+        }
+        Variant::Atom(atom::PROCESS_COUNT) => {
+            Ok(Term::uint64(heap, vm.process_table.lock().len() as u64))
+        }
+        Variant::Atom(atom::ATOM_LIMIT) => Ok(Term::uint64(heap, atom::limit() as u64)),
+        Variant::Atom(atom::ATOM_COUNT) => Ok(Term::uint64(heap, atom::count() as u64)),
         // Variant::Atom(atom::START_TIME) => {
         //     Ok(Term::int(vm.start_time))
         // }
@@ -279,6 +489,76 @@ pub fn system_info_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bi
     }
 }
 
+pub fn statistics_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let heap = &process.context_mut().heap;
+    let uint = |value: usize| Term::uint64(heap, value as u64);
+    let pair = |(total, since_last): (u64, u64)| {
+        tup2!(heap, Term::uint64(heap, total), Term::uint64(heap, since_last))
+    };
+
+    match args[0].into_variant() {
+        Variant::Atom(atom::REDUCTIONS) => {
+            let (total, since_last) = vm.stats.reductions();
+            Ok(tup2!(heap, uint(total), uint(since_last)))
+        }
+        Variant::Atom(atom::EXACT_REDUCTIONS) => {
+            let (total, since_last) = vm.stats.exact_reductions();
+            Ok(tup2!(heap, uint(total), uint(since_last)))
+        }
+        Variant::Atom(atom::RUNTIME) => Ok(pair(vm.stats.runtime(vm.elapsed_time()))),
+        Variant::Atom(atom::WALL_CLOCK) => Ok(pair(vm.stats.wall_clock(vm.elapsed_time()))),
+        Variant::Atom(atom::RUN_QUEUE) | Variant::Atom(atom::TOTAL_RUN_QUEUE_LENGTHS) => {
+            Ok(uint(vm.run_queues.waiting()))
+        }
+        // there's a single run queue shared by all schedulers
+        Variant::Atom(atom::RUN_QUEUE_LENGTHS) => {
+            Ok(cons!(heap, uint(vm.run_queues.waiting()), Term::nil()))
+        }
+        Variant::Atom(atom::ACTIVE_TASKS) => {
+            let active = vm.run_queues.waiting() + vm.run_queues.running();
+            Ok(cons!(heap, uint(active), Term::nil()))
+        }
+        Variant::Atom(atom::CONTEXT_SWITCHES) => {
+            Ok(tup2!(heap, uint(vm.stats.context_switches()), Term::int(0)))
+        }
+        Variant::Atom(atom::GARBAGE_COLLECTION) => {
+            let (gcs, reclaimed) = vm.stats.garbage_collection();
+            Ok(tup3!(heap, uint(gcs), uint(reclaimed), Term::int(0)))
+        }
+        Variant::Atom(atom::IO) => {
+            let (input, output) = vm.stats.io();
+            Ok(tup2!(
+                heap,
+                tup2!(heap, atom!(INPUT), uint(input)),
+                tup2!(heap, atom!(OUTPUT), uint(output))
+            ))
+        }
+        Variant::Atom(atom::SCHEDULER_WALL_TIME) => Ok(wall_time(vm, heap, false)),
+        Variant::Atom(atom::SCHEDULER_WALL_TIME_ALL) => Ok(wall_time(vm, heap, true)),
+        _ => Err(badarg!()),
+    }
+}
+
+/// `[{SchedulerId, ActiveTime, TotalTime}]`, or `undefined` if measurement is turned off.
+fn wall_time(vm: &vm::Machine, heap: &Heap, all: bool) -> Term {
+    let schedulers = match vm.stats.scheduler_wall_time(all) {
+        Some(schedulers) => schedulers,
+        None => return atom!(UNDEFINED),
+    };
+    schedulers
+        .into_iter()
+        .rev()
+        .fold(Term::nil(), |acc, (id, active, total)| {
+            let item = tup3!(
+                heap,
+                Term::uint(heap, id as u32),
+                Term::uint64(heap, active),
+                Term::uint64(heap, total)
+            );
+            cons!(heap, item, acc)
+        })
+}
+
 pub fn system_flag_2(vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     use std::sync::atomic::Ordering;
     match args[0].into_variant() {
@@ -295,6 +575,116 @@ pub fn system_flag_2(vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> b
     }
 }
 
+/// Memory usage in bytes, broken down the same way as `erlang:memory/0`.
+struct Memory {
+    processes: usize,
+    processes_used: usize,
+    atom: usize,
+    atom_used: usize,
+    binary: usize,
+    code: usize,
+    ets: usize,
+}
+
+impl Memory {
+    fn collect(vm: &vm::Machine) -> Self {
+        let procs: Vec<RcProcess> = {
+            let table = vm.process_table.lock();
+            table.all().into_iter().filter_map(|pid| table.get(pid)).collect()
+        };
+        let (processes, processes_used) = procs
+            .iter()
+            .map(|process| process.memory())
+            .fold((0, 0), |(a, u), (allocated, used)| (a + allocated, u + used));
+
+        let (atom, atom_used) = atom::memory();
+
+        Memory {
+            processes,
+            processes_used,
+            atom,
+            atom_used,
+            binary: crate::bitstring::allocated(),
+            code: vm.modules.lock().memory() + vm.literal_areas.lock().memory(),
+            ets: vm.ets_tables.lock().memory(),
+        }
+    }
+
+    fn system(&self) -> usize {
+        std::mem::size_of::<vm::Machine>() + self.atom + self.binary + self.code + self.ets
+    }
+
+    fn get(&self, kind: Atom) -> Option<usize> {
+        let value = match kind {
+            atom::TOTAL => self.processes + self.system(),
+            atom::PROCESSES => self.processes,
+            atom::PROCESSES_USED => self.processes_used,
+            atom::SYSTEM => self.system(),
+            atom::ATOM => self.atom,
+            atom::ATOM_USED => self.atom_used,
+            atom::BINARY => self.binary,
+            atom::CODE => self.code,
+            atom::ETS => self.ets,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+const MEMORY_TYPES: [Atom; 9] = [
+    atom::TOTAL,
+    atom::PROCESSES,
+    atom::PROCESSES_USED,
+    atom::SYSTEM,
+    atom::ATOM,
+    atom::ATOM_USED,
+    atom::BINARY,
+    atom::CODE,
+    atom::ETS,
+];
+
+pub fn memory_0(vm: &vm::Machine, process: &RcProcess, _args: &[Term]) -> bif::Result {
+    let heap = &process.context_mut().heap;
+    let memory = Memory::collect(vm);
+
+    Ok(MEMORY_TYPES.iter().rev().fold(Term::nil(), |acc, kind| {
+        let value = Term::uint64(heap, memory.get(*kind).unwrap() as u64);
+        cons!(heap, tup2!(heap, Term::atom(*kind), value), acc)
+    }))
+}
+
+pub fn memory_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let heap = &process.context_mut().heap;
+
+    match args[0].into_variant() {
+        Variant::Atom(kind) => {
+            let memory = Memory::collect(vm);
+            match memory.get(kind) {
+                Some(value) => Ok(Term::uint64(heap, value as u64)),
+                None => Err(badarg!()),
+            }
+        }
+        Variant::Nil(..) => Ok(Term::nil()),
+        _ => {
+            let cons = Cons::cast_from(&args[0])?;
+            let memory = Memory::collect(vm);
+            let items = cons
+                .iter()
+                .map(|kind| match kind.into_variant() {
+                    Variant::Atom(kind) => memory.get(kind).map(|value| (kind, value)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| badarg!())?;
+
+            Ok(items.into_iter().rev().fold(Term::nil(), |acc, (kind, value)| {
+                let value = Term::uint64(heap, value as u64);
+                cons!(heap, tup2!(heap, Term::atom(kind), value), acc)
+            }))
+        }
+    }
+}
+
 pub fn group_leader_0(_vm: &vm::Machine, process: &RcProcess, _args: &[Term]) -> bif::Result {
     Ok(Term::pid(process.local_data().group_leader))
 }
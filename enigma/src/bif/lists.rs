@@ -3,6 +3,7 @@ use crate::bif;
 use crate::process::RcProcess;
 use crate::value::{self, CastFrom, CastInto, Cons, Term, Tuple};
 use crate::vm;
+use std::cmp;
 
 pub fn member_2(_vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     // need to bump reductions as we go
@@ -44,17 +45,39 @@ pub fn member_2(_vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::
     Ok(atom!(FALSE)) // , reds_left - max_iter/16
 }
 
+/// List cells reversed per reduction.
+const REVERSE_CELLS_PER_RED: usize = 10;
+
 pub fn reverse_2(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     // Handle legal and illegal non-lists quickly.
     if args[0].is_nil() {
         return Ok(args[1]);
+    } else if !args[0].is_list() {
+        return Err(badarg!());
+    }
+
+    let context = process.context_mut();
+    let max_iter = cmp::max(context.reds, 1) * REVERSE_CELLS_PER_RED;
+    let mut list = args[0];
+    let mut acc = args[1];
+    let mut iter = 0;
+
+    while let Ok(Cons { head, tail }) = list.cast_into() {
+        if iter == max_iter {
+            // out of reductions, continue from here on the next run
+            bif_yield!(context, reverse_2, list, acc);
+        }
+        iter += 1;
+        acc = cons!(&context.heap, *head, acc);
+        list = *tail;
     }
 
-    let cons = Cons::cast_from(&args[0])?;
+    context.bump_reductions(iter / REVERSE_CELLS_PER_RED);
 
-    let heap = &process.context_mut().heap;
-    // TODO: finish up the reduction counting implementation
-    Ok(cons.iter().fold(args[1], |acc, val| cons!(heap, *val, acc)))
+    if !list.is_nil() {
+        return Err(badarg!());
+    }
+    Ok(acc)
 }
 
 pub fn keymember_3(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
@@ -122,6 +145,7 @@ fn keyfind(_func: bif::Fn, _process: &RcProcess, args: &[Term]) -> bif::Result {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::exception::{Exception, Reason};
     use crate::immix::Heap;
     use crate::module;
     use crate::process;
@@ -164,6 +188,49 @@ mod tests {
         assert_eq!(res, Ok(atom!(TRUE)));
     }
 
+    #[test]
+    fn test_reverse_2() {
+        let vm = vm::Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let heap = &process.context_mut().heap;
+
+        let list = from_vec(heap, vec![Term::int(1), Term::int(2), Term::int(3)]);
+        let tail = from_vec(heap, vec![Term::int(4)]);
+        let res = reverse_2(&vm, &process, &[list, tail]).unwrap();
+        assert_eq!(
+            to_vec(res),
+            vec![Term::int(3), Term::int(2), Term::int(1), Term::int(4), Term::nil()]
+        );
+
+        let res = reverse_2(&vm, &process, &[Term::int(1), Term::nil()]);
+        assert_eq!(res, Err(badarg!()));
+    }
+
+    #[test]
+    fn test_reverse_2_yields() {
+        let vm = vm::Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+        let heap = &context.heap;
+
+        let list = from_vec(heap, (0..100).map(Term::int).collect());
+        context.reds = 1;
+        let res = reverse_2(&vm, &process, &[list, Term::nil()]);
+        assert_eq!(res, Err(Exception::new(Reason::YIELD)));
+        assert_eq!(context.reds, 0);
+
+        // resume with the continuation that was left behind
+        let trap = context.trap.take().unwrap();
+        assert_eq!(trap.arity, 2);
+        context.reds = process::CONTEXT_REDS;
+        let args = [context.x[0], context.x[1]];
+        let res = (trap.bif)(&vm, &process, &args).unwrap();
+        assert_eq!(to_vec(res).len(), 101);
+        assert_eq!(to_vec(res)[0], Term::int(99));
+    }
+
     #[test]
     fn test_keyfind_3() {
         let vm = vm::Machine::new();
@@ -67,19 +67,37 @@ pub fn has_prepared_code_on_load_1(
     }
 }
 
-pub fn finish_loading_1(vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
-    value::Cons::cast_from(&args[0])?
+/// Loads prepared code. If any of the modules still has old code around, nothing gets loaded and
+/// `{not_purged, Modules}` is returned: processes might still be running the old code, so it can
+/// only go through `erts_internal:purge_module/2`.
+pub fn finish_loading_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
+    let prepared = value::Cons::cast_from(&args[0])?
         .iter()
-        .map(|v| {
-            v.cast_into()
-                .map(|value: &*mut Module| unsafe { Box::from_raw(*value) })
-        })
-        .collect::<Result<Vec<Box<Module>>, _>>()
-        .map_err(|_| badarg!())
-        .and_then(|mods| {
-            module::finish_loading_modules(vm, mods);
-            Ok(atom!(OK))
-        })
+        .map(|v| v.cast_into().map(|value: &*mut Module| *value))
+        .collect::<Result<Vec<*mut Module>, _>>()
+        .map_err(|_| badarg!())?;
+
+    let not_purged = {
+        let registry = vm.modules.lock();
+        prepared
+            .iter()
+            .map(|module| unsafe { (**module).name })
+            .filter(|name| registry.lookup_old(*name).is_some())
+            .collect::<Vec<_>>()
+    };
+    if !not_purged.is_empty() {
+        // the prepared code stays with its term, it can be loaded again after the purge
+        let heap = &process.context_mut().heap;
+        let modules = Cons::from_iter(not_purged.into_iter().map(Term::atom), heap);
+        return Ok(tup2!(heap, atom!(NOT_PURGED), modules));
+    }
+
+    let mods = prepared
+        .into_iter()
+        .map(|module| unsafe { Box::from_raw(module) })
+        .collect();
+    module::finish_loading_modules(vm, mods);
+    Ok(atom!(OK))
 }
 
 pub fn get_module_info_1(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
@@ -149,14 +167,45 @@ fn get_module_info(heap: &crate::immix::Heap, module: &Module, what: Term) -> bi
     }
 }
 
+/// Purges the old code of a module, in steps driven by erts_code_purger: `prepare` checks if
+/// there's any old code, the purger then makes sure no process is running it (see
+/// `check_process_code`), and `complete` releases it. `abort` gives up on the purge.
 pub fn erts_internal_purge_module_2(
     vm: &vm::Machine,
     process: &RcProcess,
     args: &[Term],
 ) -> bif::Result {
-    // prepare / prepare_on_load
-    // abort
-    // complete
-    // unimplemented!("purge_module: {} {}", args[0], args[1]);
-    Ok(atom!(TRUE))
+    let name = match args[0].into_variant() {
+        Variant::Atom(name) => name,
+        _ => return Err(badarg!()),
+    };
+
+    match args[1].into_variant() {
+        Variant::Atom(atom::PREPARE) | Variant::Atom(atom::PREPARE_ON_LOAD) => {
+            let registry = vm.modules.lock();
+            Ok(Term::boolean(registry.lookup_old(name).is_some()))
+        }
+        Variant::Atom(atom::ABORT) => Ok(atom!(TRUE)),
+        Variant::Atom(atom::COMPLETE) => {
+            let module = vm.modules.lock().purge_module(name);
+            match module {
+                Some(module) => {
+                    module::purge(vm, process.pid, module);
+                    Ok(atom!(TRUE))
+                }
+                None => Ok(atom!(FALSE)),
+            }
+        }
+        _ => Err(badarg!()),
+    }
+}
+
+/// Called by erts_literal_area_collector once all the processes copied their literals out of the
+/// active literal area. Releases it and switches to the next one, returns false if there's none.
+pub fn erts_internal_release_literal_area_switch_0(
+    vm: &vm::Machine,
+    _process: &RcProcess,
+    _args: &[Term],
+) -> bif::Result {
+    Ok(Term::boolean(vm.literal_areas.lock().switch()))
 }
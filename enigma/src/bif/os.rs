@@ -15,7 +15,7 @@ pub fn list_env_vars_0(_vm: &vm::Machine, process: &RcProcess, _args: &[Term]) -
 pub fn get_env_var_1(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif::Result {
     let heap = &process.context_mut().heap;
     let cons = Cons::cast_from(&args[0])?;
-    let name = value::cons::unicode_list_to_buf(cons, 2048).unwrap();
+    let name = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
 
     match env::var(name) {
         Ok(var) => Ok(bitstring!(heap, var)),
@@ -26,9 +26,9 @@ pub fn get_env_var_1(_vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> b
 
 pub fn set_env_var_2(_vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     let cons = Cons::cast_from(&args[0])?;
-    let name = value::cons::unicode_list_to_buf(cons, 2048).unwrap();
+    let name = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
     let cons = Cons::cast_from(&args[1])?;
-    let val = value::cons::unicode_list_to_buf(cons, 2048).unwrap();
+    let val = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
 
     env::set_var(name, val);
     Ok(atom!(TRUE))
@@ -36,7 +36,7 @@ pub fn set_env_var_2(_vm: &vm::Machine, _process: &RcProcess, args: &[Term]) ->
 
 pub fn unset_env_var_1(_vm: &vm::Machine, _process: &RcProcess, args: &[Term]) -> bif::Result {
     let cons = Cons::cast_from(&args[0])?;
-    let name = value::cons::unicode_list_to_buf(cons, 2048).unwrap();
+    let name = value::cons::unicode_list_to_buf(cons, std::usize::MAX)?;
 
     env::remove_var(name);
     Ok(atom!(TRUE))
@@ -1,4 +1,5 @@
 use crate::bif;
+use crate::immix::Heap;
 use crate::process::{self, RcProcess};
 use crate::value::{self, CastFrom, Term, Tuple, Variant};
 use crate::vm;
@@ -18,15 +19,19 @@ pub fn send_after_3(vm: &vm::Machine, process: &RcProcess, args: &[Term]) -> bif
     }
 
     let dest = args[1];
-    let msg = args[2];
     let from = process.pid;
 
+    // the message has to outlive our heap, so the timer keeps its own copy.
+    let heap = Heap::fragment();
+    let msg = args[2].deep_clone(&heap);
+
     let when = Instant::now() + Duration::from_millis(u64::from(delay));
     let fut = async move {
         tokio::timer::delay(when).await;
         vm::Machine::with_current(|vm| process::send_message(vm, from, dest, msg));
+        drop(heap);
     };
-    vm.runtime.executor().spawn(fut);
+    vm.runtime.spawn(fut);
 
     let heap = &process.context_mut().heap;
     let reference = vm.next_ref();
@@ -4,6 +4,7 @@ use crate::servo_arc::Arc;
 use crate::value::{self, CastFrom, Term};
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 // use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 // use std::cell::UnsafeCell;
 
@@ -55,32 +56,36 @@ pub struct Binary {
 
 pub type RcBinary = Arc<Binary>;
 
+/// Bytes binary operations get to copy or scan per reduction.
+pub const BYTES_PER_RED: usize = 100;
+
+/// Bytes allocated for binary data by all the live binaries.
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Total number of bytes allocated for binary data, reported by `erlang:memory(binary)`.
+pub fn allocated() -> usize {
+    ALLOCATED.load(AtomicOrdering::Relaxed)
+}
+
 impl Binary {
     pub fn new() -> Self {
-        Binary {
-            // flags: AtomicUsize::new(0),
-            // WRITABLE | ACTIVE_WRITER
-            is_writable: true,
-            data: Vec::new(),
-        }
+        Binary::from(Vec::new())
     }
 
     pub fn with_capacity(cap: usize) -> Self {
-        Binary {
-            // flags: AtomicUsize::new(0),
-            // WRITABLE | ACTIVE_WRITER
-            is_writable: true,
-            data: Vec::with_capacity(cap),
-        }
+        Binary::from(Vec::with_capacity(cap))
     }
 
     pub fn with_size(size: usize) -> Self {
-        Binary {
-            // flags: AtomicUsize::new(0),
-            // WRITABLE | ACTIVE_WRITER
-            is_writable: true,
-            data: vec![0; size],
-        }
+        Binary::from(vec![0; size])
+    }
+
+    /// Grows the binary to `len` bytes, zero filling the new space. Same caveat as `get_mut`.
+    pub fn resize(&self, len: usize) {
+        let data = self.get_mut();
+        let capacity = data.capacity();
+        data.resize(len, 0);
+        ALLOCATED.fetch_add(data.capacity() - capacity, AtomicOrdering::Relaxed);
     }
 
     #[allow(clippy::mut_from_ref)]
@@ -90,8 +95,15 @@ impl Binary {
     }
 }
 
+impl Drop for Binary {
+    fn drop(&mut self) {
+        ALLOCATED.fetch_sub(self.data.capacity(), AtomicOrdering::Relaxed);
+    }
+}
+
 impl From<Vec<u8>> for Binary {
     fn from(value: Vec<u8>) -> Self {
+        ALLOCATED.fetch_add(value.capacity(), AtomicOrdering::Relaxed);
         Binary {
             // flags: AtomicUsize::new(0),
             // WRITABLE | ACTIVE_WRITER
@@ -103,12 +115,7 @@ impl From<Vec<u8>> for Binary {
 
 impl From<&[u8]> for Binary {
     fn from(value: &[u8]) -> Self {
-        Binary {
-            // flags: AtomicUsize::new(0),
-            // WRITABLE | ACTIVE_WRITER
-            is_writable: true,
-            data: value.to_vec(),
-        }
+        Binary::from(value.to_vec())
     }
 }
 
@@ -1094,9 +1101,8 @@ pub fn append(
         // pb.flags |= PB_ACTIVE_WRITER;
 
         // Reserve extra capacity if needed.
-        let data = pb.get_mut();
-        if data.len() < size {
-            data.resize(2 * size, 0); // why 2*?
+        if pb.data.len() < size {
+            pb.resize(2 * size); // why 2*?
         }
         let mut bs = Builder::new(pb);
         bs.offset = bin_size;
@@ -1168,8 +1174,8 @@ pub fn append(
         size = if size < 256 { 256 } else { size };
 
         // Allocate the binary data struct itself.
-        // TODO: the RcBinary alloc needs to be sorted out so we don't leak
-        let new_binary = heap.alloc(Arc::new(Binary::with_size(size))).clone();
+        let new_binary = Arc::new(Binary::with_size(size));
+        heap.grow_bin_vheap(size);
         // ACTIVE_WRITER
 
         let mut bs = Builder::new(&new_binary);
@@ -1227,9 +1233,8 @@ pub fn private_append(
     //}
 
     // Reserve extra capacity if needed.
-    let data = pb.get_mut();
-    if data.capacity() < size {
-        data.resize(2 * size, 0); // why 2*?
+    if pb.data.capacity() < size {
+        pb.resize(2 * size); // why 2*?
     }
     let mut bs = Builder::new(pb);
     bs.offset = bin_size;
@@ -1249,8 +1254,8 @@ pub fn init_writable(process: &RcProcess, size: Term) -> Term {
 
     let heap = &process.context_mut().heap;
 
-    // TODO: the RcBinary alloc needs to be sorted out so we don't leak
-    let binary = heap.alloc(Arc::new(Binary::with_size(size))).clone();
+    let binary = Arc::new(Binary::with_size(size));
+    heap.grow_bin_vheap(size);
 
     Term::subbinary(
         heap,
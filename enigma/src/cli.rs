@@ -0,0 +1,182 @@
+//! erl-compatible command line handling.
+//!
+//! Emulator flags (`+S`, `+P`, ...) and `-root` configure the machine, everything else is handed
+//! to `init` as is, which takes care of `-s`, `-run`, `-eval`, `-pa`, `-noshell` and friends.
+//! Arguments following `-extra` are never interpreted.
+use crate::vm::MachineBuilder;
+
+/// Applies an erl style command line, without the program name, on top of `builder`.
+pub fn parse<I>(mut builder: MachineBuilder, args: I) -> Result<MachineBuilder, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut init_args = Vec::new();
+    let mut distributed = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with('+') {
+            builder = emulator_flag(builder, &arg[1..], &mut args)?;
+            continue;
+        }
+
+        match arg.as_str() {
+            "-root" => {
+                let root = args.next().ok_or("-root requires a directory")?;
+                builder = builder.root(root);
+            }
+            "-extra" => {
+                init_args.push(arg);
+                init_args.extend(&mut args);
+            }
+            _ => {
+                if arg == "-sname" || arg == "-name" {
+                    distributed = true;
+                }
+                init_args.push(arg);
+            }
+        }
+    }
+
+    if !distributed {
+        builder = builder.args(
+            vec!["-kernel", "start_distribution", "false"]
+                .into_iter()
+                .map(String::from),
+        );
+    }
+    Ok(builder.args(init_args))
+}
+
+/// Flags are matched longest first, so that `+SDcpu4` isn't mistaken for `+S`.
+const EMULATOR_FLAGS: &[&str] = &["SDcpu", "SDio", "hmbs", "hms", "S", "P", "t"];
+
+/// Applies a single emulator flag. The value can either be attached (`+S4`) or follow as the next
+/// argument (`+S 4`).
+fn emulator_flag<I>(
+    builder: MachineBuilder,
+    arg: &str,
+    args: &mut I,
+) -> Result<MachineBuilder, String>
+where
+    I: Iterator<Item = String>,
+{
+    let flag = EMULATOR_FLAGS
+        .iter()
+        .find(|flag| arg.starts_with(*flag))
+        .ok_or_else(|| format!("unknown emulator flag +{}", arg))?;
+
+    let value = match &arg[flag.len()..] {
+        "" => args
+            .next()
+            .ok_or_else(|| format!("+{} requires a value", flag))?,
+        value => value.to_string(),
+    };
+
+    let builder = match *flag {
+        "S" => {
+            let n = schedulers(flag, &value, builder.config().schedulers)?;
+            builder.schedulers(n)
+        }
+        "SDcpu" => {
+            let n = schedulers(flag, &value, builder.config().dirty_cpu_schedulers)?;
+            builder.dirty_cpu_schedulers(n)
+        }
+        "SDio" => builder.dirty_io_schedulers(number(flag, &value)?),
+        "P" => builder.process_limit(number(flag, &value)?),
+        "t" => builder.atom_limit(number(flag, &value)?),
+        "hms" => builder.min_heap_size(number(flag, &value)?),
+        "hmbs" => builder.min_bin_vheap_size(number(flag, &value)?),
+        _ => unreachable!(),
+    };
+    Ok(builder)
+}
+
+/// Parses a scheduler count in the `Total:Online` form. All schedulers are kept online, so only
+/// the total counts, and if it's omitted the online count stands in for it.
+fn schedulers(flag: &str, value: &str, default: usize) -> Result<usize, String> {
+    let mut parts = value.splitn(2, ':');
+    let total = parts.next().unwrap_or("");
+    let online = parts.next().unwrap_or("");
+    match (total, online) {
+        ("", "") => Ok(default),
+        ("", online) => number(flag, online),
+        (total, _) => number(flag, total),
+    }
+}
+
+fn number(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("bad value for +{}: {}", flag, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn parse_args(list: &[&str]) -> Result<MachineBuilder, String> {
+        parse(MachineBuilder::new(), args(list))
+    }
+
+    #[test]
+    fn test_emulator_flags() {
+        let builder = parse_args(&[
+            "+S", "3:3", "+SDcpu2", "+SDio", "4", "+P", "1024", "+t", "5000", "+hms", "500",
+        ])
+        .unwrap();
+        let config = builder.config();
+        assert_eq!(config.schedulers, 3);
+        assert_eq!(config.dirty_cpu_schedulers, 2);
+        assert_eq!(config.dirty_io_schedulers, 4);
+        assert_eq!(config.process_limit, 1024);
+        assert_eq!(config.atom_limit, 5000);
+        assert_eq!(config.min_heap_size, 500);
+    }
+
+    #[test]
+    fn test_init_args() {
+        let builder = parse_args(&[
+            "-root", "/opt/otp", "-noshell", "-pa", "ebin", "-eval", "halt().", "-extra", "+S",
+            "-root",
+        ])
+        .unwrap();
+        let config = builder.config();
+        assert_eq!(config.root, PathBuf::from("/opt/otp"));
+        assert_eq!(
+            config.args,
+            args(&[
+                "-kernel",
+                "start_distribution",
+                "false",
+                "-noshell",
+                "-pa",
+                "ebin",
+                "-eval",
+                "halt().",
+                "-extra",
+                "+S",
+                "-root",
+            ])
+        );
+
+        // distribution is left up to kernel when a node name is given
+        let builder = parse_args(&["-sname", "foo"]).unwrap();
+        assert_eq!(builder.config().args, args(&["-sname", "foo"]));
+    }
+
+    #[test]
+    fn test_bad_flags() {
+        assert!(parse_args(&["+S"]).is_err());
+        assert!(parse_args(&["+P", "lots"]).is_err());
+        assert!(parse_args(&["+S", "0"]).is_err());
+        assert!(parse_args(&["+Q", "10"]).is_err());
+        assert!(parse_args(&["-root"]).is_err());
+    }
+}
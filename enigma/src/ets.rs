@@ -37,6 +37,9 @@ pub use error::Result;
 pub trait Table: Send + Sync {
     fn meta(&self) -> &Metadata;
 
+    /// Memory used by the table in bytes, including the objects stored on its heap.
+    fn memory(&self) -> usize;
+
     // first, next, last, prev could be iter? --> iter can't go backwards so we'll add a Cursor API
     // almost no code uses prev() outside of a few OTP tests so we could just start with Iter.
     // also, the db impl seems to equate the two
@@ -223,6 +226,11 @@ impl TableRegistry {
         true
     }
 
+    /// Memory used by all the tables, in bytes.
+    pub fn memory(&self) -> usize {
+        self.tables.values().map(|table| table.memory()).sum()
+    }
+
     pub fn whereis(&self, name: Atom) -> Option<process::Ref> {
         self.named_tables
             .get(&(name.0 as usize))
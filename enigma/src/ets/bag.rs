@@ -34,6 +34,16 @@ impl Table for Bag {
         &self.meta
     }
 
+    fn memory(&self) -> usize {
+        let hashmap = self.hashmap.read();
+        let index = hashmap.capacity() * std::mem::size_of::<(Term, HashSet<Term>)>();
+        let values: usize = hashmap
+            .values()
+            .map(|set| set.capacity() * std::mem::size_of::<Term>())
+            .sum();
+        std::mem::size_of::<Self>() + index + values + self.heap.size()
+    }
+
     fn first(&self, _process: &RcProcess) -> Result<Term> {
         unimplemented!()
     }
@@ -34,6 +34,11 @@ impl Table for HashTable {
         &self.meta
     }
 
+    fn memory(&self) -> usize {
+        let index = self.hashmap.read().capacity() * std::mem::size_of::<(Term, Term)>();
+        std::mem::size_of::<Self>() + index + self.heap.size()
+    }
+
     fn first(&self, _process: &RcProcess) -> Result<Term> {
         unimplemented!()
     }
@@ -33,6 +33,11 @@ impl Table for OrderedSet {
         &self.meta
     }
 
+    fn memory(&self) -> usize {
+        let index = self.hashmap.read().len() * std::mem::size_of::<(Term, Term)>();
+        std::mem::size_of::<Self>() + index + self.heap.size()
+    }
+
     fn first(&self, process: &RcProcess) -> Result<Term> {
         let heap = &process.context_mut().heap;
 
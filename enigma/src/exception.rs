@@ -189,6 +189,10 @@ bitflags! {
 
         /// BIF Trap to erlang code
         const TRAP = (1 << Self::EXC_OFFSET.bits);
+        /// BIF ran out of reductions and left a continuation in the execution context
+        const YIELD = (2 << Self::EXC_OFFSET.bits);
+        /// The VM is halting, the process stops where it is without running any more code
+        const HALT = (3 << Self::EXC_OFFSET.bits);
     }
 }
 
@@ -50,6 +50,14 @@ impl ExportsTable {
         // need to clone to avoid keeping a ref too long and lock the table
     }
 
+    /// Drops all the exports pointing into `module`, once its code is purged.
+    pub fn purge(&mut self, module: *const crate::module::Module) {
+        self.exports.retain(|_, export| match export {
+            Export::Fun(ptr) => ptr.module != module,
+            Export::Bif(..) => true,
+        });
+    }
+
     // get or get stub
 }
 
@@ -1,14 +1,33 @@
 //use std::alloc::{Alloc, Global, Layout};
 use allocator_api::{Alloc, Global, Layout};
-use std::cell::Cell;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use crate::bitstring::RcBinary;
+use hashbrown::HashSet;
+use std::cell::{Cell, RefCell};
 use std::cmp;
 use std::mem;
 use std::ptr::{self, NonNull};
 
-// TODO: implement immix lines later on
+/// The number of bytes in a line. Liveness is tracked at line granularity.
+pub const LINE_SIZE: usize = 128;
+
+/// The number of lines in a block.
+pub const LINE_COUNT: usize = 256;
 
 /// The number of bytes in a block (+ the header).
-pub const DEFAULT_BLOCK_SIZE: usize = 32 * 1024 + mem::size_of::<Block>();
+pub const DEFAULT_BLOCK_SIZE: usize = LINE_SIZE * LINE_COUNT + mem::size_of::<Block>();
+
+/// The number of bytes in a heap fragment block (+ the header). Fragments hold messages and other
+/// short lived data that's copied between heaps, so they start out a lot smaller.
+pub const FRAGMENT_BLOCK_SIZE: usize = 1024 + mem::size_of::<Block>();
+
+/// Allocations bigger than this many bytes go into the large object space.
+pub const LARGE_OBJECT_SIZE: usize = 8 * 1024;
+
+/// Maximum number of free blocks the global pool holds on to, any extra are given back to the
+/// system allocator.
+const MAX_POOLED_BLOCKS: usize = 1024;
 
 pub const DEFAULT_BLOCK_ALIGN: usize = mem::align_of::<Block>();
 
@@ -24,6 +43,12 @@ pub struct Block {
 
     /// Cursor to the current free spot when bump allocating.
     ptr: Cell<NonNull<u8>>,
+
+    /// End of the hole we're currently bump allocating in.
+    limit: Cell<NonNull<u8>>,
+
+    /// Line marks, set by the garbage collector for lines holding live objects.
+    lines: [Cell<bool>; LINE_COUNT],
 }
 
 // -- block
@@ -33,6 +58,22 @@ pub struct Block {
 // oo memory space <-- Block.end
 // -- end
 
+/// An allocation that was too big for a block. Large objects get their own allocation and are
+/// never moved, they're either kept alive or freed.
+struct LargeObject {
+    /// Points to the start of the object.
+    data: NonNull<u8>,
+
+    /// Layout of the allocation (including this footer).
+    layout: Layout,
+
+    /// Link to the next large object, if any.
+    next: Cell<Option<NonNull<LargeObject>>>,
+
+    /// Set by the garbage collector if the object is live.
+    marked: Cell<bool>,
+}
+
 #[derive(Debug)]
 pub struct Heap {
     // The current block we are bump allocating within.
@@ -41,58 +82,132 @@ pub struct Heap {
     // The first block we were ever given, which is the head of the intrusive
     // linked list of all blocks this arena has been bump allocating within.
     all_blocks: Cell<NonNull<Block>>,
+
+    // The tail of the block list, new blocks are linked in after it.
+    last_block: Cell<NonNull<Block>>,
+
+    // Block used for medium sized allocations that don't fit in the current hole.
+    overflow_block: Cell<Option<NonNull<Block>>>,
+
+    // Linked list of objects in the large object space.
+    large_objects: Cell<Option<NonNull<LargeObject>>>,
+
+    // Total size of all the blocks and large objects owned by this heap (including headers).
+    size: Cell<usize>,
+
+    // Number of bytes allocated on the heap.
+    used: Cell<usize>,
+
+    // Size of the blocks we allocate (including the header).
+    block_size: usize,
+
+    // Objects on the heap that hold a reference to an off-heap binary (BEAM's MSO list).
+    off_heap: RefCell<Vec<OffHeap>>,
+
+    // Bytes of off-heap binary data referenced from this heap, the binary virtual heap.
+    bin_vheap: Cell<usize>,
+}
+
+/// A reference to an off-heap binary, held by an object allocated on the heap.
+///
+/// Heap values are never dropped, so the heap keeps track of these to release the references
+/// once the holder is gone: either when the heap itself is dropped, or when the garbage collector
+/// finds the holder dead.
+#[derive(Debug)]
+pub struct OffHeap {
+    /// Address of the object holding the reference.
+    pub object: usize,
+    /// The reference itself, somewhere inside of the object.
+    pub binary: *mut RcBinary,
+}
+
+impl OffHeap {
+    /// Drops the reference.
+    ///
+    /// # Safety
+    ///
+    /// The object has to be dead, and must not have been moved elsewhere.
+    pub unsafe fn release(self) {
+        ptr::drop_in_place(self.binary)
+    }
 }
 
 unsafe impl Sync for Heap {}
 unsafe impl Send for Heap {}
 
+/// A block pointer sitting in the global pool.
+struct FreeBlock(NonNull<Block>);
+
+unsafe impl Send for FreeBlock {}
+
+/// Free blocks shared by all the heaps. Heaps take their blocks from the pool and give them back
+/// when they're dropped or swept, so short lived processes rarely hit the system allocator.
+static BLOCK_POOL: Lazy<Mutex<Vec<FreeBlock>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
 #[inline]
 pub(crate) fn round_up_to(n: usize, divisor: usize) -> usize {
     debug_assert!(divisor.is_power_of_two());
     (n + divisor - 1) & !(divisor - 1)
 }
 
+#[inline(never)]
+#[cold]
+fn overflow() -> ! {
+    panic!("allocation too large, caused overflow")
+}
+
+/// Takes a block from the global pool, or allocates a new one if the pool is empty.
+fn acquire_block(block_size: usize) -> NonNull<Block> {
+    if block_size == DEFAULT_BLOCK_SIZE {
+        if let Some(FreeBlock(block)) = BLOCK_POOL.lock().pop() {
+            unsafe { block.as_ref().reset() };
+            return block;
+        }
+    }
+    Block::new(block_size)
+}
+
+/// Returns a block to the global pool, or frees it if the pool is full.
+fn release_block(block: NonNull<Block>) {
+    unsafe {
+        let footer = block.as_ref();
+        if footer.layout.size() == DEFAULT_BLOCK_SIZE {
+            let mut pool = BLOCK_POOL.lock();
+            if pool.len() < MAX_POOLED_BLOCKS {
+                pool.push(FreeBlock(block));
+                return;
+            }
+        }
+        // the header lives inside the block, so read it before we free the memory.
+        let (data, layout) = (footer.data, footer.layout);
+        Global.dealloc(data, layout);
+    }
+}
+
+/// Number of free blocks currently held by the global pool.
+pub fn pooled_blocks() -> usize {
+    BLOCK_POOL.lock().len()
+}
+
 impl Block {
-    fn default_block_layout() -> Layout {
+    fn block_layout(block_size: usize) -> Layout {
         if cfg!(debug_assertions) {
-            Layout::from_size_align(DEFAULT_BLOCK_SIZE, DEFAULT_BLOCK_ALIGN).unwrap()
+            Layout::from_size_align(block_size, DEFAULT_BLOCK_ALIGN).unwrap()
         } else {
-            unsafe { Layout::from_size_align_unchecked(DEFAULT_BLOCK_SIZE, DEFAULT_BLOCK_ALIGN) }
+            unsafe { Layout::from_size_align_unchecked(block_size, DEFAULT_BLOCK_ALIGN) }
         }
     }
 
     /// Allocate a new block and return its initialized header.
-    ///
-    /// If given, `alloc_layout` is the layout of the allocation request that
-    /// triggered us to fall back to allocating a new block of memory.
     #[allow(clippy::cast_ptr_alignment)]
-    fn new(alloc_layout: Option<Layout>) -> NonNull<Block> {
-        let layout = alloc_layout.map_or_else(Block::default_block_layout, |l| {
-            let align = cmp::max(l.align(), mem::align_of::<Block>());
-            if l.size() < DEFAULT_BLOCK_SIZE {
-                // If it is a small allocation, just use our default block size,
-                // but make sure it is aligned for the requested allocation.
-                Layout::from_size_align(DEFAULT_BLOCK_SIZE, align).unwrap()
-            } else {
-                // If the requested allocation is bigger than we can fit in one
-                // of our default blocks, make a special block just for this
-                // allocation.
-                //
-                // Round the size up to a multiple of our header's alignment so
-                // that we can be sure that our header is properly aligned.
-                let size = round_up_to(l.size(), mem::align_of::<Block>());
-                Layout::from_size_align(size + mem::size_of::<Block>(), align).unwrap()
-            }
-        });
-
-        let size = layout.size();
+    fn new(block_size: usize) -> NonNull<Block> {
+        debug_assert!(block_size - mem::size_of::<Block>() <= LINE_SIZE * LINE_COUNT);
+        let layout = Block::block_layout(block_size);
 
         unsafe {
             let data = Global.alloc(layout).unwrap();
 
-            let next = Cell::new(None);
-            let ptr = Cell::new(data);
-            let footer_ptr = data.as_ptr() as usize + size - mem::size_of::<Block>();
+            let footer_ptr = data.as_ptr() as usize + block_size - mem::size_of::<Block>();
             let footer_ptr = footer_ptr as *mut Block;
 
             ptr::write(
@@ -100,24 +215,309 @@ impl Block {
                 Block {
                     data,
                     layout,
-                    next,
-                    ptr,
+                    next: Cell::new(None),
+                    ptr: Cell::new(data),
+                    limit: Cell::new(NonNull::new_unchecked(footer_ptr as *mut u8)),
+                    lines: mem::zeroed(),
+                },
+            );
+            NonNull::new_unchecked(footer_ptr)
+        }
+    }
+
+    #[inline(always)]
+    fn start(&self) -> usize {
+        self.data.as_ptr() as usize
+    }
+
+    #[inline(always)]
+    fn end(&self) -> usize {
+        self as *const Block as usize
+    }
+
+    fn line_count(&self) -> usize {
+        (self.end() - self.start()) / LINE_SIZE
+    }
+
+    /// Bump allocate within the current hole.
+    #[inline(always)]
+    fn try_bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let ptr = round_up_to(self.ptr.get().as_ptr() as usize, layout.align());
+
+        let new_ptr = match ptr.checked_add(layout.size()) {
+            Some(p) => p,
+            None => overflow(),
+        };
+
+        if new_ptr <= self.limit.get().as_ptr() as usize {
+            unsafe {
+                self.ptr.set(NonNull::new_unchecked(new_ptr as *mut u8));
+                Some(NonNull::new_unchecked(ptr as *mut u8))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Bump allocate, moving on to the next holes in the block if the current one is too small.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        loop {
+            if let Some(ptr) = self.try_bump(layout) {
+                return Some(ptr);
+            }
+
+            let line = (self.limit.get().as_ptr() as usize - self.start()) / LINE_SIZE;
+            let (start, end) = self.next_hole(line)?;
+
+            unsafe {
+                let start = self.start() + start * LINE_SIZE;
+                let end = self.start() + end * LINE_SIZE;
+                self.ptr.set(NonNull::new_unchecked(start as *mut u8));
+                self.limit.set(NonNull::new_unchecked(end as *mut u8));
+            }
+        }
+    }
+
+    /// Finds the next run of free lines, starting at line `from`.
+    fn next_hole(&self, from: usize) -> Option<(usize, usize)> {
+        let count = self.line_count();
+        let start = (from..count).find(|&i| !self.lines[i].get())?;
+        let end = (start..count)
+            .find(|&i| self.lines[i].get())
+            .unwrap_or(count);
+        Some((start, end))
+    }
+
+    /// Marks all the lines spanned by an object of `size` bytes at `ptr`.
+    fn mark(&self, ptr: usize, size: usize) {
+        let first = (ptr - self.start()) / LINE_SIZE;
+        let last = (ptr + cmp::max(size, 1) - 1 - self.start()) / LINE_SIZE;
+        for line in &self.lines[first..=last] {
+            line.set(true);
+        }
+    }
+
+    fn marked_lines(&self) -> usize {
+        self.lines[..self.line_count()]
+            .iter()
+            .filter(|line| line.get())
+            .count()
+    }
+
+    fn clear_marks(&self) {
+        for line in self.lines.iter() {
+            line.set(false);
+        }
+    }
+
+    /// Rewinds the cursor, so that allocation restarts at the first hole.
+    fn rewind(&self) {
+        self.ptr.set(self.data);
+        self.limit.set(self.data);
+    }
+
+    /// Resets a recycled block, so it looks freshly allocated.
+    fn reset(&self) {
+        self.clear_marks();
+        self.next.set(None);
+        self.ptr.set(self.data);
+        unsafe {
+            self.limit
+                .set(NonNull::new_unchecked(self.end() as *mut u8));
+        }
+    }
+}
+
+impl LargeObject {
+    #[allow(clippy::cast_ptr_alignment)]
+    fn new(alloc_layout: Layout, next: Option<NonNull<LargeObject>>) -> NonNull<LargeObject> {
+        // Round the size up to a multiple of our footer's alignment so that we can be sure that
+        // our footer is properly aligned.
+        let align = cmp::max(alloc_layout.align(), mem::align_of::<LargeObject>());
+        let size = round_up_to(alloc_layout.size(), mem::align_of::<LargeObject>());
+        let layout = Layout::from_size_align(size + mem::size_of::<LargeObject>(), align).unwrap();
+
+        unsafe {
+            let data = Global.alloc(layout).unwrap();
+            let footer_ptr = (data.as_ptr() as usize + size) as *mut LargeObject;
+
+            ptr::write(
+                footer_ptr,
+                LargeObject {
+                    data,
+                    layout,
+                    next: Cell::new(next),
+                    marked: Cell::new(false),
                 },
             );
             NonNull::new_unchecked(footer_ptr)
         }
     }
+
+    /// Frees the object, returning the next one in the list.
+    unsafe fn free(object: NonNull<LargeObject>) -> Option<NonNull<LargeObject>> {
+        // the footer lives inside the allocation, so read it before we free the memory.
+        let footer = object.as_ref();
+        let (data, layout, next) = (footer.data, footer.layout, footer.next.get());
+        Global.dealloc(data, layout);
+        next
+    }
+}
+
+/// A contiguous piece of memory owned by a heap: either a block or a large object.
+#[derive(Clone, Copy)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+    space: Space,
+}
+
+#[derive(Clone, Copy)]
+enum Space {
+    Block(NonNull<Block>),
+    Large(NonNull<LargeObject>),
+}
+
+impl Region {
+    /// Marks an object of `size` bytes at `ptr` as live.
+    pub fn mark(&self, ptr: usize, size: usize) {
+        debug_assert!(ptr >= self.start && ptr < self.end);
+        unsafe {
+            match self.space {
+                Space::Block(block) => block.as_ref().mark(ptr, size),
+                Space::Large(object) => object.as_ref().marked.set(true),
+            }
+        }
+    }
 }
 
 impl Heap {
     pub fn new() -> Self {
-        let block = Block::new(None);
+        Heap::with_block_size(DEFAULT_BLOCK_SIZE)
+    }
+
+    /// A small heap, used for copying messages and signals between processes.
+    pub fn fragment() -> Self {
+        Heap::with_block_size(FRAGMENT_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(block_size: usize) -> Self {
+        debug_assert!(block_size > mem::size_of::<Block>());
+        let block = acquire_block(block_size);
         Heap {
             current_block: Cell::new(block),
             all_blocks: Cell::new(block),
+            last_block: Cell::new(block),
+            overflow_block: Cell::new(None),
+            large_objects: Cell::new(None),
+            size: Cell::new(block_size),
+            used: Cell::new(0),
+            block_size,
+            off_heap: RefCell::new(Vec::new()),
+            bin_vheap: Cell::new(0),
         }
     }
 
+    /// Total size of the heap in bytes, including any unused space in the blocks.
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// Number of bytes allocated on the heap. After a sweep this is the size of the marked lines.
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Bytes of off-heap binary data referenced from the heap.
+    pub fn bin_vheap(&self) -> usize {
+        self.bin_vheap.get()
+    }
+
+    /// Accounts for `size` bytes of newly referenced binary data on the binary virtual heap.
+    /// Sub binaries and match contexts point into a binary that's already accounted for, so
+    /// only new binaries (and binaries received from other processes) are added.
+    pub fn grow_bin_vheap(&self, size: usize) {
+        self.bin_vheap.set(self.bin_vheap.get() + size);
+    }
+
+    /// Registers a reference to an off-heap binary, held by the object at `object`.
+    pub fn track_binary(&self, object: usize, binary: *mut RcBinary) {
+        self.off_heap.borrow_mut().push(OffHeap { object, binary });
+    }
+
+    /// The distinct off-heap binaries referenced from the heap.
+    pub fn binaries(&self) -> Vec<RcBinary> {
+        let mut seen = HashSet::new();
+        self.off_heap
+            .borrow()
+            .iter()
+            .map(|entry| unsafe { &*entry.binary })
+            .filter(|binary| seen.insert(binary.heap_ptr() as usize))
+            .cloned()
+            .collect()
+    }
+
+    /// Takes the off-heap references out of the heap, for the garbage collector to sort through.
+    pub fn take_off_heap(&self) -> Vec<OffHeap> {
+        self.off_heap.replace(Vec::new())
+    }
+
+    /// Hands back the references that survived a collection, and recomputes the binary virtual
+    /// heap from them. Binaries referenced more than once are only counted once.
+    pub fn restore_off_heap(&self, mut entries: Vec<OffHeap>) {
+        let mut off_heap = self.off_heap.borrow_mut();
+        off_heap.append(&mut entries);
+
+        let mut seen = HashSet::new();
+        let size = off_heap
+            .iter()
+            .map(|entry| unsafe { &*entry.binary })
+            .filter(|binary| seen.insert(binary.heap_ptr() as usize))
+            .map(|binary| binary.data.len())
+            .sum();
+        self.bin_vheap.set(size);
+    }
+
+    /// Address ranges `(start, end)` of all the memory owned by this heap.
+    pub fn ranges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.regions().map(|region| (region.start, region.end))
+    }
+
+    /// All the blocks and large objects owned by this heap.
+    pub fn regions(&self) -> impl Iterator<Item = Region> + '_ {
+        let blocks = self.blocks().map(|block| Region {
+            start: block.start(),
+            end: block.end(),
+            space: Space::Block(NonNull::from(block)),
+        });
+        let large = self.large_objects().map(|object| Region {
+            start: object.data.as_ptr() as usize,
+            end: object as *const LargeObject as usize,
+            space: Space::Large(NonNull::from(object)),
+        });
+        blocks.chain(large)
+    }
+
+    /// Returns true if the pointer points into memory owned by this heap.
+    pub fn contains<T>(&self, ptr: *const T) -> bool {
+        let ptr = ptr as usize;
+        self.ranges().any(|(start, end)| ptr >= start && ptr < end)
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = &Block> {
+        std::iter::successors(Some(self.all_blocks.get()), |block| unsafe {
+            block.as_ref().next.get()
+        })
+        .map(|block| unsafe { &*block.as_ptr() })
+    }
+
+    fn large_objects(&self) -> impl Iterator<Item = &LargeObject> {
+        std::iter::successors(self.large_objects.get(), |object| unsafe {
+            object.as_ref().next.get()
+        })
+        .map(|object| unsafe { &*object.as_ptr() })
+    }
+
     /// Allocate an object.
     ///
     /// ## Example
@@ -141,73 +541,315 @@ impl Heap {
 
     #[inline(always)]
     pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        self.used.set(self.used.get() + layout.size());
+
+        // large objects never go into a block, even if they'd fit the current hole
+        if layout.size() > self.large_object_size() {
+            return self.alloc_large(layout);
+        }
+
+        let block = unsafe { self.current_block.get().as_ref() };
+        match block.try_bump(layout) {
+            Some(p) => p,
+            None => self.alloc_layout_slow(layout),
+        }
+    }
+
+    // Slow path allocation for when the current hole doesn't have enough room left.
+    #[inline(never)]
+    fn alloc_layout_slow(&self, layout: Layout) -> NonNull<u8> {
         unsafe {
-            let footer = self.current_block.get();
-            let footer = footer.as_ref();
-            let ptr = footer.ptr.get().as_ptr() as usize;
-            let ptr = round_up_to(ptr, layout.align());
-            let end = footer as *const _ as usize;
-            debug_assert!(ptr <= end);
-
-            let new_ptr = match ptr.checked_add(layout.size()) {
-                Some(p) => p,
-                None => self.overflow(),
-            };
-
-            if new_ptr <= end {
-                let p = ptr as *mut u8;
-                debug_assert!(new_ptr <= footer as *const _ as usize);
-                footer.ptr.set(NonNull::new_unchecked(new_ptr as *mut u8));
-                return NonNull::new_unchecked(p);
+            if layout.size() > LINE_SIZE {
+                // Medium sized objects go into an overflow block instead: skipping over the
+                // remaining holes would waste them, and they're still good for small objects.
+                if let Some(block) = self.overflow_block.get() {
+                    if let Some(p) = block.as_ref().try_bump(layout) {
+                        return p;
+                    }
+                }
+                let block = self.new_block();
+                self.overflow_block.set(Some(block));
+                return block.as_ref().try_bump(layout).unwrap();
+            }
+
+            // Look for the next hole in the current block and the ones after it, only then grab
+            // a new block.
+            let mut block = self.current_block.get();
+            loop {
+                if let Some(p) = block.as_ref().bump(layout) {
+                    self.current_block.set(block);
+                    return p;
+                }
+                block = match block.as_ref().next.get() {
+                    Some(next) => next,
+                    None => self.new_block(),
+                };
             }
         }
+    }
 
-        // TODO: verify new block alloc works
-        self.alloc_layout_slow(layout)
+    fn large_object_size(&self) -> usize {
+        cmp::min(LARGE_OBJECT_SIZE, self.block_size - mem::size_of::<Block>())
     }
 
-    #[inline(never)]
-    #[cold]
-    fn overflow(&self) -> ! {
-        panic!("allocation too large, caused overflow")
+    /// Gets a new block from the global pool and links it in at the end of the block list.
+    fn new_block(&self) -> NonNull<Block> {
+        let block = acquire_block(self.block_size);
+        unsafe { self.last_block.get().as_ref().next.set(Some(block)) };
+        self.last_block.set(block);
+        self.size.set(self.size.get() + self.block_size);
+        block
     }
 
-    // Slow path allocation for when we need to allocate a new block from the
-    // parent bump set because there isn't enough room in our current block.
-    #[inline(never)]
-    fn alloc_layout_slow(&self, layout: Layout) -> NonNull<u8> {
+    fn alloc_large(&self, layout: Layout) -> NonNull<u8> {
+        let object = LargeObject::new(layout, self.large_objects.get());
+        self.large_objects.set(Some(object));
         unsafe {
-            // Get a new block from the global allocator.
-            let size = layout.size();
-            let footer = Block::new(Some(layout));
-
-            // Set our current block's next link to this new block.
-            self.current_block.get().as_ref().next.set(Some(footer));
-
-            // Set the new block as our new current block.
-            self.current_block.set(footer);
-
-            // Move the bump ptr finger ahead to allocate room for `val`.
-            let footer = footer.as_ref();
-            let ptr = footer.ptr.get().as_ptr() as usize + size;
-            debug_assert!(
-                ptr <= footer as *const _ as usize,
-                "{} <= {}",
-                ptr,
-                footer as *const _ as usize
-            );
-            footer.ptr.set(NonNull::new_unchecked(ptr as *mut u8));
+            let object = object.as_ref();
+            self.size.set(self.size.get() + object.layout.size());
+            object.data
+        }
+    }
 
-            // Return a pointer to the start of this block.
-            footer.data.cast::<u8>()
+    /// Clears all the marks, before a collection marks the live objects again.
+    pub fn clear_marks(&self) {
+        for block in self.blocks() {
+            block.clear_marks();
+        }
+        for object in self.large_objects() {
+            object.marked.set(false);
         }
     }
 
-    // pub fn dealloc_block(ptr: BlockPtr, size: BlockSize) {
-    //     unsafe {
-    //         let layout = Layout::from_size_align_unchecked(size, size);
+    /// Releases all the memory that wasn't marked during the collection. Free blocks go back to
+    /// the global pool and unmarked large objects are freed. Partly free blocks are kept, and
+    /// allocation restarts at their first hole.
+    pub fn sweep(&self) {
+        let mut size = 0;
+        let mut used = 0;
+
+        unsafe {
+            let mut head: Option<NonNull<Block>> = None;
+            let mut tail: Option<NonNull<Block>> = None;
+            let mut next = Some(self.all_blocks.get());
+
+            while let Some(block) = next {
+                let footer = block.as_ref();
+                next = footer.next.get();
+
+                let marked = footer.marked_lines();
+                if marked == 0 {
+                    release_block(block);
+                    continue;
+                }
+
+                footer.next.set(None);
+                footer.rewind();
+                match tail {
+                    Some(tail) => tail.as_ref().next.set(Some(block)),
+                    None => head = Some(block),
+                }
+                tail = Some(block);
+                size += footer.layout.size();
+                used += marked * LINE_SIZE;
+            }
+
+            // we always need a block to allocate in
+            if head.is_none() {
+                let block = acquire_block(self.block_size);
+                head = Some(block);
+                tail = Some(block);
+                size += self.block_size;
+            }
+
+            let head = head.unwrap();
+            self.all_blocks.set(head);
+            self.current_block.set(head);
+            self.last_block.set(tail.unwrap());
+            self.overflow_block.set(None);
+
+            let mut objects = None;
+            let mut next = self.large_objects.get();
+
+            while let Some(object) = next {
+                let footer = object.as_ref();
+                if footer.marked.get() {
+                    next = footer.next.get();
+                    footer.next.set(objects);
+                    objects = Some(object);
+                    size += footer.layout.size();
+                    used += footer.layout.size();
+                } else {
+                    next = LargeObject::free(object);
+                }
+            }
+            self.large_objects.set(objects);
+        }
+
+        self.size.set(size);
+        self.used.set(used);
+    }
+
+    /// Moves all the memory owned by `other` into this heap.
+    pub fn append(&self, other: Heap) {
+        unsafe {
+            self.last_block
+                .get()
+                .as_ref()
+                .next
+                .set(Some(other.all_blocks.get()));
+            self.last_block.set(other.last_block.get());
+
+            if let Some(last) = other.large_objects().last() {
+                last.next.set(self.large_objects.get());
+                self.large_objects.set(other.large_objects.get());
+            }
+        }
+
+        self.size.set(self.size.get() + other.size());
+        self.used.set(self.used.get() + other.used());
+        self.bin_vheap.set(self.bin_vheap.get() + other.bin_vheap());
+        self.off_heap
+            .borrow_mut()
+            .extend(other.off_heap.replace(Vec::new()));
+        // the memory belongs to us now
+        mem::forget(other);
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        // NOTE: values stored on the heap are not dropped, we only release the memory and the
+        // off-heap binary references.
+        for entry in self.off_heap.get_mut().drain(..) {
+            unsafe { entry.release() };
+        }
+
+        let mut next = Some(self.all_blocks.get());
+
+        while let Some(block) = next {
+            next = unsafe { block.as_ref().next.get() };
+            release_block(block);
+        }
 
-    //         Global.dealloc(ptr, layout);
-    //     }
-    // }
+        let mut next = self.large_objects.get();
+
+        while let Some(object) = next {
+            next = unsafe { LargeObject::free(object) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heap_grows_by_blocks() {
+        let heap = Heap::new();
+        assert_eq!(heap.size(), DEFAULT_BLOCK_SIZE);
+
+        // fill up more than a block
+        for i in 0..5000 {
+            heap.alloc(i as u64);
+        }
+
+        assert_eq!(heap.size(), 2 * DEFAULT_BLOCK_SIZE);
+        assert_eq!(heap.used(), 5000 * mem::size_of::<u64>());
+    }
+
+    #[test]
+    fn test_heap_contains() {
+        let heap = Heap::fragment();
+        let other = Heap::fragment();
+        let x = heap.alloc(1u64) as *const u64;
+
+        assert!(heap.contains(x));
+        assert!(!other.contains(x));
+    }
+
+    #[test]
+    fn test_large_allocation() {
+        let heap = Heap::fragment();
+        let layout = Layout::from_size_align(4 * FRAGMENT_BLOCK_SIZE, 8).unwrap();
+        let ptr = heap.alloc_layout(layout);
+
+        assert!(heap.contains(ptr.as_ptr()));
+        assert!(heap.size() > 4 * FRAGMENT_BLOCK_SIZE);
+    }
+
+    #[test]
+    fn test_large_object_space() {
+        let heap = Heap::new();
+        let layout = Layout::from_size_align(2 * LARGE_OBJECT_SIZE, 8).unwrap();
+        let ptr = heap.alloc_layout(layout);
+
+        assert!(heap.contains(ptr.as_ptr()));
+        // the object didn't take up a block
+        assert_eq!(heap.blocks().count(), 1);
+        assert_eq!(heap.large_objects().count(), 1);
+
+        // unmarked large objects are freed by a sweep
+        heap.clear_marks();
+        heap.sweep();
+        assert_eq!(heap.large_objects().count(), 0);
+        assert_eq!(heap.size(), DEFAULT_BLOCK_SIZE);
+    }
+
+    #[test]
+    fn test_large_object_skips_current_hole() {
+        // a fresh block has room for the object, it still goes into the large object space
+        let heap = Heap::new();
+        let layout = Layout::from_size_align(heap.large_object_size() + 8, 8).unwrap();
+        let ptr = heap.alloc_layout(layout);
+
+        assert_eq!(heap.large_objects().count(), 1);
+        assert_eq!(heap.large_objects().next().unwrap().data, ptr);
+        assert_eq!(heap.blocks().count(), 1);
+    }
+
+    #[test]
+    fn test_sweep_releases_free_blocks() {
+        let heap = Heap::new();
+        for i in 0..10_000 {
+            heap.alloc(i as u64);
+        }
+        assert_eq!(heap.blocks().count(), 3);
+
+        // keep a value alive in the last block only
+        let x = heap.alloc(1u64) as *const u64 as usize;
+        heap.clear_marks();
+        let region = heap.regions().find(|r| x >= r.start && x < r.end).unwrap();
+        region.mark(x, mem::size_of::<u64>());
+        heap.sweep();
+
+        assert_eq!(heap.blocks().count(), 1);
+        assert_eq!(heap.size(), DEFAULT_BLOCK_SIZE);
+        assert_eq!(heap.used(), LINE_SIZE);
+        assert!(heap.contains(x as *const u64));
+    }
+
+    #[test]
+    fn test_sweep_reuses_holes() {
+        let heap = Heap::new();
+        let lines: Vec<usize> = (0..4)
+            .map(|_| heap.alloc([0u64; LINE_SIZE / 8]) as *const _ as usize)
+            .collect();
+
+        // line 1 stays live
+        heap.clear_marks();
+        heap.regions().next().unwrap().mark(lines[1], LINE_SIZE);
+        heap.sweep();
+
+        // a medium sized object doesn't fit into the first hole, it overflows into a new block
+        let medium = heap.alloc([0u64; LINE_SIZE / 4]) as *const _ as usize;
+        assert!(!(lines[0]..lines[3] + LINE_SIZE).contains(&medium));
+        assert_eq!(heap.blocks().count(), 2);
+
+        // small objects fill the holes around the live line
+        let a = heap.alloc([0u64; LINE_SIZE / 8]) as *const _ as usize;
+        let b = heap.alloc([0u64; LINE_SIZE / 8]) as *const _ as usize;
+        assert_eq!(a, lines[0]);
+        assert_eq!(b, lines[2]);
+    }
 }
@@ -8,7 +8,7 @@ use crate::instruction;
 use crate::process::{self, RcProcess};
 use crate::value::{self, CastFrom, CastInto, CastIntoMut, Cons, Term, Tuple, Variant};
 use crate::vm::Machine;
-use crate::{atom, bif, bitstring, module, port};
+use crate::{atom, bif, bitstring, module, port, scheduler};
 
 use futures::prelude::*;
 // end mandatory for loop
@@ -442,7 +442,10 @@ macro_rules! op_call_ext {
         let export = { $vm.exports.read().lookup(mfa) }; // drop the exports lock
 
         match export {
-            Some(Export::Fun(ptr)) => op_jump_ptr!($context, ptr),
+            Some(Export::Fun(ptr)) => {
+                $process.save_call(process::SavedCall::Call(*mfa));
+                op_jump_ptr!($context, ptr)
+            }
             // Some(Export::Bif(APPLY_2)) => unreachable!("apply/2 called via call_ext"),
             // Some(Export::Bif(APPLY_3)) => unreachable!("apply/3 called via call_ext"),
             Some(Export::Bif(_)) => unreachable!("bif called without call_bif: {}", mfa),
@@ -459,20 +462,62 @@ macro_rules! op_call_ext {
     }};
 }
 
+/// Calls a BIF that was reached through an apply or a fun call. `$tail` is set when the calling
+/// instruction was a tail call, in which case we return to the caller once the BIF is done.
 macro_rules! op_call_bif {
-    ($vm:expr, $context:expr, $process:expr, $bif:expr, $arity:expr) => {{
+    ($vm:expr, $context:expr, $process:expr, $bif:expr, $arity:expr, $tail:expr) => {{
         // make a slice out of arity x registers
         let args = &$context.x[0..$arity];
+        $context.bump_reductions(1);
         match $bif($vm, $process, args) {
             Ok(val) => {
                 $context.x[0] = val;
+                if $tail {
+                    op_return!($process, $context);
+                }
             }
+            Err(ref exc) if exc.reason == Reason::YIELD => op_yield_bif!($context, $tail),
             Err(exc) => return Err(exc),
         }
     }};
 }
+
+/// The BIF ran out of reductions, swap the process out. It gets resumed on the next run.
+macro_rules! op_yield_bif {
+    ($context:expr, $tail:expr) => {{
+        if let Some(trap) = &mut $context.trap {
+            trap.tail = $tail;
+        }
+        return Ok(process::State::Yield);
+    }};
+}
+
+/// Calls the BIF that yielded on the last run again, then continues where the original call
+/// would have. Dirty BIFs get handed over to a dirty scheduler, the process waits for them to
+/// finish without holding on to a scheduler slot.
+macro_rules! op_resume_bif {
+    ($vm:expr, $context:expr, $process:expr) => {{
+        if let Some(trap) = $context.trap.take() {
+            let args = &$context.x[0..trap.arity];
+            let res = match trap.dirty {
+                Some(dirty) => scheduler::run_dirty($vm, $process, dirty, trap.bif, args).await,
+                None => (trap.bif)($vm, $process, args),
+            };
+            match res {
+                Ok(val) => {
+                    $context.x[0] = val;
+                    if trap.tail {
+                        op_return!($process, $context);
+                    }
+                }
+                Err(ref exc) if exc.reason == Reason::YIELD => op_yield_bif!($context, trap.tail),
+                Err(exc) => return Err(exc),
+            }
+        }
+    }};
+}
 macro_rules! op_call_fun {
-    ($vm:expr, $context:expr, $process:expr, $value:expr, $arity:expr) => {{
+    ($vm:expr, $context:expr, $process:expr, $value:expr, $arity:expr, $tail:expr) => {{
         if let Ok(closure) = value::Closure::cast_from(&$value) {
             // keep X regs set based on arity
             // set additional X regs based on lambda.binding
@@ -502,7 +547,7 @@ macro_rules! op_call_fun {
             match export {
                 Some(Export::Fun(ptr)) => op_jump_ptr!($context, ptr),
                 Some(Export::Bif(bif)) => {
-                    op_call_bif!($vm, $context, &$process, bif, mfa.2 as usize)
+                    op_call_bif!($vm, $context, &$process, bif, mfa.2 as usize, $tail)
                 }
                 None => {
                     // println!("apply setup_error_handler");
@@ -517,109 +562,121 @@ macro_rules! op_call_fun {
 }
 
 macro_rules! op_apply {
-    ($vm:expr, $context:expr, $process:expr) => {{
-        let mut module = $context.x[0];
-        let mut func = $context.x[1];
-        let mut args = $context.x[2];
-
-        loop {
-            if !func.is_atom() || !module.is_atom() {
-                $context.x[0] = module;
-                $context.x[1] = func;
-                $context.x[2] = Term::nil();
-                println!(
-                    "pid={} tried to apply not a func {}:{}",
-                    $process.pid, module, func
-                );
-
-                return Err(badarg!());
-            }
-
-            if module.to_atom().unwrap() != atom::ERLANG || func.to_atom().unwrap() != atom::APPLY {
-                break;
-            }
+    ($vm:expr, $context:expr, $process:expr, $tail:expr) => {{
+        // loops around when applying erlang:hibernate/3
+        'apply: loop {
+            let mut module = $context.x[0];
+            let mut func = $context.x[1];
+            let mut args = $context.x[2];
+
+            loop {
+                if !func.is_atom() || !module.is_atom() {
+                    $context.x[0] = module;
+                    $context.x[1] = func;
+                    $context.x[2] = Term::nil();
+                    println!(
+                        "pid={} tried to apply not a func {}:{}",
+                        $process.pid, module, func
+                    );
+
+                    return Err(badarg!());
+                }
 
-            // Handle apply of apply/3...
+                if module.to_atom().unwrap() != atom::ERLANG || func.to_atom().unwrap() != atom::APPLY {
+                    break;
+                }
 
-            // continually loop over args to resolve
-            let a = args;
+                // Handle apply of apply/3...
 
-            if let Ok(cons) = Cons::cast_from(&a) {
-                let m = cons.head;
-                let a = cons.tail;
+                // continually loop over args to resolve
+                let a = args;
 
                 if let Ok(cons) = Cons::cast_from(&a) {
-                    let f = cons.head;
+                    let m = cons.head;
                     let a = cons.tail;
 
                     if let Ok(cons) = Cons::cast_from(&a) {
-                        let a = cons.head;
-                        if cons.tail.is_nil() {
-                            module = m;
-                            func = f;
-                            args = a;
-                            continue;
+                        let f = cons.head;
+                        let a = cons.tail;
+
+                        if let Ok(cons) = Cons::cast_from(&a) {
+                            let a = cons.head;
+                            if cons.tail.is_nil() {
+                                module = m;
+                                func = f;
+                                args = a;
+                                continue;
+                            }
                         }
                     }
                 }
+                break; // != erlang:apply/3
             }
-            break; // != erlang:apply/3
-        }
 
-        let mut arity = 0;
+            let mut arity = 0;
 
-        // Walk down the 3rd parameter of apply (the argument list) and copy
-        // the parameters to the x registers (reg[]).
+            // Walk down the 3rd parameter of apply (the argument list) and copy
+            // the parameters to the x registers (reg[]).
 
-        while let Ok(value::Cons { head, tail }) = args.cast_into() {
-            if arity < process::MAX_REG - 1 {
-                $context.x[arity] = *head;
-                arity += 1;
-                args = *tail
-            } else {
-                return Err(Exception::new(Reason::EXC_SYSTEM_LIMIT));
+            while let Ok(value::Cons { head, tail }) = args.cast_into() {
+                if arity < process::MAX_REG - 1 {
+                    $context.x[arity] = *head;
+                    arity += 1;
+                    args = *tail
+                } else {
+                    return Err(Exception::new(Reason::EXC_SYSTEM_LIMIT));
+                }
             }
-        }
 
-        if !args.is_nil() {
-            // Must be well-formed list
-            return Err(badarg!());
-        }
+            if !args.is_nil() {
+                // Must be well-formed list
+                return Err(badarg!());
+            }
 
-        /*
-         * Get the index into the export table, or failing that the export
-         * entry for the error handler module.
-         *
-         * Note: All BIFs have export entries; thus, no special case is needed.
-         */
+            /*
+             * Get the index into the export table, or failing that the export
+             * entry for the error handler module.
+             *
+             * Note: All BIFs have export entries; thus, no special case is needed.
+             */
 
-        let mfa = module::MFA(module.to_atom().unwrap(), func.to_atom().unwrap(), arity as u32);
+            let mfa = module::MFA(module.to_atom().unwrap(), func.to_atom().unwrap(), arity as u32);
 
-        // println!("pid={} applying/3... {}", $process.pid, mfa);
+            // println!("pid={} applying/3... {}", $process.pid, mfa);
 
-        let export = { $vm.exports.read().lookup(&mfa) }; // drop the exports lock
+            let export = { $vm.exports.read().lookup(&mfa) }; // drop the exports lock
 
-        match export {
-            Some(Export::Fun(ptr)) => op_jump_ptr!($context, ptr),
-            // Workaround for https://github.com/rust-lang/rust/issues/63479
-            Some(Export::Bif(bif)) if (bif as usize) == (APPLY_2 as usize) => {
-                // TODO: rewrite these two into Apply instruction calls
-                // I'm cheating here, *shrug*
-                op_apply_fun!($vm, $context, $process)
-            }
-            // Some(Export::Bif(_)) => unreachable!("op_apply: bif called without call_bif: {}", mfa),
-            Some(Export::Bif(bif)) => {
-                // TODO: this would still need to return on a bif if it's i_apply_only/_last?
+            match export {
+                Some(Export::Fun(ptr)) => {
+                    $process.save_call(process::SavedCall::Call(mfa));
+                    op_jump_ptr!($context, ptr)
+                }
+                // Workaround for https://github.com/rust-lang/rust/issues/63479
+                Some(Export::Bif(bif)) if (bif as usize) == (APPLY_2 as usize) => {
+                    // TODO: rewrite these two into Apply instruction calls
+                    // I'm cheating here, *shrug*
+                    op_apply_fun!($vm, $context, $process, $tail)
+                }
+                Some(Export::Bif(bif)) if (bif as usize) == (HIBERNATE_3 as usize) => {
+                    // wakes up with the call to make in x0..x2
+                    scheduler::hibernate($vm, &$process).await?;
+                    continue 'apply;
+                }
+                // Some(Export::Bif(_)) => unreachable!("op_apply: bif called without call_bif: {}", mfa),
+                Some(Export::Bif(bif)) => {
+                    // TODO: this would still need to return on a bif if it's i_apply_only/_last?
 
-                // TODO: apply_bif_error_adjustment(p, ep, reg, arity, I, stack_offset);
-                // ^ only happens in apply/fixed_apply
-                op_call_bif!($vm, $context, $process, bif, arity)
-            }
-            None => {
-                // println!("apply setup_error_handler pid={}", $process.pid);
-                call_error_handler!($vm, $process, &mfa);
-                // apply_setup_error_handler
+                    // TODO: apply_bif_error_adjustment(p, ep, reg, arity, I, stack_offset);
+                    // ^ only happens in apply/fixed_apply
+                    op_call_bif!($vm, $context, $process, bif, arity, $tail)
+                }
+                None => {
+                    // println!("apply setup_error_handler pid={}", $process.pid);
+                    call_error_handler!($vm, $process, &mfa);
+                    // apply_setup_error_handler
+                }
             }
+            break;
         }
         //    if ((ep = erts_active_export_entry(module, function, arity)) == NULL) {
         //      if ((ep = apply_setup_error_handler(p, module, function, arity, reg)) == NULL)
@@ -633,7 +690,7 @@ macro_rules! op_apply {
 }
 
 macro_rules! op_apply_fun {
-    ($vm:expr, $context:expr, $process:expr) => {{
+    ($vm:expr, $context:expr, $process:expr, $tail:expr) => {{
         // Walk down the 3rd parameter of apply (the argument list) and copy
         // the parameters to the x registers (reg[]).
 
@@ -657,7 +714,7 @@ macro_rules! op_apply_fun {
         }
         //context.x[arity] = fun;
 
-        op_call_fun!($vm, $context, $process, fun, arity);
+        op_call_fun!($vm, $context, $process, fun, arity, $tail);
     }};
 }
 
@@ -691,7 +748,7 @@ macro_rules! cond_fail {
 }
 
 macro_rules! op_fixed_apply {
-    ($vm:expr, $context:expr, $process:expr, $arity:expr) => {{
+    ($vm:expr, $context:expr, $process:expr, $arity:expr, $tail:expr) => {{
         let arity = $arity as usize;
         let module = $context.x[arity];
         let func = $context.x[arity + 1];
@@ -709,7 +766,14 @@ macro_rules! op_fixed_apply {
 
         // Handle apply of apply/3...
         if module == atom::ERLANG && func == atom::APPLY && $arity == 3 {
-            op_apply!($vm, $context, $process);
+            op_apply!($vm, $context, $process, $tail);
+            continue;
+        }
+
+        if module == atom::ERLANG && func == atom::HIBERNATE && $arity == 3 {
+            scheduler::hibernate($vm, &$process).await?;
+            // the stack is gone, so this is a tail call no matter how we got here
+            op_apply!($vm, $context, $process, true);
             continue;
         }
 
@@ -726,12 +790,15 @@ macro_rules! op_fixed_apply {
         let export = { $vm.exports.read().lookup(&mfa) }; // drop the exports lock
 
         match export {
-            Some(Export::Fun(ptr)) => op_jump_ptr!($context, ptr),
+            Some(Export::Fun(ptr)) => {
+                $process.save_call(process::SavedCall::Call(mfa));
+                op_jump_ptr!($context, ptr)
+            }
             Some(Export::Bif(bif)) => {
                 // TODO: apply_bif_error_adjustment(p, ep, reg, arity, I, stack_offset);
                 // ^ only happens in apply/fixed_apply
 
-                op_call_bif!($vm, $context, $process, bif, arity)
+                op_call_bif!($vm, $context, $process, bif, arity, $tail)
             }
             None => {
                 // println!("fixed_apply setup_error_handler pid={}", $process.pid);
@@ -751,14 +818,14 @@ macro_rules! op_fixed_apply {
 }
 
 macro_rules! safepoint_and_reduce {
-    ($vm:expr, $process:expr, $reductions:expr) => {{
+    ($vm:expr, $process:expr, $context:expr) => {{
         // if $vm.gc_safepoint(&$process) {
         //     return Ok(());
         // }
 
         // Reduce once we've exhausted all the instructions in a context.
-        if $reductions > 0 {
-            $reductions -= 1;
+        if $context.reds > 0 {
+            $context.bump_reductions(1);
         } else {
             // $vm
             //     .process_pool
@@ -782,6 +849,7 @@ macro_rules! op_float {
 
 const APPLY_2: bif::Fn = bif::bif_erlang_apply_2;
 const APPLY_3: bif::Fn = bif::bif_erlang_apply_3;
+const HIBERNATE_3: bif::Fn = bif::bif_erlang_hibernate_3;
 
 instruction!(
     fn func_info(module: s, function: s, arity: t) {
@@ -805,14 +873,20 @@ instruction!(
         // send x1 to x0, write result to x0
         let pid = context.x[0];
         let msg = context.x[1];
+        process.save_call(process::SavedCall::Send);
         let res = match pid.into_variant() {
-            Variant::Port(id) => port::send_message(vm, process.pid, id, msg),
-            _ => process::send_message(vm, process.pid, pid, msg),
-        }?;
+            Variant::Port(id) => port::send_message(vm, process.pid, id, msg)?,
+            _ => {
+                let size = process::send_message(vm, process.pid, pid, msg)?;
+                context.bump_reductions(process::send_reductions(size));
+                msg
+            }
+        };
         context.x[0] = res;
     },
     fn remove_message() {
         // Unlink the current message from the message queue. Remove any timeout.
+        process.save_call(process::SavedCall::Receive);
         process.local_data_mut().mailbox.remove();
         // clear timeout
         context.timeout.take();
@@ -821,6 +895,7 @@ instruction!(
     },
     fn timeout() {
         //  Reset the save point of the mailbox and clear the timeout flag.
+        process.save_call(process::SavedCall::Timeout);
         process.local_data_mut().mailbox.reset();
         // clear timeout
         context.timeout.take();
@@ -854,10 +929,14 @@ instruction!(
 
         // LOCK mailbox on looprec, unlock on wait/waittimeout
         let cancel = process.context_mut().recv_channel.take().unwrap();
-        cancel.await; // suspend process
+        // suspend process, letting others use our scheduler slot
+        scheduler::suspend(vm, process, cancel).await;
 
         // println!("pid={} resumption ", process.pid);
         process.process_incoming()?;
+        scheduler::wait_suspended(vm, process).await?;
+        // no X registers are live while waiting
+        process::gc::maybe_collect(vm, &process, 0)?;
     },
     fn wait_timeout(label: l, time: s) {
         // Sets up a timeout of Time milliseconds and saves the address of the
@@ -878,14 +957,15 @@ instruction!(
                 // println!("infinity wait");
                 op_jump!(context, label);
 
-                cancel.await; // suspend process
+                // suspend process, letting others use our scheduler slot
+                scheduler::suspend(vm, process, cancel).await;
                 // println!("select! resumption pid={}", process.pid);
             },
             Variant::Integer(ms) => {
                 let when = time::Duration::from_millis(ms as u64);
                 use tokio::future::FutureExt;
 
-                match cancel.timeout(when).await {
+                match scheduler::suspend(vm, process, cancel.timeout(when)).await {
                     Ok(_) =>  {
                         // jump to success (start of recv loop)
                         op_jump!(context, label);
@@ -927,12 +1007,19 @@ instruction!(
             _ => unreachable!("{}", context.expand_arg(time))
         }
         process.process_incoming()?;
+        scheduler::wait_suspended(vm, process).await?;
+        // no X registers are live while waiting
+        process::gc::maybe_collect(vm, &process, 0)?;
     },
-    fn recv_mark(_fail: l) {
-        process.local_data_mut().mailbox.mark();
+    fn recv_mark(fail: l) {
+        // a reference is about to be made, that the receive at `fail` matches on. The messages
+        // already queued up can't contain it, so the receive can skip them.
+        let module = context.ip.module as usize;
+        process.local_data_mut().mailbox.mark((module, fail));
     },
-    fn recv_set(_fail: l) {
-        process.local_data_mut().mailbox.set();
+    fn recv_set(fail: l) {
+        let module = context.ip.module as usize;
+        process.local_data_mut().mailbox.set((module, fail));
     },
     fn call(arity: t, label: l) {
         // store arity as live
@@ -943,7 +1030,7 @@ instruction!(
         //     let (mfa, _) = context.ip.lookup_func_info().unwrap();
         //     info!("pid={} action=call mfa={}", process.pid, mfa);
         // }
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn call_last(arity: t, label: l, words: r) {
         // store arity as live
@@ -954,7 +1041,7 @@ instruction!(
         // let (mfa, _) = context.ip.lookup_func_info().unwrap();
         // info!("pid={} action=call_last mfa={}", process.pid, mfa);
         // }
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn call_only(arity: t, label: l) {
         // store arity as live
@@ -964,7 +1051,7 @@ instruction!(
         // let (mfa, _) = context.ip.lookup_func_info().unwrap();
         // info!("pid={} action=call_only mfa={}", process.pid, mfa);
         // }
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     // TODO: module.imports lookups as embedded MFA exports
     fn call_ext(arity: t, destination: u) {
@@ -972,24 +1059,26 @@ instruction!(
         context.cp = Some(context.ip);
 
         op_call_ext!(vm, context, &process, arity, destination);
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn call_ext_only(arity: t, destination: u) {
         op_call_ext!(vm, context, &process, arity, destination);
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn call_ext_last(arity: t, destination: u, words: r) {
         op_deallocate(context, words);
 
         op_call_ext!(vm, context, &process, arity, destination);
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn call_bif(arity: t, bif: b) {
         // call a bif, store result in x0
 
         let args = &context.x[0..arity as usize];
+        context.bump_reductions(1);
         match bif(vm, &process, args) {
             Ok(val) => context.x[0] = val,
+            Err(ref exc) if exc.reason == Reason::YIELD => op_yield_bif!(context, false),
             Err(exc) => return Err(exc),
         }
     },
@@ -998,11 +1087,13 @@ instruction!(
         op_deallocate(context, words);
 
         let args = &context.x[0..arity as usize];
+        context.bump_reductions(1);
         match bif(vm, &process, args) {
             Ok(val) => {
                 context.x[0]= val;
                 op_return!(process, context);
             },
+            Err(ref exc) if exc.reason == Reason::YIELD => op_yield_bif!(context, true),
             Err(exc) => return Err(exc),
         }
     },
@@ -1010,11 +1101,13 @@ instruction!(
         // call a bif, store result in x0, return
 
         let args = &context.x[0..arity as usize];
+        context.bump_reductions(1);
         match bif(vm, &process, args) {
             Ok(val) => {
                 context.x[0]= val;
                 op_return!(process, context);
             },
+            Err(ref exc) if exc.reason == Reason::YIELD => op_yield_bif!(context, true),
             Err(exc) => return Err(exc),
         }
     },
@@ -1022,35 +1115,42 @@ instruction!(
         // save pointer onto CP
         context.cp = Some(context.ip);
 
-        op_apply_fun!(vm, context, process)
+        op_apply_fun!(vm, context, process, false)
     },
     fn apply_fun_last(words: r) {
         op_deallocate(context, words);
 
-        op_apply_fun!(vm, context, process)
+        op_apply_fun!(vm, context, process, true)
     },
     fn apply_fun_only() {
-        op_apply_fun!(vm, context, process)
+        op_apply_fun!(vm, context, process, true)
     },
     fn i_apply() {
         // different from apply(), it's a bif override
         context.cp = Some(context.ip);
 
-        op_apply!(vm, context, process);
+        op_apply!(vm, context, process, false);
     },
     fn i_apply_last(words: r) {
         op_deallocate(context, words);
 
-        op_apply!(vm, context, process);
+        op_apply!(vm, context, process, true);
     },
     fn i_apply_only() {
-        op_apply!(vm, context, process);
+        op_apply!(vm, context, process, true);
+    },
+    fn i_hibernate() {
+        // erlang:hibernate/3 throws the stack away, so it's always a tail call
+        scheduler::hibernate(vm, &process).await?;
+        op_apply!(vm, context, process, true);
     },
     fn bif0(bif: b, reg: d) {
+        context.bump_reductions(1);
         let val = bif(vm, &process, &[]).unwrap(); // bif0 can't fail
         context.set_register(reg, val);
     },
     fn bif1(fail: l, bif: b, arg1: s, reg: d) {
+        context.bump_reductions(1);
         let args = &[#arg1];
         match bif(vm, &process, args) {
             Ok(val) => context.set_register(reg, val),
@@ -1058,6 +1158,7 @@ instruction!(
         }
     },
     fn bif2(fail: l, bif: b, arg1: s, arg2: s, reg: d) {
+        context.bump_reductions(1);
         let args = &[#arg1, #arg2];
         match bif(vm, &process, args) {
             Ok(val) => context.set_register(reg, val),
@@ -1079,8 +1180,9 @@ instruction!(
         context
             .stack
             .resize(context.stack.len() + stackneed as usize, NIL);
-        // TODO: check heap for heapneed space!
         context.callstack.push((stackneed, context.cp.take()));
+        // the heap grows on demand, but this is a safepoint where we can collect.
+        process::gc::maybe_collect(vm, &process, live as usize)?;
     },
     fn allocate_zero(stackneed: r, live: r) {
         context
@@ -1094,11 +1196,12 @@ instruction!(
         context
             .stack
             .resize(context.stack.len() + stackneed as usize, NIL);
-        // TODO: check heap for heapneed space!
         context.callstack.push((stackneed, context.cp.take()));
+        // the heap grows on demand, but this is a safepoint where we can collect.
+        process::gc::maybe_collect(vm, &process, live as usize)?;
     },
-    fn test_heap(_heapneed: r, n: r) {
-        // println!("TestHeap unimplemented!");
+    fn test_heap(_heapneed: r, live: r) {
+        process::gc::maybe_collect(vm, &process, live as usize)?;
     },
     fn init(n: d) {
         context.set_register(n, NIL)
@@ -1472,19 +1575,20 @@ instruction!(
     fn apply(arity: t) {
         context.cp = Some(context.ip);
 
-        op_fixed_apply!(vm, context, &process, arity);
+        op_fixed_apply!(vm, context, &process, arity, false);
         // call this fixed_apply, used for ops (apply, apply_last).
         // apply is the func that's equivalent to erlang:apply/3 (and instrs)
-        safepoint_and_reduce!(vm, process, context.reds);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn apply_last(arity: t, nwords: r) {
         op_deallocate(context, nwords);
 
-        op_fixed_apply!(vm, context, &process, arity);
-        safepoint_and_reduce!(vm, process, context.reds);
+        op_fixed_apply!(vm, context, &process, arity, true);
+        safepoint_and_reduce!(vm, process, context);
     },
     fn gc_bif1(fail: l, _live: r, bif: b, arg1: s, reg: d) {
         // TODO: GcBif needs to handle GC as necessary
+        context.bump_reductions(1);
         let args = &[#arg1];
         match bif(vm, &process, args) {
             Ok(val) => context.set_register(reg, val),
@@ -1493,6 +1597,7 @@ instruction!(
     },
     fn gc_bif2(fail: l, _live: r, bif: b, arg1: s, arg2: s, reg: d) {
         // TODO: GcBif needs to handle GC as necessary
+        context.bump_reductions(1);
         let args = &[#arg1, #arg2];
         match bif(vm, &process, args) {
             Ok(val) => context.set_register(reg, val),
@@ -1501,6 +1606,7 @@ instruction!(
     },
     fn gc_bif3(fail: l, _live: r, bif: b, arg1: s, arg2: s, arg3: s, reg: d) {
         // TODO: GcBif needs to handle GC as necessary
+        context.bump_reductions(1);
         let args = &[#arg1, #arg2, #arg3];
         match bif(vm, &process, args) {
             Ok(val) => context.set_register(reg, val),
@@ -1574,6 +1680,8 @@ instruction!(
                     Variant::Atom(atom::ALL) => context.bs.put_binary_all(source),
                     _ => unimplemented!("bs_put_binary size {:?}", size),
                 }
+                let bytes = source.to_bytes().map_or(0, |bytes| bytes.len());
+                context.bump_reductions(bytes / bitstring::BYTES_PER_RED);
             }
             _ => panic!("Bad argument to BsPutBinary: {}", source)
         }
@@ -1598,6 +1706,7 @@ instruction!(
     fn bs_put_string(binary: v) {
         // BsPutString uses the StrT strings table! needs to be patched in loader
         context.bs.put_bytes(binary);
+        context.bump_reductions(binary.len() / bitstring::BYTES_PER_RED);
     },
     fn bs_get_tail(cxt: d, destination: d, live: r) {
         // very similar to the old BsContextToBinary
@@ -2093,7 +2202,7 @@ instruction!(
     fn call_fun(arity: t) {
         let value = context.x[arity as usize];
         context.cp = Some(context.ip);
-        op_call_fun!(vm, context, process, value, arity)
+        op_call_fun!(vm, context, process, value, arity, false)
     },
     fn get_hd(source: xy, destination: xy) {
         if let Ok(value::Cons { head, .. }) = #source.cast_into() {
@@ -11,6 +11,7 @@ pub mod nanbox;
 pub mod atom;
 pub mod bif;
 pub mod bitstring;
+pub mod cli;
 pub mod etf;
 pub mod ets;
 pub mod exports_table;
@@ -26,8 +27,10 @@ pub mod persistent_term;
 pub mod port;
 pub mod process;
 pub mod regex;
+pub mod scheduler;
 pub mod servo_arc;
 pub mod signal_queue;
+pub mod statistics;
 pub mod value;
 
 #[macro_use]
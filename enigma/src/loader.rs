@@ -49,6 +49,7 @@ pub type ExtList = Vec<LValue>;
 
 const APPLY_2: crate::bif::Fn = crate::bif::bif_erlang_apply_2;
 const APPLY_3: crate::bif::Fn = crate::bif::bif_erlang_apply_3;
+const HIBERNATE_3: crate::bif::Fn = crate::bif::bif_erlang_hibernate_3;
 
 /// Compact term encoding values. BEAM does some tricks to be able to share the memory layout with
 /// regular values, but for the most part we don't need this (it also doesn't fit nanboxed values
@@ -677,6 +678,13 @@ impl<'a> Loader<'a> {
                                 args: vec![],
                             })
                         }
+                        // call_ext u==3 u$bif:erlang:hibernate/3 => i_hibernate
+                        Some(bif) if (*bif as usize) == (HIBERNATE_3 as usize) => {
+                            self.instructions.push(Instruction {
+                                op: O::IHibernate,
+                                args: vec![],
+                            })
+                        }
                         // call_ext u Bif=u$is_bif => call_bif Bif
                         Some(bif) => self.instructions.push(Instruction {
                             op: O::CallBif,
@@ -703,6 +711,14 @@ impl<'a> Loader<'a> {
                                 args: vec![d.clone()],
                             })
                         }
+                        // hibernate throws the stack away, no need to deallocate
+                        // call_ext_last u==3 u$bif:erlang:hibernate/3 D => i_hibernate
+                        Some(bif) if (*bif as usize) == (HIBERNATE_3 as usize) => {
+                            self.instructions.push(Instruction {
+                                op: O::IHibernate,
+                                args: vec![],
+                            })
+                        }
                         // call_ext_last u Bif=u$is_bif D => deallocate D | call_bif_only Bif
                         Some(bif) => {
                             self.instructions.push(Instruction {
@@ -731,6 +747,13 @@ impl<'a> Loader<'a> {
                                 args: vec![],
                             })
                         }
+                        // call_ext_only u==3 u$bif:erlang:hibernate/3 => i_hibernate
+                        Some(bif) if (*bif as usize) == (HIBERNATE_3 as usize) => {
+                            self.instructions.push(Instruction {
+                                op: O::IHibernate,
+                                args: vec![],
+                            })
+                        }
                         // call_ext_only Ar=u Bif=u$is_bif => call_bif_only Bif
                         Some(bif) => self.instructions.push(Instruction {
                             op: O::CallBifOnly,
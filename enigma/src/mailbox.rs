@@ -1,14 +1,22 @@
 use std::collections::VecDeque;
 
+use crate::immix::Heap;
 use crate::value::Term;
 
+/// Identifies the receive a mark was made for, as the module and label of its loop.
+pub type Marker = (usize, u32);
+
 #[derive(Debug, Default)]
 pub struct Mailbox {
-    queue: VecDeque<Term>,
+    /// Received messages. Messages stored off heap carry the heap fragment holding their terms,
+    /// which the process only adopts once it gets to the message.
+    queue: VecDeque<(Term, Option<Heap>)>,
 
     /// Save pointer to track position to the current offset when scanning through the mailbox.
     save: usize,
-    mark: Option<usize>,
+
+    /// End of the queue at the time of `recv_mark`, along with the receive it was made for.
+    mark: Option<(usize, Marker)>,
 }
 
 impl Mailbox {
@@ -21,22 +29,39 @@ impl Mailbox {
     }
 
     pub fn send(&mut self, message: Term) {
-        self.queue.push_back(message);
+        self.queue.push_back((message, None));
+    }
+
+    /// Queues a message whose terms live in `fragment`, outside of the process heap.
+    pub fn send_off_heap(&mut self, message: Term, fragment: Option<Heap>) {
+        self.queue.push_back((message, fragment));
     }
 
     pub fn receive(&mut self) -> Option<Term> {
-        self.queue.get(self.save).copied()
+        self.queue.get(self.save).map(|(message, _)| *message)
     }
 
-    // recv_mark
-    pub fn mark(&mut self) {
-        self.mark = Some(self.save);
+    /// Takes the heap fragment of the current message if it's stored off heap. The process has
+    /// to adopt it, since the message is now reachable from its registers.
+    pub fn take_fragment(&mut self) -> Option<Heap> {
+        self.queue
+            .get_mut(self.save)
+            .and_then(|(_, fragment)| fragment.take())
     }
 
-    // recv_set
-    pub fn set(&mut self) {
-        if let Some(mark) = self.mark {
-            self.save = mark;
+    /// Remembers the end of the queue for the receive identified by `marker` (recv_mark). The
+    /// messages queued up so far can't contain a reference that's made after this point.
+    pub fn mark(&mut self, marker: Marker) {
+        self.mark = Some((self.queue.len(), marker));
+    }
+
+    /// Skips the messages that were already queued up when the mark was made, if it was made for
+    /// this receive (recv_set).
+    pub fn set(&mut self, marker: Marker) {
+        if let Some((position, mark)) = self.mark {
+            if mark == marker {
+                self.save = position;
+            }
         }
     }
 
@@ -53,6 +78,13 @@ impl Mailbox {
     /// Once we're done with a message, we have to specifically pop
     pub fn remove(&mut self) {
         self.queue.remove(self.save);
+
+        // the marked position moves up along with the messages behind it
+        if let Some((position, _)) = &mut self.mark {
+            if self.save < *position {
+                *position -= 1;
+            }
+        }
     }
 
     pub fn has_messages(&self) -> bool {
@@ -64,4 +96,69 @@ impl Mailbox {
     pub fn len(&self) -> usize {
         self.queue.len()
     }
+
+    /// Iterator over all the queued messages, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Term> {
+        self.queue.iter().map(|(message, _)| message)
+    }
+
+    /// Mutable iterator over the messages stored on the process heap, used by the garbage
+    /// collector to update roots. Messages stored off heap are left alone.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Term> {
+        self.queue
+            .iter_mut()
+            .filter(|(_, fragment)| fragment.is_none())
+            .map(|(message, _)| message)
+    }
+
+    /// Size of the heap fragments of the messages stored off heap, as `(size, used)` in bytes.
+    pub fn off_heap_size(&self) -> (usize, usize) {
+        self.queue
+            .iter()
+            .filter_map(|(_, fragment)| fragment.as_ref())
+            .fold((0, 0), |(size, used), heap| (size + heap.size(), used + heap.used()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKER: Marker = (1, 10);
+
+    #[test]
+    fn test_mark_set() {
+        let mut mailbox = Mailbox::new();
+        mailbox.send(Term::int(1));
+        mailbox.send(Term::int(2));
+
+        mailbox.mark(MARKER);
+        mailbox.send(Term::int(3));
+
+        // a different receive starts from the beginning
+        mailbox.set((1, 20));
+        assert_eq!(mailbox.receive(), Some(Term::int(1)));
+
+        mailbox.set(MARKER);
+        assert_eq!(mailbox.receive(), Some(Term::int(3)));
+
+        mailbox.remove();
+        mailbox.reset();
+        assert_eq!(mailbox.receive(), Some(Term::int(1)));
+        assert_eq!(mailbox.len(), 2);
+    }
+
+    #[test]
+    fn test_mark_remove() {
+        let mut mailbox = Mailbox::new();
+        mailbox.send(Term::int(1));
+        mailbox.send(Term::int(2));
+        mailbox.mark(MARKER);
+        mailbox.send(Term::int(3));
+
+        // removing a message ahead of the mark keeps it in place
+        mailbox.remove();
+        mailbox.set(MARKER);
+        assert_eq!(mailbox.receive(), Some(Term::int(3)));
+    }
 }
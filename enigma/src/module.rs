@@ -4,9 +4,12 @@ use crate::exports_table::ExportsTable;
 use crate::immix::Heap;
 use crate::instruction::{self, Instruction};
 use crate::loader::Line;
+use crate::process::{self, PID};
 use crate::value::{self, CastFrom, Term, Variant};
 use crate::vm::Machine;
 use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct MFA(pub Atom, pub Atom, pub u32);
@@ -71,6 +74,21 @@ pub struct Module {
 }
 
 impl Module {
+    /// Memory used by the module in bytes: the loaded code, its metadata and the literals.
+    pub fn memory(&self) -> usize {
+        use std::mem::size_of;
+
+        size_of::<Module>()
+            + self.imports.capacity() * size_of::<MFA>()
+            + self.exports.capacity() * size_of::<(Atom, u32, u32)>()
+            + (self.constants.capacity() + self.literals.capacity()) * size_of::<Term>()
+            + self.lambdas.capacity() * size_of::<Lambda>()
+            + self.funs.capacity() * size_of::<((Atom, u32), u32)>()
+            + self.instructions.capacity() * size_of::<Instruction>()
+            + self.lines.capacity() * size_of::<Line>()
+            + self.literal_heap.size()
+    }
+
     fn process_exports(&self, exports: &mut ExportsTable) {
         // process_exports
         let funs = &self.funs;
@@ -147,6 +165,8 @@ pub fn load_module(vm: &Machine, path: &str) -> Result<*const Module, std::io::E
     })
 }
 
+/// Makes prepared modules current, the versions they replace become old code. There's only room
+/// for one old version, so any previous old code has to be purged first (see `finish_loading_1`).
 pub fn finish_loading_modules(vm: &Machine, modules: Vec<Box<Module>>) {
     for module in modules {
         let mut registry = vm.modules.lock();
@@ -167,6 +187,59 @@ pub fn finish_loading_modules(vm: &Machine, modules: Vec<Box<Module>>) {
     }
 }
 
+/// Releases the code of a purged module. Processes might still reference its literals, so the
+/// literal heap is handed over to the literal area collector, which releases it once every
+/// process has copied the literals it uses onto its own heap. `from` is the purging process.
+pub fn purge(vm: &Machine, from: PID, mut module: Box<Module>) {
+    vm.exports.write().purge(&*module);
+    let literals = std::mem::replace(&mut module.literal_heap, Heap::fragment());
+    drop(module);
+
+    let mut areas = vm.literal_areas.lock();
+    areas.queue.push_back(literals);
+    if areas.active.is_none() && areas.queue.len() == 1 {
+        // the collector is idle, wake it up. Otherwise it picks the area up once it's done with
+        // the ones in front of it.
+        let collector = vm.literal_collector.load(Ordering::Relaxed) as PID;
+        if collector != 0 {
+            let _ = process::send_message(vm, from, Term::pid(collector), atom!(COPY_LITERALS));
+        }
+    }
+}
+
+/// Literal heaps of purged modules (literal areas), queued up for release. See
+/// `erts_literal_area_collector`: the collector switches to the next area with
+/// `erts_internal:release_literal_area_switch/0`, then asks all processes to copy the literals
+/// they still reference out of it with a `copy_literals` system task.
+#[derive(Debug)]
+pub struct LiteralAreas {
+    /// The area processes are currently copying literals out of.
+    pub active: Option<Heap>,
+    /// Areas waiting to be handled.
+    pub queue: VecDeque<Heap>,
+}
+
+impl LiteralAreas {
+    pub fn new() -> Self {
+        LiteralAreas {
+            active: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Memory used by the areas in bytes.
+    pub fn memory(&self) -> usize {
+        self.active.iter().chain(self.queue.iter()).map(Heap::size).sum()
+    }
+
+    /// Releases the active area and makes the next queued one active. Returns false if there was
+    /// nothing left to handle.
+    pub fn switch(&mut self) -> bool {
+        self.active = self.queue.pop_front();
+        self.active.is_some()
+    }
+}
+
 impl CastFrom<Term> for *mut Module {
     type Error = value::WrongBoxError;
 
@@ -6,12 +6,15 @@ use parking_lot::Mutex;
 
 pub struct ModuleRegistry {
     pub modules: HashMap<Atom, Box<Module>>,
+    /// Previous versions of reloaded modules, kept around until they're purged.
+    pub old_modules: HashMap<Atom, Box<Module>>,
 }
 
 impl ModuleRegistry {
     pub fn with_rc() -> Mutex<ModuleRegistry> {
         Mutex::new(ModuleRegistry {
             modules: HashMap::new(),
+            old_modules: HashMap::new(),
         })
     }
 
@@ -32,12 +35,35 @@ impl ModuleRegistry {
         Ok(self.add_module(name, Box::new(module)))
     }
 
+    /// Adds a module, the version it replaces (if any) becomes old code. Any old code that was
+    /// still around has to be purged beforehand.
     pub fn add_module(&mut self, atom: Atom, module: Box<Module>) -> &Module {
-        self.modules.insert(atom, module);
+        if let Some(current) = self.modules.insert(atom, module) {
+            self.old_modules.insert(atom, current);
+        }
         &*self.modules[&atom]
     }
 
     pub fn lookup(&self, atom: Atom) -> Option<&Module> {
         self.modules.get(&atom).map(|module| &(**module))
     }
+
+    /// Memory used by all the loaded code in bytes, old code included.
+    pub fn memory(&self) -> usize {
+        self.modules
+            .values()
+            .chain(self.old_modules.values())
+            .map(|module| module.memory())
+            .sum()
+    }
+
+    /// Looks up the old code of a module.
+    pub fn lookup_old(&self, atom: Atom) -> Option<&Module> {
+        self.old_modules.get(&atom).map(|module| &(**module))
+    }
+
+    /// Removes the old code of a module, handing it back so its literals can be released.
+    pub fn purge_module(&mut self, atom: Atom) -> Option<Box<Module>> {
+        self.old_modules.remove(&atom)
+    }
 }
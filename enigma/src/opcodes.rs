@@ -577,6 +577,7 @@ pub enum Opcode {
     IApply = 177,
     IApplyOnly = 178,
     IApplyLast = 179,
+    IHibernate = 180,
 }
 
 static ARITY_MAP: &'static [usize] = &[
@@ -761,6 +762,7 @@ static ARITY_MAP: &'static [usize] = &[
     0, // opcode: 177 (i_apply)
     1, // opcode: 178 (i_apply_last)
     0, // opcode: 179 (i_apply_only)
+    0, // opcode: 180 (i_hibernate)
 ];
 
 #[inline]
@@ -1,6 +1,6 @@
 use crate::value::{Term, Variant, Tuple, CastFrom};
 use crate::process::{PID, Ref};
-use crate::exception::{Exception};
+use crate::exception::{Exception, Reason};
 use crate::atom;
 use crate::value;
 use crate::vm::Machine;
@@ -28,6 +28,8 @@ pub struct Port {
     owner: PID,
     // chan: mpsc::UnboundedSender<Signal>,
     pub chan: mpsc::UnboundedSender<Signal>,
+    /// Processes monitoring this port, they get a `DOWN` message once it terminates.
+    monitors: Vec<(PID, Ref)>,
 }
 
 impl Port {
@@ -35,10 +37,19 @@ impl Port {
         Port {
             id,
             owner,
-            chan
+            chan,
+            monitors: Vec::new(),
         }
     }
 
+    pub fn monitor(&mut self, from: PID, reference: Ref) {
+        self.monitors.push((from, reference));
+    }
+
+    pub fn demonitor(&mut self, from: PID, reference: Ref) {
+        self.monitors.retain(|(pid, r)| !(*pid == from && *r == reference));
+    }
+
     // TODO: probably better to return the future here and await outside
     // pub async fn send_message(&mut self, msg: Vec<u8>) { // Result<(), mpsc::SendError> {
     //     self.chan.send_async(Signal::Command(msg)).await;
@@ -87,6 +98,10 @@ impl Table {
         self.ports.get(&pid).map(|port| port.lock())
     }
 
+    pub fn remove(&mut self, pid: ID) -> Option<Port> {
+        self.ports.remove(&pid).map(Mutex::into_inner)
+    }
+
     fn next_pid(&mut self) -> ID {
         let pid = self.next_pid;
 
@@ -117,11 +132,12 @@ pub fn spawn(
     match tup[0].into_variant() {
         Variant::Atom(atom::SPAWN) => {
             match tup[1].into_variant() {
-                Variant::Atom(atom::TTY_SL) => vm.runtime.executor().spawn(tty(pid, owner, input)),
+                Variant::Atom(atom::TTY_SL) => vm.runtime.spawn(tty(pid, owner, input)),
                 Variant::Cons(..) => {
                     let cons = value::Cons::cast_from(&tup[1]).unwrap();
-                    match value::cons::unicode_list_to_buf(cons, 2048).unwrap().as_ref() {
-                        "tty_sl -c -e" => vm.runtime.executor().spawn(tty(pid, owner, input)),
+                    let command = value::cons::unicode_list_to_buf(cons, std::usize::MAX).unwrap();
+                    match command.as_ref() {
+                        "tty_sl -c -e" => vm.runtime.spawn(tty(pid, owner, input)),
                         _ => unimplemented!("port::spawn for {}", args),
                     };
                 }
@@ -130,7 +146,7 @@ pub fn spawn(
         }
         Variant::Atom(atom::FD) => {
             match (tup[1].into_variant(), tup[2].into_variant()) {
-                (Variant::Integer(2), Variant::Integer(2)) => vm.runtime.executor().spawn(stderr(pid, owner, input)),
+                (Variant::Integer(2), Variant::Integer(2)) => vm.runtime.spawn(stderr(pid, owner, input)),
                 _ => unimplemented!("port::spawn for {}", args),
             }
         }
@@ -149,7 +165,7 @@ pub fn send_message(
      // info!("sending from {} to port {} msg {}", from, port, msg);
 
     let res = vm.port_table.read().lookup(port).map(|port| port.chan.clone());
-    if let Some(mut chan) = res {
+    if let Some(chan) = res {
         // TODO: error unhandled
         let tup = Tuple::cast_from(&msg)?;
         if !tup.len() == 2 || !tup[0].is_pid() {
@@ -162,7 +178,9 @@ pub fn send_message(
                     Variant::Atom(atom::COMMAND) => {
                         // TODO: validate tuple len 2
                         let bytes = crate::bif::erlang::list_to_iodata(cmd[1]).unwrap();
-                        vm.runtime.block_on(chan.send(Signal::Command(bytes)));
+                        vm.stats.io_output(bytes.len());
+                        // the channel is unbounded, no need to wait
+                        let _ = chan.unbounded_send(Signal::Command(bytes));
                     }
                     _ => unimplemented!("msg to port {}", msg),
                 }
@@ -183,7 +201,7 @@ pub fn close(vm: &Machine, from: PID, port: ID) -> Result<(), Exception> {
         // TODO: error unhandled
 
         let future = async move {chan.send(Signal::Close).await; };
-        vm.runtime.executor().spawn(future);
+        vm.runtime.spawn(future);
     }
     Ok(())
 }
@@ -213,7 +231,7 @@ pub fn control(
                 data: bytes,
             }).await;
         };
-        vm.runtime.executor().spawn(future);
+        vm.runtime.spawn(future);
 
         Ok(reference)
     } else {
@@ -223,6 +241,20 @@ pub fn control(
     }
 }
 
+/// Drops a port whose driver has finished, and lets its monitors know.
+fn terminate(vm: &Machine, id: ID) {
+    let port = vm.port_table.write().remove(id);
+    if let Some(port) = port {
+        for (pid, reference) in port.monitors {
+            crate::process::send_signal(vm, pid, crate::process::Signal::MonitorDown {
+                from: id,
+                reason: Exception::with_value(Reason::EXC_EXIT, atom!(NORMAL)),
+                reference,
+            });
+        }
+    }
+}
+
 // TODO: needs type async fn
 type Driver = fn(owner: PID, input: mpsc::UnboundedReceiver<Signal>);
 
@@ -428,6 +460,7 @@ async fn tty(id: ID, owner: PID, input: mpsc::UnboundedReceiver<Signal>) {
                     Ok(bytes) => {
                         // info!("id: {} , owner {} recv! {:?}", id, owner, &buf[..bytes]);
                         let vm = Machine::current();
+                        vm.stats.io_input(bytes);
                         // need to return a tuple, but want to avoid heap alloc here..
                         let bin = Arc::new(crate::bitstring::Binary::from(&buf[..bytes]));
                         crate::process::send_signal(&vm, owner, crate::process::Signal::PortMessage {
@@ -441,6 +474,7 @@ async fn tty(id: ID, owner: PID, input: mpsc::UnboundedReceiver<Signal>) {
             },
         }
     }
+    terminate(&Machine::current(), id);
 }
 
 async fn stderr(id: ID, _owner: PID, input: mpsc::UnboundedReceiver<Signal>) {
@@ -474,4 +508,5 @@ async fn stderr(id: ID, _owner: PID, input: mpsc::UnboundedReceiver<Signal>) {
         }
         // port_control stuff (op_get_winsize)
     }
+    terminate(&Machine::current(), id);
 }
@@ -1,5 +1,6 @@
 pub use self::table::PID;
 use crate::atom::{self, Atom};
+use crate::bif;
 use crate::bitstring;
 use crate::exception::{Exception, Reason};
 use crate::immix::Heap;
@@ -7,23 +8,27 @@ use crate::instruction::Ptr;
 use crate::instruction;
 use crate::mailbox::Mailbox;
 use crate::module::{Module, MFA};
+use crate::port;
+use crate::scheduler::{Dirty, Priority, Status};
 // use crate::servo_arc::Arc; can't do receiver self
 use crate::signal_queue::SignalQueue;
-pub use crate::signal_queue::{ExitKind, Signal};
-use crate::value::{self, CastInto, Term};
+pub use crate::signal_queue::{ExitKind, Signal, SuspendReply};
+use crate::value::{self, CastFrom, CastInto, Term};
 use crate::vm::Machine;
 
 use hashbrown::{HashMap, HashSet};
 use std::cell::UnsafeCell;
+use std::collections::VecDeque;
 // use std::panic::RefUnwindSafe;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 
 use tokio::prelude::*;
 
 use futures::prelude::*;
 
+pub mod gc;
 pub mod registry;
 pub mod table;
 
@@ -31,6 +36,66 @@ pub type RcProcess = Pin<Arc<Process>>;
 
 pub type Ref = usize;
 
+/// Marks an alias reference. Aliases carry the PID of their owner in the bits below, so that
+/// messages sent to them can be routed without looking anything up.
+const ALIAS_BIT: Ref = 1 << 63;
+
+/// Number of low bits in an alias reference that hold the unique part.
+const ALIAS_ID_BITS: u32 = 31;
+
+/// Makes a new alias reference, owned by `pid`.
+pub fn alias_ref(vm: &Machine, pid: PID) -> Ref {
+    ALIAS_BIT | ((pid as Ref) << ALIAS_ID_BITS) | (vm.next_ref() & ((1 << ALIAS_ID_BITS) - 1))
+}
+
+/// Returns the process owning `reference`, if it's an alias.
+pub fn alias_owner(reference: Ref) -> Option<PID> {
+    if reference & ALIAS_BIT == 0 {
+        return None;
+    }
+    Some(((reference & !ALIAS_BIT) >> ALIAS_ID_BITS) as PID)
+}
+
+/// How an alias gets deactivated, besides by `unalias/1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasKind {
+    /// Only by `unalias/1`.
+    ExplicitUnalias,
+    /// Once the message sent through it is received.
+    Reply,
+    /// Along with the monitor it belongs to, on demonitor or once the `DOWN` message arrives.
+    Demonitor,
+    /// Like `Demonitor`, or once the message sent through it is received, which also removes the
+    /// monitor.
+    ReplyDemonitor,
+}
+
+/// What one of our monitors is watching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monitored {
+    Process(PID),
+    /// A process that was looked up by its registered name, `DOWN` reports it as `{Name, Node}`.
+    Name(PID, Atom),
+    Port(port::ID),
+    /// The time offset of the clock service. Ours never changes, so it never triggers.
+    TimeOffset,
+}
+
+impl Monitored {
+    /// The type and the object of the `DOWN` message for this monitor.
+    pub fn down_object(self, heap: &Heap) -> (Term, Term) {
+        match self {
+            Monitored::Process(pid) => (atom!(PROCESS), Term::pid(pid)),
+            Monitored::Name(_, name) => (
+                atom!(PROCESS),
+                tup2!(heap, Term::atom(name), atom!(NO_NODE_NO_HOST)),
+            ),
+            Monitored::Port(id) => (atom!(PORT), Term::port(id)),
+            Monitored::TimeOffset => (atom!(TIME_OFFSET), atom!(CLOCK_SERVICE)),
+        }
+    }
+}
+
 // TODO: Only store "live" regs in the execution context and swap them into VM/scheduler
 // also this way, regs could be a &mut [] slice with no clone?
 
@@ -38,10 +103,27 @@ pub type Ref = usize;
 pub const MAX_REG: usize = 1024;
 // pub const MAX_REG: usize = 255;
 
+/// Reductions a process gets to execute before it's swapped out.
+pub const CONTEXT_REDS: usize = 2000;
+
+/// Reductions it costs to send a message, on top of the copying.
+const SEND_REDS: usize = 4;
+
+/// Reductions it costs to send a message after copying `size` bytes of it.
+pub fn send_reductions(size: usize) -> usize {
+    SEND_REDS + size / gc::WORD_SIZE / 100
+}
+
 bitflags! {
     pub struct Flag: u8 {
         const INITIAL = 0;
         const TRAP_EXIT = (1 << 0);
+        /// Incoming messages stay in their heap fragments until the process receives them.
+        const OFF_HEAP_MSGQ = (1 << 1);
+        /// Hides the message queue, dictionary and stack from inspection, and stops saving calls.
+        const SENSITIVE = (1 << 2);
+        /// The system can't keep going without this process, it takes the VM down when it exits.
+        const SYSTEM_PROC = (1 << 3);
     }
 }
 
@@ -65,6 +147,8 @@ pub struct ExecutionContext {
     pub callstack: Vec<(instruction::Regs, Option<instruction::Ptr>)>,
     /// Process heap
     pub heap: Heap,
+    /// Heap fragments holding received messages, merged into the heap on the next collection.
+    pub fragments: Vec<Heap>,
     /// Number of catches on stack.
     pub catches: usize,
     /// Program pointer, points to the current instruction.
@@ -78,8 +162,12 @@ pub struct ExecutionContext {
     pub bs: bitstring::Builder,
     ///
     pub exc: Option<Exception>,
-    /// Reductions left
+    /// Reductions left in the current time slice.
     pub reds: usize,
+    /// Reductions executed by the process so far.
+    pub reductions: usize,
+    /// A BIF that ran out of reductions, to be resumed once the process is scheduled again.
+    pub trap: Option<Trap>,
 
     /// Waker associated with the wait
     pub recv_channel: Option<futures::channel::oneshot::Receiver<()>>,
@@ -136,6 +224,30 @@ impl ExecutionContext {
             }
         }
     }
+
+    /// Charges `reds` reductions to the process. Once the time slice is used up, the process gets
+    /// swapped out on the next call.
+    #[inline]
+    pub fn bump_reductions(&mut self, reds: usize) {
+        self.reds = self.reds.saturating_sub(reds);
+        self.reductions += reds;
+    }
+
+    /// Charges the rest of the time slice to the process.
+    #[inline]
+    pub fn bump_all_reductions(&mut self) {
+        self.bump_reductions(self.reds)
+    }
+}
+
+/// A BIF continuation: `bif` gets called again with the first `arity` X registers as arguments.
+pub struct Trap {
+    pub bif: bif::Fn,
+    pub arity: usize,
+    /// Whether to return to the caller once the BIF is done (call_bif_only, call_bif_last).
+    pub tail: bool,
+    /// Dirty scheduler the BIF has to run on, if any.
+    pub dirty: Option<Dirty>,
 }
 
 impl ExecutionContext {
@@ -146,6 +258,7 @@ impl ExecutionContext {
             stack: Vec::with_capacity(32),
             callstack: Vec::with_capacity(8),
             heap: Heap::new(),
+            fragments: Vec::new(),
             catches: 0,
             ip: instruction::Ptr { ptr: 0, module },
             cp: None,
@@ -160,6 +273,8 @@ impl ExecutionContext {
             // TODO: not great
             bs: unsafe { std::mem::uninitialized() },
             reds: 0,
+            reductions: 0,
+            trap: None,
             timeout: None,
             recv_channel: None,
         }
@@ -169,25 +284,69 @@ impl ExecutionContext {
 bitflags! {
     pub struct StateFlag: u8 {
         const INITIAL = 0;
+        /// Suspended by `erlang:suspend_process/1,2`. The process keeps handling signals, but
+        /// stays off the run queue until it gets resumed.
+        const SUSPENDED = (1 << 0);
+        /// Sleeping in `erlang:hibernate/3` until a message arrives.
+        const HIBERNATED = (1 << 1);
+    }
+}
 
-        const PRQ_OFFSET = 0;
-        const PRQ_BITS = 3;
+/// A suspend request waiting for a reply, sent once the suspend took effect.
+pub struct PendingSuspend {
+    from: PID,
+    reply: SuspendReply,
+    /// Owns the reply tag.
+    _fragment: Option<Heap>,
+    /// The requester resumed the process before it got to suspend.
+    cancelled: bool,
+}
 
-        const PRQ_MAX = 0b100;
-        const PRQ_HIGH = 0b11;
-        const PRQ_MEDIUM = 0b10;
-        const PRQ_LOW = 0b01;
+/// Maximum number of calls `process_flag(save_calls, N)` keeps track of.
+pub const MAX_SAVED_CALLS: usize = 10000;
+
+/// An entry in the list of last calls, see `process_flag(save_calls, N)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SavedCall {
+    /// A call to a global function.
+    Call(MFA),
+    Send,
+    Receive,
+    Timeout,
+}
 
-        const PRQ_MASK = (1 << Self::PRQ_BITS.bits) - 1;
+impl SavedCall {
+    pub fn to_term(self, heap: &Heap) -> Term {
+        match self {
+            SavedCall::Call(MFA(module, function, arity)) => tup3!(
+                heap,
+                Term::atom(module),
+                Term::atom(function),
+                Term::uint(heap, arity)
+            ),
+            SavedCall::Send => atom!(SEND),
+            SavedCall::Receive => atom!(RECEIVE),
+            SavedCall::Timeout => atom!(TIMEOUT),
+        }
     }
 }
 
+/// The last calls of a process, oldest first.
+#[derive(Debug)]
+pub struct SavedCalls {
+    pub len: usize,
+    pub calls: VecDeque<SavedCall>,
+}
+
 /// Process-local data. Should only be touched by the owning process.
 pub struct LocalData {
     context: Box<ExecutionContext>,
 
     pub state: StateFlag,
 
+    /// Scheduling priority, picks the run queue the process waits in.
+    pub priority: Priority,
+
     /// Parent process PID
     parent: PID,
 
@@ -206,12 +365,19 @@ pub struct LocalData {
     /// A set of PIDs representing linked processes.
     pub links: HashSet<PID>,
 
-    /// A map of monitor references to process PIDs.
-    pub monitors: HashMap<Ref, PID>,
+    /// A map of monitor references to what they're watching.
+    pub monitors: HashMap<Ref, Monitored>,
 
     /// A list of PIDs to processes that monitor this process.
     pub lt_monitors: Vec<(PID, Ref)>,
 
+    /// Tags that replace `'DOWN'` in the `DOWN` messages of our monitors, set with the
+    /// `{tag, Tag}` monitor option.
+    pub monitor_tags: HashMap<Ref, Term>,
+
+    /// Active aliases, messages sent to any other alias of this process get dropped.
+    pub aliases: HashMap<Ref, AliasKind>,
+
     /// A three-stage signal queue for messages and process lifecycle signals.
     pub signal_queue: SignalQueue,
 
@@ -225,6 +391,24 @@ pub struct LocalData {
 
     /// A [process dictionary](https://www.erlang.org/course/advanced#dict)
     pub dictionary: HashMap<Term, Term>,
+
+    /// Garbage collector state and settings.
+    pub gc: gc::GcInfo,
+
+    /// Number of times the process got suspended and not resumed yet, by all processes.
+    pub suspend_count: usize,
+
+    /// Suspend requests to reply to once the process suspends.
+    pub suspend_replies: Vec<PendingSuspend>,
+
+    /// Processes this process suspended, with the number of times it did so.
+    pub suspending: HashMap<PID, usize>,
+
+    /// The last calls made, if `save_calls` is enabled.
+    pub saved_calls: Option<SavedCalls>,
+
+    /// Reference identifying the process, handed out by `process_info(Pid, magic_ref)`.
+    pub magic_ref: Option<Ref>,
 }
 
 pub struct Process {
@@ -237,6 +421,19 @@ pub struct Process {
 
     /// If the process is waiting for a message.
     pub waiting_for_message: AtomicBool,
+
+    /// Scheduling state, see `Status`.
+    pub status: AtomicU8,
+
+    /// If the process is currently executing a BIF on a dirty scheduler.
+    pub running_dirty: AtomicBool,
+
+    /// Set once the process got killed while running dirty. The process exits as soon as the
+    /// dirty BIF returns.
+    pub pending_exit: AtomicBool,
+
+    /// Set once the process started exiting, it's no longer alive from then on.
+    pub exiting: AtomicBool,
 }
 
 unsafe impl Sync for LocalData {}
@@ -260,6 +457,7 @@ impl Process {
             context: Box::new(context),
             flags: Flag::INITIAL,
             state: StateFlag::INITIAL,
+            priority: Priority::Normal,
             parent,
             group_leader,
             name: None,
@@ -268,16 +466,28 @@ impl Process {
             links: HashSet::new(),
             monitors: HashMap::new(),
             lt_monitors: Vec::new(),
+            monitor_tags: HashMap::new(),
+            aliases: HashMap::new(),
             signal_queue: SignalQueue::new(),
             mailbox: Mailbox::new(),
             thread_id: None,
             dictionary: HashMap::new(),
+            gc: gc::GcInfo::new(),
+            suspend_count: 0,
+            suspend_replies: Vec::new(),
+            suspending: HashMap::new(),
+            saved_calls: None,
+            magic_ref: None,
         };
 
         Arc::pin(Process {
             pid,
             local_data: UnsafeCell::new(local_data),
             waiting_for_message: AtomicBool::new(false),
+            status: AtomicU8::new(Status::Runnable as u8),
+            running_dirty: AtomicBool::new(false),
+            pending_exit: AtomicBool::new(false),
+            exiting: AtomicBool::new(false),
         })
     }
 
@@ -321,25 +531,230 @@ impl Process {
         self.pid == 0
     }
 
+    /// Whether this is a system process, spawned via erts_internal:spawn_system_process/3.
+    pub fn is_system(&self) -> bool {
+        self.local_data().flags.contains(Flag::SYSTEM_PROC)
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.local_data().priority
+    }
+
+    pub fn status(&self) -> Status {
+        Status::from_u8(self.status.load(Ordering::Acquire))
+    }
+
+    pub fn set_status(&self, status: Status) {
+        self.status.store(status as u8, Ordering::Release)
+    }
+
+    pub fn is_running_dirty(&self) -> bool {
+        self.running_dirty.load(Ordering::Acquire)
+    }
+
+    pub fn has_pending_exit(&self) -> bool {
+        self.pending_exit.load(Ordering::Acquire)
+    }
+
+    pub fn is_exiting(&self) -> bool {
+        self.exiting.load(Ordering::Acquire)
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.local_data().state.contains(StateFlag::SUSPENDED)
+    }
+
+    /// Records a call if `save_calls` is enabled and the process isn't sensitive.
+    #[inline]
+    pub fn save_call(&self, call: SavedCall) {
+        let local_data = self.local_data_mut();
+        if local_data.flags.contains(Flag::SENSITIVE) {
+            return;
+        }
+        if let Some(saved) = &mut local_data.saved_calls {
+            if saved.calls.len() == saved.len {
+                saved.calls.pop_front();
+            }
+            saved.calls.push_back(call);
+        }
+    }
+
+    /// Sets the number of calls to keep track of, returning the previous one. Zero turns saving
+    /// calls off.
+    pub fn set_save_calls(&self, len: usize) -> usize {
+        let local_data = self.local_data_mut();
+        let old = local_data.saved_calls.as_ref().map_or(0, |saved| saved.len);
+        local_data.saved_calls = if len == 0 {
+            None
+        } else {
+            let mut calls = local_data
+                .saved_calls
+                .take()
+                .map_or_else(VecDeque::new, |saved| saved.calls);
+            while calls.len() > len {
+                calls.pop_front();
+            }
+            Some(SavedCalls { len, calls })
+        };
+        old
+    }
+
+    /// Memory used by the process in bytes, as `(allocated, used)`: the process structure, the
+    /// heap and message fragments, the stack and the message queue.
+    pub fn memory(&self) -> (usize, usize) {
+        let context = self.context();
+        let local_data = self.local_data();
+        let word = std::mem::size_of::<Term>();
+
+        let base = std::mem::size_of::<Process>()
+            + local_data.mailbox.len() * word
+            + local_data.dictionary.capacity() * 2 * word
+            + context.callstack.capacity()
+                * std::mem::size_of::<(instruction::Regs, Option<Ptr>)>();
+        let fragments = &context.fragments;
+        let (off_heap, off_heap_used) = local_data.mailbox.off_heap_size();
+
+        let allocated =
+            base + gc::heap_size(context) + context.stack.capacity() * word + off_heap;
+        let used = base
+            + context.heap.used()
+            + fragments.iter().map(Heap::used).sum::<usize>()
+            + context.stack.len() * word
+            + off_heap_used;
+        (allocated, used)
+    }
+
     pub fn send_signal(&self, signal: Signal) {
         self.local_data_mut().signal_queue.send_external(signal);
         self.wake_up()
     }
 
     // TODO: remove
-    pub fn send_message(&self, from: PID, message: Term) {
-        if from == self.pid {
+    /// Returns the amount of bytes copied.
+    pub fn send_message(&self, from: PID, message: Term) -> usize {
+        let size = if from == self.pid {
             // skip the signal_queue completely
             self.local_data_mut().mailbox.send(message);
+            0
         } else {
             self.local_data_mut()
                 .signal_queue
                 .send_external(Signal::Message {
                     value: message,
                     from,
-                });
+                })
+        };
+        self.wake_up();
+        size
+    }
+
+    /// Sends a message through `alias`, it only arrives if the alias is still active by then.
+    /// Returns the amount of bytes copied.
+    pub fn send_alias_message(&self, vm: &Machine, from: PID, alias: Ref, message: Term) -> usize {
+        let size = if from == self.pid {
+            if self.handle_alias_message(Some(vm), alias) {
+                self.local_data_mut().mailbox.send(message);
+            }
+            0
+        } else {
+            self.local_data_mut()
+                .signal_queue
+                .send_external(Signal::AliasMessage {
+                    value: message,
+                    alias,
+                    from,
+                })
+        };
+        self.wake_up();
+        size
+    }
+
+    /// Returns true if a message that was sent through `alias` should be delivered. Aliases that
+    /// only wait for a reply get deactivated. Without `vm`, the current machine gets used.
+    fn handle_alias_message(&self, vm: Option<&Machine>, alias: Ref) -> bool {
+        match self.local_data().aliases.get(&alias).copied() {
+            None => false,
+            Some(AliasKind::Reply) => {
+                self.unalias(alias);
+                true
+            }
+            Some(AliasKind::ReplyDemonitor) => {
+                match vm {
+                    Some(vm) => self.demonitor(vm, alias),
+                    None => self.demonitor(&Machine::current(), alias),
+                };
+                true
+            }
+            Some(_) => true,
+        }
+    }
+
+    /// Deactivates `alias`, returning false if it wasn't an active alias.
+    pub fn unalias(&self, alias: Ref) -> bool {
+        self.local_data_mut().aliases.remove(&alias).is_some()
+    }
+
+    /// Removes the monitor identified by `reference`, and its alias if it should go along with
+    /// it. Returns false if there was no such monitor.
+    pub fn demonitor(&self, vm: &Machine, reference: Ref) -> bool {
+        let local_data = self.local_data_mut();
+        if let Some(AliasKind::Demonitor) | Some(AliasKind::ReplyDemonitor) =
+            local_data.aliases.get(&reference)
+        {
+            local_data.aliases.remove(&reference);
+        }
+        local_data.monitor_tags.remove(&reference);
+
+        // remove the target from our monitor tree, and ourselves from theirs
+        match local_data.monitors.remove(&reference) {
+            Some(Monitored::Port(id)) => {
+                if let Some(mut port) = vm.port_table.read().lookup(id) {
+                    port.demonitor(self.pid, reference);
+                }
+                true
+            }
+            Some(Monitored::Process(pid)) | Some(Monitored::Name(pid, _)) => {
+                if pid == self.pid {
+                    local_data
+                        .lt_monitors
+                        .retain(|(x, r)| !(*x == pid && *r == reference));
+                } else {
+                    // send DEMONITOR signal to the other process
+                    self::send_signal(
+                        vm,
+                        pid,
+                        Signal::Demonitor {
+                            from: self.pid,
+                            reference,
+                        },
+                    );
+                }
+                true
+            }
+            Some(Monitored::TimeOffset) => true,
+            None => false,
+        }
+    }
+
+    /// Delivers the `DOWN` message of a monitor that's been taken off the monitor tree, or never
+    /// made it there because the target didn't exist.
+    pub fn monitor_down(&self, reference: Ref, kind: Term, object: Term, reason: Term) {
+        let heap = &self.context_mut().heap;
+        let local_data = self.local_data_mut();
+        let tag = local_data
+            .monitor_tags
+            .remove(&reference)
+            .unwrap_or(atom!(DOWN_U));
+        let ref_term = Term::reference(heap, reference);
+        let msg = tup!(heap, tag, ref_term, kind, object, reason);
+        local_data.mailbox.send(msg);
+
+        // the monitor is gone, and so is its alias unless it has to be removed explicitly
+        if let Some(AliasKind::Demonitor) | Some(AliasKind::ReplyDemonitor) =
+            local_data.aliases.get(&reference)
+        {
+            local_data.aliases.remove(&reference);
         }
-        self.wake_up()
     }
 
     // awkward result, but it works
@@ -349,6 +764,10 @@ impl Process {
         if !local_data.mailbox.has_messages() {
             self.process_incoming()?
         }
+        // the message is about to be matched on, move it onto the process heap
+        if let Some(fragment) = local_data.mailbox.take_fragment() {
+            self.context_mut().fragments.push(fragment);
+        }
         Ok(local_data.mailbox.receive())
     }
 
@@ -379,10 +798,42 @@ impl Process {
         }
 
         // get internal, if we ran out, start processing external
-        while let Some(signal) = self.local_data_mut().signal_queue.receive() {
+        while let Some((signal, fragment)) = self.local_data_mut().signal_queue.receive() {
+            let local_data = self.local_data_mut();
+
+            // messages sent through an alias only get through while the alias is active
+            let signal = match signal {
+                Signal::AliasMessage { from, alias, value } => {
+                    if !self.handle_alias_message(None, alias) {
+                        continue;
+                    }
+                    Signal::Message { from, value }
+                }
+                signal => signal,
+            };
+
+            // messages stored off heap keep their fragment until they're received
+            if let Signal::Message { value, .. } = signal {
+                if local_data.flags.contains(Flag::OFF_HEAP_MSGQ) {
+                    local_data.mailbox.send_off_heap(value, fragment);
+                    continue;
+                }
+            }
+
+            // so do suspend reply tags, until the reply is sent
+            if let Signal::Suspend { from, reply } = signal {
+                self.handle_suspend_signal(from, reply, fragment);
+                continue;
+            }
+
+            // the signal's terms live in the fragment, hold onto it until the next collection
+            if let Some(fragment) = fragment {
+                context.fragments.push(fragment);
+            }
+
             match signal {
                 Signal::Message { value, .. } => {
-                    self.local_data_mut().mailbox.send(value);
+                    local_data.mailbox.send(value);
                 }
                 Signal::PortMessage { from, value, .. } => {
                     // info!(
@@ -391,10 +842,7 @@ impl Process {
                     // );
                     // we only get the binary, so construct message on heap
                     let heap = &self.context_mut().heap;
-                    let binary = Term::from(heap.alloc(value::Boxed {
-                        header: value::BOXED_BINARY,
-                        value,
-                    }));
+                    let binary = Term::rc_binary(heap, value);
                     let msg = tup2!(heap, Term::port(from), tup2!(heap, atom!(DATA), binary));
                     self.local_data_mut().mailbox.send(msg);
                 }
@@ -424,27 +872,166 @@ impl Process {
                         self.local_data_mut().lt_monitors.remove(pos);
                     }
                 }
+                Signal::SystemTask { from, request } => {
+                    self.handle_system_task(from, request);
+                }
+                Signal::AliasMessage { .. } | Signal::Suspend { .. } => unreachable!(),
+                Signal::Resume { from, count } => {
+                    self.handle_resume_signal(from, count);
+                }
+                Signal::SaveCalls {
+                    from,
+                    reference,
+                    len,
+                } => {
+                    let old = self.set_save_calls(len);
+                    let heap = &self.context_mut().heap;
+                    let old = Term::uint64(heap, old as u64);
+                    self.send_reply(&Machine::current(), from, reference, old);
+                }
             }
         }
         Ok(())
     }
 
+    /// Sends `{Reference, Result}` to a process waiting on a request it made.
+    fn send_reply(&self, vm: &Machine, to: PID, reference: Ref, result: Term) {
+        let receiver = vm.process_table.lock().get(to);
+        if let Some(receiver) = receiver {
+            let heap = &self.context_mut().heap;
+            let reference = Term::reference(heap, reference);
+            receiver.send_message(self.pid, tup2!(heap, reference, result));
+        }
+    }
+
+    fn handle_suspend_signal(
+        &self,
+        from: PID,
+        reply: Option<SuspendReply>,
+        fragment: Option<Heap>,
+    ) {
+        let local_data = self.local_data_mut();
+        local_data.suspend_count += 1;
+        local_data.state.insert(StateFlag::SUSPENDED);
+
+        if let Some(reply) = reply {
+            local_data.suspend_replies.push(PendingSuspend {
+                from,
+                reply,
+                _fragment: fragment,
+                cancelled: false,
+            });
+        }
+    }
+
+    fn handle_resume_signal(&self, from: PID, count: usize) {
+        let local_data = self.local_data_mut();
+        local_data.suspend_count = local_data.suspend_count.saturating_sub(count);
+        if local_data.suspend_count == 0 {
+            local_data.state.remove(StateFlag::SUSPENDED);
+        }
+
+        // the requester took the suspend back before it took effect
+        local_data
+            .suspend_replies
+            .iter_mut()
+            .rev()
+            .filter(|pending| pending.from == from && !pending.cancelled)
+            .take(count)
+            .for_each(|pending| pending.cancelled = true);
+    }
+
+    /// Replies to the suspend requests handled so far. Only called once the process actually
+    /// suspended (or got resumed before it did), or when it exits.
+    pub fn reply_suspended(&self, vm: &Machine) {
+        for pending in self.local_data_mut().suspend_replies.drain(..) {
+            self.send_suspend_reply(vm, pending, false);
+        }
+    }
+
+    /// Sends `{Tag, Result}` to a process that asked for a suspend.
+    fn send_suspend_reply(&self, vm: &Machine, pending: PendingSuspend, exiting: bool) {
+        let result = match (pending.cancelled, exiting, pending.reply.sync) {
+            (true, ..) => atom!(NOT_SUSPENDED),
+            (false, false, true) => atom!(TRUE),
+            (false, false, false) => atom!(SUSPENDED),
+            (false, true, true) => atom!(BADARG),
+            (false, true, false) => atom!(EXITED),
+        };
+        let receiver = vm.process_table.lock().get(pending.from);
+        if let Some(receiver) = receiver {
+            let heap = &self.context_mut().heap;
+            receiver.send_message(self.pid, tup2!(heap, pending.reply.tag, result));
+        }
+    }
+
+    /// Looks at the signals sent to the process while it's executing on a dirty scheduler. The
+    /// process state belongs to the process until the dirty BIF returns, so the signals stay queued
+    /// up for the process to handle itself once it's back. Only a `kill`, which terminates the
+    /// process no matter what, is flagged right away, so that the dirty BIF can give up early.
+    pub fn handle_dirty_signals(&self) {
+        let killed = |signal: &Signal| match signal {
+            Signal::Exit { reason, kind, .. } => {
+                *kind == ExitKind::Exit && reason.value == atom!(KILL)
+            }
+            _ => false,
+        };
+
+        if self.local_data().signal_queue.any_external(killed) {
+            self.pending_exit.store(true, Ordering::Release);
+            self.wake_up();
+        }
+    }
+
+    /// Queues up a system task requested by another process. The request was already validated by
+    /// `erts_internal:request_system_task/3`.
+    fn handle_system_task(&self, from: PID, request: Term) {
+        let request = value::Tuple::cast_from(&request).unwrap();
+
+        let task = match request[0].into_variant() {
+            value::Variant::Atom(atom::GARBAGE_COLLECT) => gc::Task::GarbageCollect {
+                major: request[2] == atom!(MAJOR),
+            },
+            value::Variant::Atom(atom::COPY_LITERALS) => gc::Task::CopyLiterals,
+            value::Variant::Atom(atom::CHECK_PROCESS_CODE) => {
+                gc::Task::CheckProcessCode(request[2].to_atom().unwrap())
+            }
+            _ => unreachable!("unknown system task {}", request[0]),
+        };
+        self.local_data_mut().gc.request(from, request[1], task);
+    }
+
+    /// Returns true if the process is running code of `module`, or might return into it.
+    pub fn check_process_code(&self, module: &Module) -> bool {
+        let module = module as *const Module;
+        let context = self.context();
+        let in_module = |ptr: &Ptr| ptr.module == module;
+
+        in_module(&context.ip)
+            || context.cp.as_ref().map_or(false, in_module)
+            || context
+                .callstack
+                .iter()
+                .any(|(_, cp)| cp.as_ref().map_or(false, in_module))
+            || context.stack.iter().any(|term| {
+                term.get_boxed_header() == Ok(value::BOXED_CATCH)
+                    && in_module(term.get_boxed_value::<Ptr>().unwrap())
+            })
+    }
+
     fn handle_monitor_down_signal(&self, signal: Signal) {
         // Create a 'DOWN' message and replace the signal with it...
         if let Signal::MonitorDown {
-            from,
-            reason,
-            reference,
+            reason, reference, ..
         } = signal
         {
-            // assert!(is_immed(reason));
-            let heap = &self.context_mut().heap;
-            let from = Term::pid(from);
-            let reference = Term::reference(heap, reference as usize);
-            let reason = reason.value;
-
-            let msg = tup!(heap, atom!(DOWN_U), reference, atom!(PROCESS), from, reason);
-            self.local_data_mut().mailbox.send(msg);
+            // if we've demonitored in the meantime, the DOWN message must not show up anymore
+            let monitored = match self.local_data_mut().monitors.remove(&reference) {
+                Some(monitored) => monitored,
+                None => return,
+            };
+            let (kind, object) = monitored.down_object(&self.context_mut().heap);
+            self.monitor_down(reference, kind, object, reason.value);
         // bump reds by 8?
         } else {
             unreachable!();
@@ -531,6 +1118,7 @@ impl Process {
         // print!("pid={} exiting reason={}\r\n", self.pid, reason.value);
 
         // set state to exiting
+        self.exiting.store(true, Ordering::Release);
 
         // TODO: cancel timers
 
@@ -541,6 +1129,34 @@ impl Process {
             vm.process_registry.lock().unregister(name);
         }
 
+        // answer any outstanding system tasks and suspend requests, the requesters are waiting
+        // for a reply.
+        while let Some((signal, fragment)) = local_data.signal_queue.receive() {
+            match signal {
+                Signal::SystemTask { from, request } => self.handle_system_task(from, request),
+                Signal::Suspend { from, reply } => {
+                    self.handle_suspend_signal(from, reply, fragment)
+                }
+                Signal::SaveCalls {
+                    from, reference, ..
+                } => self.send_reply(vm, from, reference, atom!(BADARG)),
+                _ => (),
+            }
+        }
+        gc::abort_requests(vm, self);
+        for pending in local_data.suspend_replies.drain(..) {
+            self.send_suspend_reply(vm, pending, true);
+        }
+
+        // resume the processes we suspended
+        for (pid, count) in local_data.suspending.drain() {
+            let msg = Signal::Resume {
+                from: self.pid,
+                count,
+            };
+            self::send_signal(vm, pid, msg);
+        }
+
         // delete links
         for pid in local_data.links.drain() {
             // TODO: reason has to be deep cloned, make a constructor
@@ -555,14 +1171,24 @@ impl Process {
         }
 
         // delete monitors
-        for (reference, pid) in local_data.monitors.drain() {
+        for (reference, monitored) in local_data.monitors.drain() {
             // we're watching someone else
             // send_demonitor(mon)
-            let msg = Signal::Demonitor {
-                from: self.pid,
-                reference,
-            };
-            self::send_signal(vm, pid, msg);
+            match monitored {
+                Monitored::Process(pid) | Monitored::Name(pid, _) => {
+                    let msg = Signal::Demonitor {
+                        from: self.pid,
+                        reference,
+                    };
+                    self::send_signal(vm, pid, msg);
+                }
+                Monitored::Port(id) => {
+                    if let Some(mut port) = vm.port_table.read().lookup(id) {
+                        port.demonitor(self.pid, reference);
+                    }
+                }
+                Monitored::TimeOffset => (),
+            }
         }
 
         for (pid, reference) in local_data.lt_monitors.drain(..) {
@@ -598,6 +1224,13 @@ pub fn allocate(
         module, /*, vm.global_allocator.clone(), &vm.config*/
     );
 
+    // the machine's default heap sizes are in words
+    let gc_info = &mut process.local_data_mut().gc;
+    gc_info.min_heap_size = vm.config.min_heap_size * gc::WORD_SIZE;
+    gc_info.threshold = gc_info.min_heap_size;
+    gc_info.min_bin_vheap_size = vm.config.min_bin_vheap_size * gc::WORD_SIZE;
+    gc_info.bin_vheap_size = gc_info.min_bin_vheap_size;
+
     process_table.map(pid, process.clone());
 
     Ok(process)
@@ -609,9 +1242,40 @@ bitflags! {
         const LINK = 1;
         const MONITOR = 2;
         // const USE_ARGS = 4;
-        // const SYSTEM_PROC = 8;
-        // const OFF_HEAP_MSGQ = 16;
-        // const ON_HEAP_MSGQ = 32;
+        const SYSTEM_PROC = 8;
+        const OFF_HEAP_MSGQ = 16;
+        const ON_HEAP_MSGQ = 32;
+    }
+}
+
+/// Options for spawning a new process, as given to `spawn_opt`.
+pub struct SpawnOpts {
+    pub flags: SpawnFlag,
+    pub max_heap_size: Option<gc::MaxHeapSize>,
+    /// Minimum heap size, in bytes.
+    pub min_heap_size: Option<usize>,
+    /// Minimum binary virtual heap size, in bytes.
+    pub min_bin_vheap_size: Option<usize>,
+    pub fullsweep_after: Option<usize>,
+    pub priority: Option<Priority>,
+    /// Turns the reference of the `monitor` option into an alias.
+    pub monitor_alias: Option<AliasKind>,
+    /// Replaces `'DOWN'` in the `DOWN` message of the `monitor` option.
+    pub monitor_tag: Option<Term>,
+}
+
+impl Default for SpawnOpts {
+    fn default() -> Self {
+        SpawnOpts {
+            flags: SpawnFlag::NONE,
+            max_heap_size: None,
+            min_heap_size: None,
+            min_bin_vheap_size: None,
+            fullsweep_after: None,
+            priority: None,
+            monitor_alias: None,
+            monitor_tag: None,
+        }
     }
 }
 
@@ -622,25 +1286,65 @@ pub fn spawn(
     module: *const Module,
     func: Atom,
     args: Term,
-    flags: SpawnFlag,
+    opts: SpawnOpts,
 ) -> Result<Term, Exception> {
+    // the arguments need to be a proper list that fits into the registers
+    let mut arity = 0;
+    let mut cons = &args;
+    while let Ok(value::Cons { tail, .. }) = cons.cast_into() {
+        arity += 1;
+        cons = tail;
+    }
+    if !cons.is_nil() {
+        return Err(badarg!());
+    }
+    if arity > MAX_REG {
+        return Err(Exception::new(Reason::EXC_SYSTEM_LIMIT));
+    }
+
     let new_proc = allocate(vm, parent.pid, parent.local_data().group_leader, module)?;
     let context = new_proc.context_mut();
     let mut ret = Term::pid(new_proc.pid);
 
-    // Set the arglist into process registers.
-    // TODO: it also needs to deep clone all the vals (for example lists etc)
+    // Set the arglist into process registers, copying them onto the new process heap.
     let mut i = 0;
     let mut cons = &args;
     while let Ok(value::Cons { head, tail }) = cons.cast_into() {
-        context.x[i] = *head;
+        context.x[i] = head.deep_clone(&context.heap);
         i += 1;
         cons = tail;
     }
-    // lastly, the tail
-    context.x[i] = *cons;
 
-    new_proc.local_data_mut().initial_call = MFA(unsafe { (*module).name }, func, i as u32);
+    new_proc.local_data_mut().initial_call = MFA(unsafe { (*module).name }, func, arity as u32);
+
+    let gc = &mut new_proc.local_data_mut().gc;
+    if let Some(min_heap_size) = opts.min_heap_size {
+        gc.set_min_heap_size(min_heap_size);
+    }
+
+    if let Some(max_heap_size) = opts.max_heap_size {
+        gc.set_max_heap_size(max_heap_size);
+    }
+
+    if let Some(min_bin_vheap_size) = opts.min_bin_vheap_size {
+        gc.set_min_bin_vheap_size(min_bin_vheap_size);
+    }
+
+    if let Some(fullsweep_after) = opts.fullsweep_after {
+        gc.fullsweep_after = fullsweep_after;
+    }
+
+    if opts.flags.contains(SpawnFlag::OFF_HEAP_MSGQ) {
+        new_proc.local_data_mut().flags |= Flag::OFF_HEAP_MSGQ;
+    }
+
+    if opts.flags.contains(SpawnFlag::SYSTEM_PROC) {
+        new_proc.local_data_mut().flags |= Flag::SYSTEM_PROC;
+    }
+
+    if let Some(priority) = opts.priority {
+        new_proc.local_data_mut().priority = priority;
+    }
 
     // print!(
     //     "Spawning... pid={} mfa={} args={}\r\n",
@@ -653,26 +1357,36 @@ pub fn spawn(
     let func = unsafe {
         (*module)
             .funs
-            .get(&(func, i as u32)) // arglist arity
+            .get(&(func, arity as u32))
             .expect("process::spawn could not locate func")
     };
 
     context.ip.ptr = *func;
 
     // Check if this process should be initially linked to its parent.
-    if flags.contains(SpawnFlag::LINK) {
+    if opts.flags.contains(SpawnFlag::LINK) {
         new_proc.local_data_mut().links.insert(parent.pid);
 
         parent.local_data_mut().links.insert(new_proc.pid);
     }
 
-    if flags.contains(SpawnFlag::MONITOR) {
-        let reference = vm.next_ref();
+    if opts.flags.contains(SpawnFlag::MONITOR) {
+        let reference = match opts.monitor_alias {
+            Some(kind) => {
+                let reference = alias_ref(vm, parent.pid);
+                parent.local_data_mut().aliases.insert(reference, kind);
+                reference
+            }
+            None => vm.next_ref(),
+        };
+        if let Some(tag) = opts.monitor_tag {
+            parent.local_data_mut().monitor_tags.insert(reference, tag);
+        }
 
         parent
             .local_data_mut()
             .monitors
-            .insert(reference, new_proc.pid);
+            .insert(reference, Monitored::Process(new_proc.pid));
 
         new_proc
             .local_data_mut()
@@ -684,15 +1398,13 @@ pub fn spawn(
     }
 
     let future = crate::vm::run_with_error_handling(new_proc);
-    vm.process_pool
-        .executor()
-        .spawn(future);
+    vm.process_pool.spawn(future);
 
     Ok(ret)
 }
 
-/// Send a message from `sender` to `pid`.
-pub fn send_message(vm: &Machine, sender: PID, pid: Term, msg: Term) -> Result<Term, Exception> {
+/// Send a message from `sender` to `pid`. Returns the amount of bytes copied.
+pub fn send_message(vm: &Machine, sender: PID, pid: Term, msg: Term) -> Result<usize, Exception> {
     // println!("sending from={} to={}, msg={}", sender, pid, msg);
     let receiver = match pid.into_variant() {
         value::Variant::Atom(name) => {
@@ -704,22 +1416,43 @@ pub fn send_message(vm: &Machine, sender: PID, pid: Term, msg: Term) -> Result<T
             }
         }
         value::Variant::Pid(pid) => vm.process_table.lock().get(pid),
+        // aliases are references, messages to inactive ones get dropped silently
+        value::Variant::Pointer(..) if pid.get_boxed_header() == Ok(value::BOXED_REF) => {
+            let alias = *pid.get_boxed_value::<Ref>().unwrap();
+            let owner = alias_owner(alias).and_then(|owner| vm.process_table.lock().get(owner));
+            return Ok(owner.map_or(0, |owner| owner.send_alias_message(vm, sender, alias, msg)));
+        }
         _ => return Err(badarg!()),
     };
 
     if let Some(receiver) = receiver {
-        receiver.send_message(sender, msg);
+        Ok(receiver.send_message(sender, msg))
     } else {
         println!("NOTFOUND");
+        Ok(0)
     }
-
-    Ok(msg)
 }
 
 /// Send a signal to `pid`.
 pub fn send_signal(vm: &Machine, pid: PID, signal: Signal) -> bool {
-    if let Some(receiver) = vm.process_table.lock().get(pid) {
+    let receiver = vm.process_table.lock().get(pid);
+    if let Some(receiver) = receiver {
+        let notify = match signal {
+            Signal::Message { .. } | Signal::AliasMessage { .. } | Signal::PortMessage { .. } => {
+                false
+            }
+            _ => true,
+        };
         receiver.send_signal(signal);
+
+        // the receiver can't handle the signal until it's back on a normal scheduler, ask the
+        // dirty process signal handler to do it on its behalf
+        if notify && receiver.is_running_dirty() {
+            let handler = vm.dirty_signal_handler.load(Ordering::Relaxed) as PID;
+            if let Some(handler) = vm.process_table.lock().get(handler) {
+                handler.send_message(pid, Term::pid(pid));
+            }
+        }
         return true;
     }
     false
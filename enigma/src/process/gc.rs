@@ -0,0 +1,1202 @@
+//! Per-process garbage collector.
+//!
+//! A collection traces all the live data reachable from the process roots (the live X registers,
+//! the stack, the mailbox, the process dictionary, monitor tags and any in-flight exception).
+//! Objects on the process heap are marked in place, at line granularity, and blocks without any
+//! live lines are returned to the global block pool. Objects in message fragments are evacuated
+//! into the heap, after which the fragments are released.
+//!
+//! Terms that point outside of the memory owned by the process (module literals, for example) are
+//! left untouched. Signals still sitting in the signal queue carry their own heap fragments, so
+//! they don't need to be traced.
+//!
+//! Marking in place leaves holes in the heap. Once the live data takes up less than half of the
+//! heap, or after `fullsweep_after` in-place collections, the next collection compacts it instead:
+//! everything is copied into a fresh heap. This is what we call a major collection.
+//!
+//! Collections happen at safepoints (`test_heap`/`allocate_heap`, or a `wait` in a receive), where
+//! the number of live X registers is known. Other processes can request a collection through
+//! `erts_internal:request_system_task/3`, those requests are also served at the next safepoint.
+//!
+//! Binaries are kept off-heap and shared between processes; the heap only holds references to
+//! them. Each heap tracks these references, so that the ones held by dead objects get released
+//! by a collection, and accounts for the size of the binaries it references: the binary virtual
+//! heap. Once that grows past `bin_vheap_size`, a collection is triggered even if the heap itself
+//! is small, so that processes juggling large binaries don't keep them alive for too long.
+//!
+//! Module literals aren't copied onto the heap. When a module gets purged, its literal area is
+//! queued up for release, and `erts_literal_area_collector` asks every process to copy out the
+//! literals it still references with a `copy_literals` system task. That's a collection which
+//! treats the literal area like a fragment, except that the literals are copied instead of moved
+//! since other processes share them.
+//!
+//! After each collection the `max_heap_size` limit is checked: a process that's still over the
+//! limit gets killed, and/or reported to the system logger.
+use super::{ExecutionContext, Process, RcProcess, PID};
+use crate::exception::{Exception, Reason};
+use crate::immix::block::{Region, DEFAULT_BLOCK_SIZE};
+use crate::immix::Heap;
+use crate::value::{self, Boxed, CastFrom, Cons, Term, Tuple, Variant};
+use crate::vm::Machine;
+use crate::{atom, bitstring, exception, instruction, module, process};
+use hashbrown::HashMap;
+use std::sync::atomic::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{cmp, mem, ptr};
+
+/// Default minimum heap size (in bytes). Collections are never triggered below this size.
+pub const DEFAULT_MIN_HEAP_SIZE: usize = DEFAULT_BLOCK_SIZE;
+
+/// Default minimum binary virtual heap size (in bytes), same as BEAM's 46422 words.
+pub const DEFAULT_MIN_BIN_VHEAP_SIZE: usize = 46422 * WORD_SIZE;
+
+/// Default number of in-place collections before a major collection is forced.
+pub const DEFAULT_FULLSWEEP_AFTER: usize = 65535;
+
+/// Size of a word in bytes. Heap sizes are reported to Erlang in words.
+pub const WORD_SIZE: usize = mem::size_of::<Term>();
+
+/// Words of live data the collector gets through per reduction.
+const GC_WORDS_PER_RED: usize = 10;
+
+/// Garbage collector state and settings for a single process.
+#[derive(Debug)]
+pub struct GcInfo {
+    /// Heap size (in bytes) past which the next collection is triggered.
+    pub threshold: usize,
+    /// Minimum heap size (in bytes).
+    pub min_heap_size: usize,
+    /// Number of collections performed so far.
+    pub collections: usize,
+    /// Total number of bytes reclaimed so far.
+    pub reclaimed: usize,
+    /// Whether the next collection should compact the heap.
+    pub compact: bool,
+    /// Number of in-place collections after which a major collection is forced.
+    pub fullsweep_after: usize,
+    /// Number of in-place collections since the last major collection.
+    pub minor_gcs: usize,
+    /// Size of the live data (in bytes) after the last collection.
+    pub recent_size: usize,
+    /// Pending collections requested by other processes.
+    pub requests: Vec<Request>,
+    /// Heap size limit.
+    pub max_heap_size: MaxHeapSize,
+    /// Binary virtual heap size (in bytes) past which the next collection is triggered.
+    pub bin_vheap_size: usize,
+    /// Minimum binary virtual heap size (in bytes).
+    pub min_bin_vheap_size: usize,
+}
+
+/// The `max_heap_size` process flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxHeapSize {
+    /// Maximum total heap size (in words), 0 means there's no limit.
+    pub size: usize,
+    /// Whether to kill the process once it goes over the limit.
+    pub kill: bool,
+    /// Whether to send an error report to the system logger once the process goes over the limit.
+    pub error_logger: bool,
+}
+
+impl Default for MaxHeapSize {
+    fn default() -> Self {
+        MaxHeapSize {
+            size: 0,
+            kill: true,
+            error_logger: true,
+        }
+    }
+}
+
+impl MaxHeapSize {
+    /// Parses either a word count, or a map with `size` and optionally `kill` and
+    /// `error_logger`. A limit smaller than `min_heap_size` (in words) is invalid.
+    pub fn from_term(term: Term, min_heap_size: usize) -> Option<Self> {
+        let mut max_heap_size = MaxHeapSize::default();
+
+        let size = match term.into_variant() {
+            Variant::Integer(size) => size,
+            Variant::Pointer(..) => {
+                let map = value::Map::cast_from(&term).ok()?;
+                let valid_keys = map.0.keys().all(|key| match key.into_variant() {
+                    Variant::Atom(atom::SIZE)
+                    | Variant::Atom(atom::KILL)
+                    | Variant::Atom(atom::ERROR_LOGGER) => true,
+                    _ => false,
+                });
+                if !valid_keys {
+                    return None;
+                }
+
+                if let Some(kill) = map.0.get(&atom!(KILL)) {
+                    max_heap_size.kill = kill.to_bool()?;
+                }
+                if let Some(error_logger) = map.0.get(&atom!(ERROR_LOGGER)) {
+                    max_heap_size.error_logger = error_logger.to_bool()?;
+                }
+                match map.0.get(&atom!(SIZE))?.into_variant() {
+                    Variant::Integer(size) => size,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        if size < 0 || (size != 0 && (size as usize) < min_heap_size) {
+            return None;
+        }
+        max_heap_size.size = size as usize;
+        Some(max_heap_size)
+    }
+
+    pub fn to_term(&self, heap: &Heap) -> Term {
+        map!(heap,
+            atom!(SIZE) => Term::uint64(heap, self.size as u64),
+            atom!(KILL) => Term::boolean(self.kill),
+            atom!(ERROR_LOGGER) => Term::boolean(self.error_logger)
+        )
+    }
+}
+
+/// A system task requested via `erts_internal:request_system_task/3`. Once it's done, a
+/// `{Tag, ReqId, Result}` message is sent back to the requester.
+#[derive(Debug)]
+pub struct Request {
+    from: PID,
+    task: Task,
+    id: Term,
+    /// Owns the request id, since it has to survive the collection.
+    _heap: Heap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// `garbage_collect`: run a collection, replies with `true`.
+    GarbageCollect { major: bool },
+    /// `copy_literals`: copy everything that's still referenced from the active literal area
+    /// onto the heap, replies with `ok`.
+    CopyLiterals,
+    /// `check_process_code`: replies with whether the process is running old code of the module.
+    CheckProcessCode(atom::Atom),
+}
+
+impl Task {
+    fn tag(self) -> Term {
+        match self {
+            Task::GarbageCollect { .. } => atom!(GARBAGE_COLLECT),
+            Task::CopyLiterals => atom!(COPY_LITERALS),
+            Task::CheckProcessCode(..) => atom!(CHECK_PROCESS_CODE),
+        }
+    }
+
+    /// The reply sent if the process exits before getting to the task.
+    fn noproc(self) -> Term {
+        match self {
+            Task::CopyLiterals => atom!(OK),
+            _ => atom!(FALSE),
+        }
+    }
+}
+
+impl GcInfo {
+    pub fn new() -> Self {
+        GcInfo {
+            threshold: DEFAULT_MIN_HEAP_SIZE,
+            min_heap_size: DEFAULT_MIN_HEAP_SIZE,
+            collections: 0,
+            reclaimed: 0,
+            compact: false,
+            fullsweep_after: DEFAULT_FULLSWEEP_AFTER,
+            minor_gcs: 0,
+            recent_size: 0,
+            requests: Vec::new(),
+            max_heap_size: MaxHeapSize::default(),
+            bin_vheap_size: DEFAULT_MIN_BIN_VHEAP_SIZE,
+            min_bin_vheap_size: DEFAULT_MIN_BIN_VHEAP_SIZE,
+        }
+    }
+
+    /// Sets a new minimum heap size (in bytes), growing the threshold along with it.
+    pub fn set_min_heap_size(&mut self, min_heap_size: usize) {
+        self.min_heap_size = min_heap_size;
+        self.threshold = cmp::max(self.threshold, min_heap_size);
+    }
+
+    /// Sets a new minimum binary virtual heap size (in bytes).
+    pub fn set_min_bin_vheap_size(&mut self, min_bin_vheap_size: usize) {
+        self.min_bin_vheap_size = min_bin_vheap_size;
+        self.bin_vheap_size = cmp::max(self.bin_vheap_size, min_bin_vheap_size);
+    }
+
+    /// Sets a new heap size limit, making sure we collect before going over it.
+    pub fn set_max_heap_size(&mut self, max_heap_size: MaxHeapSize) {
+        self.max_heap_size = max_heap_size;
+        if max_heap_size.size != 0 {
+            self.threshold = cmp::min(self.threshold, max_heap_size.size * WORD_SIZE);
+        }
+    }
+
+    /// Queues up a system task requested by `from`, identified by `id`.
+    pub fn request(&mut self, from: PID, id: Term, task: Task) {
+        let heap = Heap::fragment();
+        self.requests.push(Request {
+            from,
+            task,
+            id: id.deep_clone(&heap),
+            _heap: heap,
+        });
+    }
+}
+
+/// Size of the process heap including all of the attached message fragments, in bytes.
+pub fn heap_size(context: &ExecutionContext) -> usize {
+    context
+        .fragments
+        .iter()
+        .fold(context.heap.size(), |acc, fragment| acc + fragment.size())
+}
+
+/// Size of the off-heap binaries referenced from the process heap and the attached message
+/// fragments, in bytes.
+pub fn bin_vheap(context: &ExecutionContext) -> usize {
+    context
+        .fragments
+        .iter()
+        .fold(context.heap.bin_vheap(), |acc, fragment| acc + fragment.bin_vheap())
+}
+
+/// Runs a collection if the process heap or the binary virtual heap grew past its threshold, or
+/// if another process requested one. `live` is the number of live X registers. Fails if the
+/// process has to be killed for going over its `max_heap_size`.
+#[inline]
+pub fn maybe_collect(vm: &Machine, process: &RcProcess, live: usize) -> Result<(), Exception> {
+    let gc = &process.local_data().gc;
+
+    if !gc.requests.is_empty() {
+        serve_requests(vm, process, live);
+    } else if heap_size(process.context()) > gc.threshold
+        || bin_vheap(process.context()) > gc.bin_vheap_size
+    {
+        collect(process, live);
+    } else {
+        return Ok(());
+    }
+    check_max_heap_size(vm, process)
+}
+
+/// Enforces the `max_heap_size` limit, meant to be called right after a collection.
+pub fn check_max_heap_size(vm: &Machine, process: &RcProcess) -> Result<(), Exception> {
+    let max_heap_size = process.local_data().gc.max_heap_size;
+    let total_heap_size = heap_size(process.context()) / WORD_SIZE;
+
+    if max_heap_size.size == 0 || total_heap_size <= max_heap_size.size {
+        return Ok(());
+    }
+
+    if max_heap_size.error_logger {
+        report_max_heap_size(vm, process, total_heap_size);
+    }
+
+    if max_heap_size.kill {
+        // same as being sent a kill signal: the exit can't be caught.
+        process.context_mut().catches = 0;
+        return Err(Exception::with_value(Reason::EXT_EXIT, atom!(KILLED)));
+    }
+    Ok(())
+}
+
+/// Sends an error report about the process going over its heap limit to the system logger.
+fn report_max_heap_size(vm: &Machine, process: &RcProcess, total_heap_size: usize) {
+    let local_data = process.local_data();
+    let max_heap_size = local_data.gc.max_heap_size;
+    let heap = &process.context().heap;
+
+    let format = bitstring!(
+        heap,
+        concat!(
+            "     Process:          ~p~n",
+            "     Context:          maximum heap size reached~n",
+            "     Max Heap Size:    ~p~n",
+            "     Total Heap Size:  ~p~n",
+            "     Kill:             ~p~n",
+            "     Error Logger:     ~p~n",
+            "     GC Info:          ~p~n",
+        )
+    );
+    let args = vec![
+        Term::pid(process.pid),
+        Term::uint64(heap, max_heap_size.size as u64),
+        Term::uint64(heap, total_heap_size as u64),
+        Term::boolean(max_heap_size.kill),
+        Term::boolean(max_heap_size.error_logger),
+        info(process, heap),
+    ];
+    let args = Cons::from_iter(args.into_iter(), heap);
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_micros() as u64)
+        .unwrap_or(0);
+    let meta = map!(heap,
+        atom!(PID) => Term::pid(process.pid),
+        atom!(GL) => Term::pid(local_data.group_leader),
+        atom!(TIME) => Term::uint64(heap, time),
+        atom!(ERROR_LOGGER) => map!(heap, atom!(TAG) => atom!(ERROR), atom!(EMULATOR) => atom!(TRUE))
+    );
+
+    let msg = tup!(heap, atom!(LOG), atom!(ERROR), format, args, meta);
+    let logger = vm.system_logger.load(Ordering::Relaxed) as PID;
+    let _ = super::send_message(vm, process.pid, Term::pid(logger), msg);
+}
+
+/// Builds the `garbage_collection_info` list for `process` on `heap`.
+pub fn info(process: &RcProcess, heap: &Heap) -> Term {
+    let context = process.context();
+    let words = |bytes: usize| Term::uint64(heap, (bytes / WORD_SIZE) as u64);
+    let fragments: usize = context.fragments.iter().map(Heap::size).sum();
+
+    // there's no generational heap (yet), so the old heap is always empty.
+    let items = vec![
+        tup2!(heap, atom!(OLD_HEAP_BLOCK_SIZE), Term::int(0)),
+        tup2!(heap, atom!(HEAP_BLOCK_SIZE), words(context.heap.size())),
+        tup2!(heap, atom!(MBUF_SIZE), words(fragments)),
+        tup2!(heap, atom!(RECENT_SIZE), words(process.local_data().gc.recent_size)),
+        tup2!(heap, atom!(STACK_SIZE), Term::uint(heap, context.stack.len() as u32)),
+        tup2!(heap, atom!(OLD_HEAP_SIZE), Term::int(0)),
+        tup2!(heap, atom!(HEAP_SIZE), words(context.heap.used())),
+        tup2!(heap, atom!(BIN_VHEAP_SIZE), words(bin_vheap(context))),
+        tup2!(heap, atom!(BIN_VHEAP_BLOCK_SIZE), words(process.local_data().gc.bin_vheap_size)),
+        tup2!(heap, atom!(BIN_OLD_VHEAP_SIZE), Term::int(0)),
+        tup2!(heap, atom!(BIN_OLD_VHEAP_BLOCK_SIZE), Term::int(0)),
+    ];
+    Cons::from_iter(items.into_iter(), heap)
+}
+
+/// Runs a major collection, keeping the first `live` X registers.
+pub fn collect_major(process: &RcProcess, live: usize) {
+    process.local_data_mut().gc.compact = true;
+    collect(process, live);
+}
+
+#[cold]
+fn serve_requests(vm: &Machine, process: &RcProcess, live: usize) {
+    let requests = mem::replace(&mut process.local_data_mut().gc.requests, Vec::new());
+    let requested = |task| requests.iter().any(|request| request.task == task);
+
+    if requested(Task::GarbageCollect { major: true }) {
+        process.local_data_mut().gc.compact = true;
+    }
+
+    if requested(Task::CopyLiterals) {
+        // the area can't be released until we reply
+        let areas = vm.literal_areas.lock();
+        collect_literals(process, live, areas.active.as_ref());
+    } else if requests.iter().any(|request| match request.task {
+        Task::GarbageCollect { .. } => true,
+        _ => false,
+    }) {
+        collect(process, live);
+    }
+
+    let heap = &process.context().heap;
+    for request in requests {
+        let result = match request.task {
+            Task::GarbageCollect { .. } => atom!(TRUE),
+            Task::CopyLiterals => atom!(OK),
+            Task::CheckProcessCode(module) => {
+                let registry = vm.modules.lock();
+                let old = registry.lookup_old(module);
+                Term::boolean(old.map_or(false, |module| process.check_process_code(module)))
+            }
+        };
+        let reply = tup3!(heap, request.task.tag(), request.id.deep_clone(heap), result);
+        // the requester might have exited in the meantime, that's fine.
+        let _ = super::send_message(vm, process.pid, Term::pid(request.from), reply);
+    }
+}
+
+/// Replies to the pending requests of an exiting process, so that nobody waits on them forever.
+pub fn abort_requests(vm: &Machine, process: &Process) {
+    let requests = mem::replace(&mut process.local_data_mut().gc.requests, Vec::new());
+
+    let heap = &process.context().heap;
+    for request in requests {
+        let id = request.id.deep_clone(heap);
+        let reply = tup3!(heap, request.task.tag(), id, request.task.noproc());
+        let _ = super::send_message(vm, process.pid, Term::pid(request.from), reply);
+    }
+}
+
+/// Collects the process heap, keeping the first `live` X registers.
+pub fn collect(process: &RcProcess, live: usize) {
+    collect_literals(process, live, None)
+}
+
+/// Compacts the heap of a hibernating process down to the first `live` X registers and the
+/// message queue. The new heap is made of small blocks, so that an idle process holds on to as
+/// little memory as possible.
+pub fn hibernate(process: &RcProcess, live: usize) {
+    process.local_data_mut().gc.compact = true;
+    collect_into(process, live, None, Heap::fragment());
+}
+
+/// Collects the process heap, keeping the first `live` X registers. Anything still referenced in
+/// the `literals` area gets copied onto the heap, so that the area can be released.
+pub fn collect_literals(process: &RcProcess, live: usize, literals: Option<&Heap>) {
+    collect_into(process, live, literals, Heap::new())
+}
+
+/// Collects the process heap, evacuated objects get copied into `heap`.
+fn collect_into(process: &RcProcess, live: usize, literals: Option<&Heap>, heap: Heap) {
+    let local_data = process.local_data_mut();
+    let context = process.context_mut();
+    let before = heap_size(context);
+    let compact = local_data.gc.compact;
+
+    if !compact {
+        context.heap.clear_marks();
+    }
+
+    // references to off-heap binaries, sorted out once we know what's live
+    let mut off_heap = context.heap.take_off_heap();
+    for fragment in &context.fragments {
+        off_heap.extend(fragment.take_off_heap());
+    }
+
+    let mut collector = Collector::new(context, &heap, compact, literals);
+
+    for reg in context.x[..live].iter_mut() {
+        *reg = collector.copy(*reg);
+    }
+
+    for slot in context.stack.iter_mut() {
+        *slot = collector.copy(*slot);
+    }
+
+    if let Some(exc) = &mut context.exc {
+        exc.value = collector.copy(exc.value);
+        exc.trace = collector.copy(exc.trace);
+    }
+
+    for message in local_data.mailbox.iter_mut() {
+        *message = collector.copy(*message);
+    }
+
+    let dictionary = mem::replace(&mut local_data.dictionary, HashMap::new());
+    local_data.dictionary = dictionary
+        .into_iter()
+        .map(|(key, val)| (collector.copy(key), collector.copy(val)))
+        .collect();
+
+    for tag in local_data.monitor_tags.values_mut() {
+        *tag = collector.copy(*tag);
+    }
+
+    // Release the references held by dead objects. Evacuated objects are tracked by the new heap
+    // already, so only the ones that stay in place are kept.
+    let mut kept = Vec::new();
+    for entry in off_heap {
+        let live = collector.forward.contains_key(&entry.object);
+        match collector.locate(entry.object as *const u8) {
+            Location::InPlace(..) if live => kept.push(entry),
+            _ if live => (),
+            _ => unsafe { entry.release() },
+        }
+    }
+
+    if compact {
+        // swap in the new heap, releasing the old one.
+        context.heap = heap;
+    } else {
+        context.heap.sweep();
+        if heap.used() > 0 {
+            context.heap.append(heap);
+        }
+    }
+    context.fragments.clear();
+    context.heap.restore_off_heap(kept);
+    let used = context.heap.used();
+    // charge the process for the work, which is proportional to the live data
+    context.bump_reductions(1 + used / WORD_SIZE / GC_WORDS_PER_RED);
+
+    let gc = &mut local_data.gc;
+    gc.collections += 1;
+    gc.reclaimed += before.saturating_sub(used);
+    gc.recent_size = used;
+    gc.minor_gcs = if compact { 0 } else { gc.minor_gcs + 1 };
+    // leave enough room for the live data to double before collecting again.
+    gc.threshold = cmp::max(
+        gc.min_heap_size,
+        cmp::max(context.heap.size(), used * 2),
+    );
+    gc.bin_vheap_size = cmp::max(gc.min_bin_vheap_size, context.heap.bin_vheap() * 2);
+    // compacting right after a compaction won't gain us anything.
+    gc.compact =
+        (!compact && used < context.heap.size() / 2) || gc.minor_gcs >= gc.fullsweep_after;
+
+    // collect before going over the heap limit, so we notice in time.
+    let max_heap_size = gc.max_heap_size.size * WORD_SIZE;
+    if max_heap_size != 0 && context.heap.size() < max_heap_size {
+        gc.threshold = cmp::min(gc.threshold, max_heap_size);
+    }
+}
+
+/// Where an object lives, relative to the heap being collected.
+enum Location {
+    /// Memory we don't own, like module literals.
+    Foreign,
+    /// A literal area that's about to be released, the object has to be copied out. Unlike an
+    /// evacuated object, it's shared with other processes so it can't be moved.
+    Literal,
+    /// Memory that's being released, the object has to be copied out.
+    Evacuate,
+    /// The object stays where it is.
+    InPlace(Region),
+}
+
+struct Collector<'a> {
+    /// Memory owned by the process, sorted by start address. The flag is set for regions that
+    /// are being evacuated.
+    regions: Vec<(Region, bool)>,
+    /// Addresses of already copied objects, mapped to their new location.
+    forward: HashMap<usize, Term>,
+    /// The heap we're copying into.
+    to: &'a Heap,
+    /// Address ranges of the literal area that's being released.
+    literals: Vec<(usize, usize)>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(
+        context: &ExecutionContext,
+        to: &'a Heap,
+        compact: bool,
+        literals: Option<&Heap>,
+    ) -> Self {
+        let fragments = context
+            .fragments
+            .iter()
+            .flat_map(|fragment| fragment.regions())
+            .map(|region| (region, true));
+        let heap = context.heap.regions().map(|region| (region, compact));
+
+        let mut regions: Vec<_> = fragments.chain(heap).collect();
+        regions.sort_unstable_by_key(|(region, _)| region.start);
+
+        Collector {
+            regions,
+            forward: HashMap::new(),
+            to,
+            literals: literals.map_or_else(Vec::new, |heap| heap.ranges().collect()),
+        }
+    }
+
+    fn locate<T>(&self, ptr: *const T) -> Location {
+        let ptr = ptr as usize;
+        let i = match self
+            .regions
+            .binary_search_by_key(&ptr, |(region, _)| region.start)
+        {
+            Ok(i) => i,
+            Err(0) => return self.locate_foreign(ptr),
+            Err(i) => i - 1,
+        };
+
+        match self.regions[i] {
+            (region, _) if ptr >= region.end => self.locate_foreign(ptr),
+            (_, true) => Location::Evacuate,
+            (region, false) => Location::InPlace(region),
+        }
+    }
+
+    fn locate_foreign(&self, ptr: usize) -> Location {
+        if self
+            .literals
+            .iter()
+            .any(|&(start, end)| ptr >= start && ptr < end)
+        {
+            Location::Literal
+        } else {
+            Location::Foreign
+        }
+    }
+
+    fn copy(&mut self, term: Term) -> Term {
+        match term.into_variant() {
+            Variant::Cons(..) => self.copy_list(term),
+            Variant::Pointer(ptr) => self.copy_boxed(term, ptr),
+            _ => term,
+        }
+    }
+
+    /// Copies (or marks) the list spine iteratively, so that long lists don't overflow the stack.
+    fn copy_list(&mut self, term: Term) -> Term {
+        let to = self.to;
+        let mut result = Term::nil();
+        let mut last: Option<*mut Cons> = None;
+        let mut current = term;
+
+        loop {
+            let (next, done) = match current.into_variant() {
+                Variant::Cons(ptr) => match self.locate(ptr) {
+                    // memory we don't own
+                    Location::Foreign => (current, true),
+                    _ if self.forward.contains_key(&(ptr as usize)) => {
+                        (self.forward[&(ptr as usize)], true)
+                    }
+                    // the rest of the list gets copied in one go
+                    Location::Literal => {
+                        let new = current.deep_clone(to);
+                        self.forward.insert(ptr as usize, new);
+                        (new, true)
+                    }
+                    Location::Evacuate => {
+                        let cons = unsafe { &*ptr };
+                        let new = to.alloc(Cons {
+                            head: Term::nil(),
+                            tail: Term::nil(),
+                        });
+                        let new_term = Term::from(&mut *new);
+                        self.forward.insert(ptr as usize, new_term);
+
+                        new.head = self.copy(cons.head);
+                        current = cons.tail;
+                        (new_term, false)
+                    }
+                    Location::InPlace(region) => {
+                        let cons = unsafe { &mut *(ptr as *mut Cons) };
+                        let term = current;
+                        self.forward.insert(ptr as usize, term);
+                        region.mark(ptr as usize, mem::size_of::<Cons>());
+
+                        cons.head = self.copy(cons.head);
+                        current = cons.tail;
+                        (term, false)
+                    }
+                },
+                // an improper tail
+                _ => (self.copy(current), true),
+            };
+
+            match last {
+                Some(cons) => unsafe { (*cons).tail = next },
+                None => result = next,
+            }
+
+            if done {
+                return result;
+            }
+
+            last = match next.into_variant() {
+                Variant::Cons(ptr) => Some(ptr as *mut Cons),
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn copy_boxed(&mut self, term: Term, ptr: *const value::Header) -> Term {
+        let location = self.locate(ptr);
+
+        if let Location::Foreign = location {
+            return term;
+        }
+
+        if let Some(forwarded) = self.forward.get(&(ptr as usize)).copied() {
+            return forwarded;
+        }
+
+        if let Location::InPlace(region) = location {
+            self.forward.insert(ptr as usize, term);
+            unsafe { self.trace_boxed(ptr, region) };
+            return term;
+        }
+
+        if let Location::Literal = location {
+            let new = term.deep_clone(self.to);
+            self.forward.insert(ptr as usize, new);
+            return new;
+        }
+
+        let to = self.to;
+
+        // Values that don't hold any terms are moved over as-is: the old heap never runs
+        // destructors, so reading the value out of it transfers ownership.
+        macro_rules! relocate {
+            ($type:ty) => {{
+                let value = ptr::read(&(*(ptr as *const Boxed<$type>)).value);
+                Term::boxed(to, *ptr, value)
+            }};
+        }
+
+        let new = unsafe {
+            match *ptr {
+                value::BOXED_TUPLE => {
+                    let tuple = &*(ptr as *const Tuple);
+                    let new = value::tuple(to, tuple.len);
+                    for (i, val) in tuple.iter().enumerate() {
+                        ptr::write(&mut new[i], self.copy(*val));
+                    }
+                    Term::from(new)
+                }
+                value::BOXED_MAP => {
+                    let map = ptr::read(&(*(ptr as *const Boxed<value::Map>)).value);
+                    let mut new_map = value::HAMT::new();
+                    for (key, val) in map.0.iter() {
+                        new_map.insert(self.copy(*key), self.copy(*val));
+                    }
+                    Term::map(to, new_map)
+                }
+                value::BOXED_CLOSURE => {
+                    let mut closure = ptr::read(&(*(ptr as *const Boxed<value::Closure>)).value);
+                    if let Some(binding) = &mut closure.binding {
+                        for val in binding.iter_mut() {
+                            *val = self.copy(*val);
+                        }
+                    }
+                    Term::closure(to, closure)
+                }
+                value::BOXED_REF => relocate!(process::Ref),
+                // these hold a reference to an off-heap binary, which the new heap has to track
+                value::BOXED_BINARY => {
+                    let value = ptr::read(&(*(ptr as *const Boxed<bitstring::RcBinary>)).value);
+                    Term::rc_binary(to, value)
+                }
+                value::BOXED_BIGINT => relocate!(value::BigInt),
+                value::BOXED_CATCH => relocate!(instruction::Ptr),
+                value::BOXED_STACKTRACE => relocate!(exception::StackTrace),
+                value::BOXED_MATCHBUFFER => {
+                    let value = ptr::read(&(*(ptr as *const Boxed<bitstring::MatchBuffer>)).value);
+                    Term::matchbuffer(to, value)
+                }
+                value::BOXED_SUBBINARY => {
+                    let value = ptr::read(&(*(ptr as *const Boxed<bitstring::SubBinary>)).value);
+                    Term::subbinary(to, value)
+                }
+                value::BOXED_MODULE => relocate!(*mut module::Module),
+                value::BOXED_EXPORT => relocate!(module::MFA),
+                value::BOXED_FILE => relocate!(std::fs::File),
+                value::BOXED_BUFFER => relocate!(crate::bif::prim_buffer::Buffer),
+                value::BOXED_REGEX => relocate!(regex::bytes::Regex),
+                header => unreachable!("gc: unknown boxed header {}", header),
+            }
+        };
+
+        self.forward.insert(ptr as usize, new);
+        new
+    }
+
+    /// Updates the terms inside a boxed value that stays in place, and marks the lines it
+    /// occupies.
+    unsafe fn trace_boxed(&mut self, ptr: *const value::Header, region: Region) {
+        macro_rules! size {
+            ($type:ty) => {
+                mem::size_of::<Boxed<$type>>()
+            };
+        }
+
+        let size = match *ptr {
+            value::BOXED_TUPLE => {
+                let tuple = &mut *(ptr as *mut Tuple);
+                for val in tuple.iter_mut() {
+                    *val = self.copy(*val);
+                }
+                mem::size_of::<Tuple>() + tuple.len as usize * mem::size_of::<Term>()
+            }
+            value::BOXED_MAP => {
+                let map = &mut (*(ptr as *mut Boxed<value::Map>)).value;
+                let mut new_map = value::HAMT::new();
+                for (key, val) in map.0.iter() {
+                    new_map.insert(self.copy(*key), self.copy(*val));
+                }
+                map.0 = new_map;
+                size!(value::Map)
+            }
+            value::BOXED_CLOSURE => {
+                let closure = &mut (*(ptr as *mut Boxed<value::Closure>)).value;
+                if let Some(binding) = &mut closure.binding {
+                    for val in binding.iter_mut() {
+                        *val = self.copy(*val);
+                    }
+                }
+                size!(value::Closure)
+            }
+            value::BOXED_REF => size!(process::Ref),
+            value::BOXED_BINARY => size!(bitstring::RcBinary),
+            value::BOXED_BIGINT => size!(value::BigInt),
+            value::BOXED_CATCH => size!(instruction::Ptr),
+            value::BOXED_STACKTRACE => size!(exception::StackTrace),
+            value::BOXED_MATCHBUFFER => size!(bitstring::MatchBuffer),
+            value::BOXED_SUBBINARY => size!(bitstring::SubBinary),
+            value::BOXED_MODULE => size!(*mut module::Module),
+            value::BOXED_EXPORT => size!(module::MFA),
+            value::BOXED_FILE => size!(std::fs::File),
+            value::BOXED_BUFFER => size!(crate::bif::prim_buffer::Buffer),
+            value::BOXED_REGEX => size!(regex::bytes::Regex),
+            header => unreachable!("gc: unknown boxed header {}", header),
+        };
+
+        region.mark(ptr as usize, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::Module;
+    use crate::vm::Machine;
+
+    fn pointer(term: Term) -> usize {
+        match term.into_variant() {
+            Variant::Pointer(ptr) => ptr as usize,
+            Variant::Cons(ptr) => ptr as usize,
+            _ => panic!("not a boxed term"),
+        }
+    }
+
+    #[test]
+    fn test_collect_keeps_live_data() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        // produce plenty of garbage
+        for i in 0..10_000 {
+            tup2!(&context.heap, Term::int(i), Term::nil());
+        }
+
+        let heap = &context.heap;
+        let list = cons!(heap, Term::int(1), cons!(heap, Term::int(2), Term::nil()));
+        context.x[0] = tup2!(heap, atom!(OK), list);
+        context.x[1] = list;
+
+        let before = heap_size(context);
+        collect(&process, 2);
+
+        assert!(heap_size(context) < before);
+        assert_eq!(process.local_data().gc.collections, 1);
+
+        let heap = &context.heap;
+        let list = cons!(heap, Term::int(1), cons!(heap, Term::int(2), Term::nil()));
+        assert_eq!(context.x[0], tup2!(heap, atom!(OK), list));
+        assert_eq!(context.x[1], list);
+        assert!(context.heap.contains(pointer(context.x[0]) as *const u8));
+
+        // sharing is preserved
+        let tuple = Tuple::cast_from(&context.x[0]).unwrap();
+        assert_eq!(pointer(tuple[1]), pointer(context.x[1]));
+    }
+
+    #[test]
+    fn test_collect_roots() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+        let heap = &context.heap;
+
+        context.stack.push(tup2!(heap, Term::int(1), Term::int(2)));
+        process
+            .local_data_mut()
+            .dictionary
+            .insert(atom!(OK), tup2!(heap, Term::int(3), Term::int(4)));
+        process
+            .local_data_mut()
+            .mailbox
+            .send(tup2!(heap, Term::int(5), Term::int(6)));
+
+        collect(&process, 0);
+
+        let heap = &context.heap;
+        assert_eq!(context.stack[0], tup2!(heap, Term::int(1), Term::int(2)));
+        assert!(heap.contains(pointer(context.stack[0]) as *const u8));
+
+        let val = process.local_data().dictionary[&atom!(OK)];
+        assert_eq!(val, tup2!(heap, Term::int(3), Term::int(4)));
+        assert!(heap.contains(pointer(val) as *const u8));
+
+        let msg = process.local_data_mut().mailbox.receive().unwrap();
+        assert_eq!(msg, tup2!(heap, Term::int(5), Term::int(6)));
+        assert!(heap.contains(pointer(msg) as *const u8));
+    }
+
+    #[test]
+    fn test_collect_skips_foreign_terms() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        // literals live on the module heap and are never moved
+        let literals = Heap::new();
+        let literal = tup2!(&literals, Term::int(1), Term::int(2));
+        context.x[0] = literal;
+
+        collect(&process, 1);
+
+        assert_eq!(pointer(context.x[0]), pointer(literal));
+    }
+
+    #[test]
+    fn test_collect_compacts_fragmented_heap() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        // a single live tuple surrounded by garbage
+        for i in 0..10_000 {
+            tup2!(&context.heap, Term::int(i), Term::nil());
+        }
+        context.x[0] = tup2!(&context.heap, Term::int(1), Term::int(2));
+        let before = pointer(context.x[0]);
+
+        // the first collection marks in place, but notices the heap is mostly empty
+        collect(&process, 1);
+        assert_eq!(pointer(context.x[0]), before);
+        assert!(process.local_data().gc.compact);
+
+        // so the next one moves the tuple into a fresh heap
+        collect(&process, 1);
+        assert_ne!(pointer(context.x[0]), before);
+        assert!(!process.local_data().gc.compact);
+        assert_eq!(
+            context.x[0],
+            tup2!(&context.heap, Term::int(1), Term::int(2))
+        );
+    }
+
+    #[test]
+    fn test_requested_collection() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let id = tup2!(&process.context().heap, Term::int(1), Term::int(2));
+        let task = Task::GarbageCollect { major: true };
+        process.local_data_mut().gc.request(process.pid, id, task);
+
+        maybe_collect(&vm, &process, 0).unwrap();
+
+        let gc = &process.local_data().gc;
+        assert_eq!(gc.collections, 1);
+        assert!(gc.requests.is_empty());
+
+        let heap = &process.context().heap;
+        let msg = process.local_data_mut().mailbox.receive().unwrap();
+        let expected = tup3!(
+            heap,
+            atom!(GARBAGE_COLLECT),
+            tup2!(heap, Term::int(1), Term::int(2)),
+            atom!(TRUE)
+        );
+        assert_eq!(msg, expected);
+        assert!(heap.contains(pointer(msg) as *const u8));
+    }
+
+    #[test]
+    fn test_max_heap_size_from_term() {
+        let heap = Heap::new();
+
+        let max = MaxHeapSize::from_term(Term::int(10_000), 100).unwrap();
+        assert_eq!(max.size, 10_000);
+        assert!(max.kill && max.error_logger);
+
+        let map = map!(&heap, atom!(SIZE) => Term::int(0), atom!(KILL) => atom!(FALSE));
+        let max = MaxHeapSize::from_term(map, 100).unwrap();
+        assert_eq!(max.size, 0);
+        assert!(!max.kill && max.error_logger);
+        assert_eq!(MaxHeapSize::from_term(max.to_term(&heap), 100), Some(max));
+
+        // below the minimum heap size
+        assert_eq!(MaxHeapSize::from_term(Term::int(10), 100), None);
+        // unknown keys
+        let map = map!(&heap, atom!(SIZE) => Term::int(0), atom!(VALUE) => atom!(TRUE));
+        assert_eq!(MaxHeapSize::from_term(map, 100), None);
+    }
+
+    #[test]
+    fn test_max_heap_size_kills() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        process.local_data_mut().gc.set_max_heap_size(MaxHeapSize {
+            size: 2 * DEFAULT_MIN_HEAP_SIZE / WORD_SIZE,
+            kill: true,
+            error_logger: false,
+        });
+
+        // keep enough live data to go over the limit
+        let mut list = Term::nil();
+        for i in 0..10_000 {
+            list = cons!(&context.heap, Term::int(i), list);
+        }
+        context.x[0] = list;
+
+        let res = maybe_collect(&vm, &process, 1);
+        assert_eq!(
+            res,
+            Err(Exception::with_value(Reason::EXT_EXIT, atom!(KILLED)))
+        );
+        assert_eq!(context.catches, 0);
+    }
+
+    #[test]
+    fn test_collect_adopts_fragments() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        let fragment = Heap::fragment();
+        let msg = tup2!(&fragment, Term::int(1), Term::int(2));
+        context.fragments.push(fragment);
+        process.local_data_mut().mailbox.send(msg);
+
+        collect(&process, 0);
+
+        assert!(context.fragments.is_empty());
+        let msg = process.local_data_mut().mailbox.receive().unwrap();
+        assert!(context.heap.contains(pointer(msg) as *const u8));
+    }
+
+    #[test]
+    fn test_collect_skips_off_heap_messages() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        let fragment = Heap::fragment();
+        let msg = tup2!(&fragment, Term::int(1), Term::int(2));
+        process.local_data_mut().mailbox.send_off_heap(msg, Some(fragment));
+
+        collect(&process, 0);
+        assert_eq!(process.local_data_mut().mailbox.receive(), Some(msg));
+        assert!(!context.heap.contains(pointer(msg) as *const u8));
+
+        // receiving moves the message onto the process heap
+        assert_eq!(process.receive().unwrap(), Some(msg));
+        assert_eq!(context.fragments.len(), 1);
+
+        collect(&process, 0);
+        let msg = process.local_data_mut().mailbox.receive().unwrap();
+        assert!(context.heap.contains(pointer(msg) as *const u8));
+    }
+
+    #[test]
+    fn test_collect_releases_binaries() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+        let heap = &context.heap;
+
+        let live = Term::binary(heap, bitstring::Binary::from(vec![1; 1024]));
+        let dead = Term::binary(heap, bitstring::Binary::from(vec![2; 512]));
+        let live_ref = live.get_boxed_value::<bitstring::RcBinary>().unwrap().clone();
+        let dead_ref = dead.get_boxed_value::<bitstring::RcBinary>().unwrap().clone();
+        context.x[0] = live;
+        assert_eq!(bin_vheap(context), 1536);
+
+        collect(&process, 1);
+        assert_eq!(bin_vheap(context), 1024);
+        // only our own reference is left
+        assert!(dead_ref.is_unique());
+        assert!(!live_ref.is_unique());
+
+        // compacting moves the reference over to the new heap
+        collect_major(&process, 1);
+        assert_eq!(bin_vheap(context), 1024);
+        assert!(!live_ref.is_unique());
+
+        collect(&process, 0);
+        assert_eq!(bin_vheap(context), 0);
+        assert!(live_ref.is_unique());
+    }
+
+    #[test]
+    fn test_bin_vheap_triggers_collection() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        // the heap itself stays tiny
+        let size = DEFAULT_MIN_BIN_VHEAP_SIZE + 1;
+        Term::binary(&context.heap, bitstring::Binary::from(vec![0; size]));
+        assert!(heap_size(context) <= process.local_data().gc.threshold);
+
+        maybe_collect(&vm, &process, 0).unwrap();
+        assert_eq!(process.local_data().gc.collections, 1);
+        assert_eq!(bin_vheap(context), 0);
+    }
+
+    #[test]
+    fn test_collect_copies_literals() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        let area = Heap::new();
+        let literal = tup2!(&area, Term::int(1), cons!(&area, Term::int(2), Term::nil()));
+        context.x[0] = literal;
+        context.x[1] = literal;
+
+        // other literal areas are left alone
+        collect(&process, 2);
+        assert!(area.contains(pointer(context.x[0]) as *const u8));
+
+        collect_literals(&process, 2, Some(&area));
+        assert!(!area.contains(pointer(context.x[0]) as *const u8));
+        assert!(context.heap.contains(pointer(context.x[0]) as *const u8));
+        assert_eq!(context.x[0], literal);
+        // sharing is preserved
+        assert_eq!(pointer(context.x[0]), pointer(context.x[1]));
+    }
+
+    #[test]
+    fn test_hibernate_shrinks_heap() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        for i in 0..10_000 {
+            tup2!(&context.heap, Term::int(i), Term::nil());
+        }
+        let heap = &context.heap;
+        context.x[0] = atom!(OK);
+        context.x[1] = tup2!(heap, Term::int(1), Term::int(2));
+        context.x[2] = cons!(heap, context.x[1], Term::nil());
+
+        hibernate(&process, 3);
+        assert!(context.heap.size() < DEFAULT_MIN_HEAP_SIZE);
+        assert_eq!(context.x[1], tup2!(&context.heap, Term::int(1), Term::int(2)));
+        assert_eq!(context.x[2], cons!(&context.heap, context.x[1], Term::nil()));
+    }
+
+    #[test]
+    fn test_abort_requests() {
+        let vm = Machine::new();
+        let module: *const Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+
+        let gc = &mut process.local_data_mut().gc;
+        gc.request(process.pid, Term::int(1), Task::GarbageCollect { major: false });
+        gc.request(process.pid, Term::int(2), Task::CopyLiterals);
+        abort_requests(&vm, &process);
+        assert!(process.local_data().gc.requests.is_empty());
+
+        let heap = &process.context().heap;
+        let mailbox = &mut process.local_data_mut().mailbox;
+        let expected = tup3!(heap, atom!(GARBAGE_COLLECT), Term::int(1), atom!(FALSE));
+        assert_eq!(mailbox.receive().unwrap(), expected);
+        let expected = tup3!(heap, atom!(COPY_LITERALS), Term::int(2), atom!(OK));
+        assert_eq!(mailbox.receive().unwrap(), expected);
+    }
+}
@@ -18,12 +18,17 @@
 //!         panic!("No PIDs available!");
 //!     }
 //!
+//! ## Numbers and serials
+//!
+//! Like in BEAM, a PID is made up of a process number and a serial, shown as
+//! `<0.Number.Serial>`. PIDs are handed out in order, so once all the numbers
+//! have been used they get reused with the next serial. A stale PID therefore
+//! never points at a newer process that got the same number.
+//!
 //! ## Recycling
 //!
-//! PIDs are recycled once a certain number of PIDs has been used. The exact
-//! amount of PIDs that can be used before recycling depends on the system
-//! architecture. In most cases PID recycling will only occur very rarely, if
-//! ever at all.
+//! PIDs are recycled once all the serials have been used as well. Even then
+//! a PID is only handed out again if no existing process holds it.
 //!
 //! ## PID Availability
 //!
@@ -39,6 +44,33 @@ pub type PID = u32;
 /// The maximum PID value.
 pub const MAX_PID: PID = u32::MAX;
 
+/// Number of bits used for the process number, the rest of the PID is the serial.
+pub const NUMBER_BITS: u32 = 15;
+
+/// The maximum process number.
+pub const MAX_NUMBER: u32 = (1 << NUMBER_BITS) - 1;
+
+/// The maximum serial.
+pub const MAX_SERIAL: u32 = MAX_PID >> NUMBER_BITS;
+
+/// Builds a PID out of a process number and a serial.
+pub fn from_parts(number: u32, serial: u32) -> Option<PID> {
+    if number > MAX_NUMBER || serial > MAX_SERIAL {
+        return None;
+    }
+    Some((serial << NUMBER_BITS) | number)
+}
+
+/// The process number of a PID.
+pub fn number(pid: PID) -> u32 {
+    pid & MAX_NUMBER
+}
+
+/// The serial of a PID.
+pub fn serial(pid: PID) -> u32 {
+    pid >> NUMBER_BITS
+}
+
 #[derive(Debug, Default)]
 pub struct Table<T: Clone> {
     /// The PID to use for the next process.
@@ -47,6 +79,9 @@ pub struct Table<T: Clone> {
     /// When set to true, previously used PIDs may be recycled.
     recycle: bool,
 
+    /// Maximum number of processes that can exist at the same time.
+    limit: usize,
+
     /// PIDs of existing processes, and their corresponding processes.
     ///
     /// An entry's value may be set to None, indicating that the PID has been
@@ -56,18 +91,32 @@ pub struct Table<T: Clone> {
 
 impl<T: Clone> Table<T> {
     pub fn new() -> Self {
+        Self::with_limit(MAX_PID as usize)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
         Table {
             next_pid: 0,
             recycle: false,
+            limit: std::cmp::min(limit, MAX_PID as usize),
             processes: HashMap::new(),
         }
     }
 
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of processes, including reserved PIDs.
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
     /// Reserves a new PID.
     ///
     /// If no PID could be reserved a None value is returned.
     pub fn reserve(&mut self) -> Option<PID> {
-        while (self.processes.len() as u32) < MAX_PID {
+        while self.processes.len() < self.limit {
             let pid = self.next_pid();
 
             if self.recycle && self.processes.contains_key(&pid) {
@@ -167,6 +216,56 @@ mod tests {
         assert_eq!(pid2.unwrap(), 1);
     }
 
+    #[test]
+    fn test_reserve_with_limit() {
+        let mut table = Table::<()>::with_limit(2);
+
+        assert!(table.reserve().is_some());
+        let pid = table.reserve().unwrap();
+        assert!(table.reserve().is_none());
+
+        table.release(pid);
+        assert!(table.reserve().is_some());
+    }
+
+    #[test]
+    fn test_reserve_next_serial() {
+        let mut table = Table::<()>::new();
+        table.next_pid = MAX_NUMBER;
+
+        let pid = table.reserve().unwrap();
+        assert_eq!((number(pid), serial(pid)), (MAX_NUMBER, 0));
+
+        // numbers get reused with the next serial
+        let pid = table.reserve().unwrap();
+        assert_eq!((number(pid), serial(pid)), (0, 1));
+        assert_eq!(from_parts(0, 1), Some(pid));
+    }
+
+    #[test]
+    fn test_stale_pid() {
+        let mut table = Table::new();
+        let stale = table.reserve().unwrap();
+        table.map(stale, 10);
+        table.release(stale);
+
+        table.next_pid = from_parts(number(stale), 1).unwrap();
+        let pid = table.reserve().unwrap();
+        table.map(pid, 20);
+
+        assert_eq!(number(pid), number(stale));
+        assert!(table.get(stale).is_none());
+        assert!(!table.contains_key(stale));
+        assert_eq!(table.get(pid).unwrap(), 20);
+    }
+
+    #[test]
+    fn test_from_parts() {
+        assert_eq!(from_parts(MAX_NUMBER, MAX_SERIAL), Some(MAX_PID));
+        assert_eq!(from_parts(MAX_NUMBER + 1, 0), None);
+        assert_eq!(from_parts(0, MAX_SERIAL + 1), None);
+    }
+
     #[test]
     fn test_map() {
         let mut table = Table::new();
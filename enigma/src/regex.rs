@@ -7,10 +7,14 @@ use regex::bytes::{Regex, RegexBuilder};
 // use std::error::Error;
 // pub use error::Result;
 
+/// Subject size in bytes above which `re:run` moves over to a dirty CPU scheduler.
+const DIRTY_SUBJECT_SIZE: usize = 64 * 1024;
+
 pub mod bif {
     use super::*;
     use crate::bif::Result;
     use crate::process::RcProcess;
+    use crate::scheduler;
     use crate::value::{self, CastFrom, Term};
     use crate::vm;
 
@@ -33,6 +37,11 @@ pub mod bif {
         // println!("run/3: {} {}", args[0], args[1]);
         let string = crate::bif::erlang::list_to_iodata(args[0]).unwrap(); // TODO: error handling
 
+        // large subjects would block the scheduler for too long
+        if string.len() > DIRTY_SUBJECT_SIZE && scheduler::current().is_none() {
+            return scheduler::schedule(process, Some(scheduler::Dirty::Cpu), run_3, args);
+        }
+
         let regex = match args[1].get_boxed_header() {
             Ok(value::BOXED_REGEX) => {
                 let regex = regex::bytes::Regex::cast_from(&args[1]).unwrap();
@@ -0,0 +1,476 @@
+//! Run queues, deciding which processes get to execute on the process pool.
+//!
+//! Only as many processes as there are schedulers execute at once. A process holds on to a
+//! scheduler slot while it executes, and gives it up whenever it yields or waits for a message.
+//! Free slots are handed out by priority, same as on BEAM: `max` processes run before `high`
+//! ones, which run before `normal` and `low` ones. `normal` and `low` processes share a queue,
+//! but a `low` process gets put back at the end of it until it has been skipped over
+//! `RESCHEDULE_LOW` times, so it runs less often without ever being starved.
+//!
+//! BIFs and NIFs that would block a scheduler for a long time run on dirty schedulers instead:
+//! separate thread pools for CPU-bound (`dirty_cpu`) and I/O-bound (`dirty_io`) work. While the
+//! BIF is running there, the process gives up its normal scheduler slot.
+//!
+//! A process suspended by `erlang:suspend_process/1,2` keeps handling signals, but doesn't get
+//! back on the run queue until it's resumed. A hibernating process stays off it until it gets a
+//! message.
+use crate::atom::{self, Atom};
+use crate::bif;
+use crate::exception::{Exception, Reason};
+use crate::process::{self, RcProcess, StateFlag, Trap};
+use crate::value::{self, CastInto, Term};
+use crate::vm::Machine;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// Process scheduling priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    Max,
+    High,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn from_atom(atom: Atom) -> Option<Self> {
+        match atom {
+            atom::MAX => Some(Priority::Max),
+            atom::HIGH => Some(Priority::High),
+            atom::NORMAL => Some(Priority::Normal),
+            atom::LOW => Some(Priority::Low),
+            _ => None,
+        }
+    }
+
+    pub fn to_atom(self) -> Atom {
+        match self {
+            Priority::Max => atom::MAX,
+            Priority::High => atom::HIGH,
+            Priority::Normal => atom::NORMAL,
+            Priority::Low => atom::LOW,
+        }
+    }
+
+    /// Index of the run queue processes with this priority wait in.
+    fn queue(self) -> usize {
+        match self {
+            Priority::Max => 0,
+            Priority::High => 1,
+            Priority::Normal | Priority::Low => 2,
+        }
+    }
+}
+
+/// Number of times a `low` process gets skipped over in favor of `normal` ones.
+const RESCHEDULE_LOW: usize = 8;
+
+/// A process waiting for a scheduler slot.
+struct Waiter {
+    priority: Priority,
+    skipped: usize,
+    wake: oneshot::Sender<()>,
+}
+
+struct Inner {
+    /// Number of free scheduler slots. Only non-zero while nobody is waiting.
+    free: usize,
+    /// Waiting processes, one queue for `max`, `high`, and `normal` + `low`.
+    queues: [VecDeque<Waiter>; 3],
+}
+
+impl Inner {
+    /// Picks the process that gets the next free slot.
+    fn next(&mut self) -> Option<Waiter> {
+        for queue in &mut self.queues[..2] {
+            if let Some(waiter) = queue.pop_front() {
+                return Some(waiter);
+            }
+        }
+
+        let queue = &mut self.queues[2];
+        while let Some(mut waiter) = queue.pop_front() {
+            if waiter.priority == Priority::Low && waiter.skipped < RESCHEDULE_LOW {
+                waiter.skipped += 1;
+                queue.push_back(waiter);
+                continue;
+            }
+            return Some(waiter);
+        }
+        None
+    }
+}
+
+pub struct RunQueues {
+    /// Total number of scheduler slots.
+    schedulers: usize,
+    inner: Mutex<Inner>,
+}
+
+impl RunQueues {
+    pub fn new(schedulers: usize) -> Self {
+        RunQueues {
+            schedulers,
+            inner: Mutex::new(Inner {
+                free: schedulers,
+                queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            }),
+        }
+    }
+
+    /// Waits until a scheduler slot is available for a process with the given priority.
+    pub async fn acquire(&self, priority: Priority) {
+        let wait = {
+            let mut inner = self.inner.lock();
+            if inner.free > 0 {
+                inner.free -= 1;
+                return;
+            }
+            let (wake, wait) = oneshot::channel();
+            inner.queues[priority.queue()].push_back(Waiter {
+                priority,
+                skipped: 0,
+                wake,
+            });
+            wait
+        };
+        // the slot is handed over to us directly by `release`
+        let _ = wait.await;
+    }
+
+    /// Gives up a scheduler slot, handing it over to the next waiting process.
+    pub fn release(&self) {
+        let mut inner = self.inner.lock();
+        while let Some(waiter) = inner.next() {
+            if waiter.wake.send(()).is_ok() {
+                return;
+            }
+        }
+        inner.free += 1;
+    }
+
+    /// Number of processes waiting to be scheduled.
+    pub fn waiting(&self) -> usize {
+        let inner = self.inner.lock();
+        inner.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Number of processes currently holding on to a scheduler slot.
+    pub fn running(&self) -> usize {
+        self.schedulers - self.inner.lock().free
+    }
+
+    /// Number of processes waiting to be scheduled, per priority.
+    pub fn len(&self, priority: Priority) -> usize {
+        let inner = self.inner.lock();
+        inner.queues[priority.queue()]
+            .iter()
+            .filter(|waiter| waiter.priority == priority)
+            .count()
+    }
+}
+
+/// Scheduling state of a process, as reported by `process_info(Pid, status)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Off the run queues, waiting for a message or a timeout.
+    Waiting,
+    /// Waiting for a scheduler slot.
+    Runnable,
+    /// Holding on to a scheduler slot.
+    Running,
+}
+
+impl Status {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Status::Waiting,
+            1 => Status::Runnable,
+            _ => Status::Running,
+        }
+    }
+
+    pub fn to_atom(self) -> Atom {
+        match self {
+            Status::Waiting => atom::WAITING,
+            Status::Runnable => atom::RUNNABLE,
+            Status::Running => atom::RUNNING,
+        }
+    }
+}
+
+/// Waits until the process gets a scheduler slot, it's runnable until then.
+pub async fn acquire(vm: &Machine, process: &RcProcess) {
+    process.set_status(Status::Runnable);
+    vm.run_queues.acquire(process.priority()).await;
+    process.set_status(Status::Running);
+}
+
+/// Gives up the scheduler slot of the process for as long as it's waiting on `future`.
+pub async fn suspend<F: Future>(vm: &Machine, process: &RcProcess, future: F) -> F::Output {
+    process.set_status(Status::Waiting);
+    vm.run_queues.release();
+    let res = future.await;
+    acquire(vm, process).await;
+    res
+}
+
+/// Number of dirty I/O scheduler threads, same as the BEAM default.
+pub const DIRTY_IO_SCHEDULERS: usize = 10;
+
+/// Dirty scheduler type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dirty {
+    Cpu,
+    Io,
+}
+
+impl Dirty {
+    pub fn to_atom(self) -> Atom {
+        match self {
+            Dirty::Cpu => atom::DIRTY_CPU,
+            Dirty::Io => atom::DIRTY_IO,
+        }
+    }
+}
+
+thread_local!(
+    static CURRENT: Cell<Option<Dirty>> = Cell::new(None);
+    static ID: Cell<Option<usize>> = Cell::new(None);
+);
+
+/// Type of the dirty scheduler the current thread belongs to, `None` for normal schedulers.
+pub fn current() -> Option<Dirty> {
+    CURRENT.with(Cell::get)
+}
+
+/// Marks the current thread as a dirty scheduler thread.
+pub fn set_current(dirty: Dirty) {
+    CURRENT.with(|current| current.set(Some(dirty)))
+}
+
+/// Index of the scheduler the current thread belongs to, `None` for other threads. Normal
+/// schedulers come first, followed by dirty CPU and dirty I/O schedulers.
+pub fn id() -> Option<usize> {
+    ID.with(Cell::get)
+}
+
+pub fn set_id(id: usize) {
+    ID.with(|current| current.set(Some(id)))
+}
+
+/// Reschedules the process so that `bif` gets called with `args` on the given scheduler type
+/// (`None` being a normal scheduler). The caller needs to return the result right away.
+pub fn schedule(
+    process: &RcProcess,
+    dirty: Option<Dirty>,
+    bif: bif::Fn,
+    args: &[Term],
+) -> bif::Result {
+    let context = process.context_mut();
+    // most of the time the arguments already are the X registers
+    if args.as_ptr() != context.x.as_ptr() {
+        context.x[..args.len()].copy_from_slice(args);
+    }
+    context.trap = Some(Trap {
+        bif,
+        arity: args.len(),
+        tail: false,
+        dirty,
+    });
+    Err(Exception::new(Reason::YIELD))
+}
+
+/// Runs a BIF on a dirty scheduler, giving up the scheduler slot of the process until it's done.
+pub async fn run_dirty(
+    vm: &Machine,
+    process: &RcProcess,
+    dirty: Dirty,
+    bif: bif::Fn,
+    args: &[Term],
+) -> bif::Result {
+    let pool = match dirty {
+        Dirty::Cpu => &vm.dirty_cpu,
+        Dirty::Io => &vm.runtime,
+    };
+    let (tx, rx) = oneshot::channel();
+    let job = process.clone();
+    let args = args.to_vec();
+
+    process.running_dirty.store(true, Ordering::Release);
+    pool.spawn(async move {
+        let vm = Machine::current();
+        let started = Instant::now();
+        let res = bif(&vm, &job, &args);
+        vm.stats.charge(started.elapsed());
+        let _ = tx.send(res);
+    });
+    let res = suspend(vm, process, rx).await;
+    process.running_dirty.store(false, Ordering::Release);
+
+    // signals that arrived while running dirty were left for us, exit signals included
+    process.pending_exit.store(false, Ordering::Release);
+    process.process_incoming()?;
+
+    res.expect("dirty scheduler dropped the job")
+}
+
+/// Keeps a suspended process off the run queue until it gets resumed, handling incoming signals
+/// in the meantime. Called whenever the process gets scheduled in or wakes up from a receive, so
+/// that's when a suspend takes effect.
+pub async fn wait_suspended(vm: &Machine, process: &RcProcess) -> Result<(), Exception> {
+    loop {
+        // the suspend took effect (or got taken back), let the requesters know
+        process.reply_suspended(vm);
+        if !process.is_suspended() {
+            return Ok(());
+        }
+
+        let context = process.context_mut();
+        let cancel = match context.recv_channel.take() {
+            Some(cancel) => cancel,
+            None => {
+                let (trigger, cancel) = oneshot::channel::<()>();
+                context.timeout = Some(trigger);
+                cancel
+            }
+        };
+        // woken up by the next signal
+        let _ = suspend(vm, process, cancel).await;
+        process.process_incoming()?;
+    }
+}
+
+/// Puts the process into hibernation for `erlang:hibernate/3`, X registers 0 to 2 hold the
+/// module, function and arguments to wake up in. The call stack gets thrown away, so returning
+/// from that function exits the process. Unless there's a message waiting already, the heap is
+/// compacted down to the live data before the process goes to sleep. Returns once there's a
+/// message to handle.
+pub async fn hibernate(vm: &Machine, process: &RcProcess) -> Result<(), Exception> {
+    let context = process.context_mut();
+
+    let mut args = context.x[2];
+    while let Ok(value::Cons { tail, .. }) = args.cast_into() {
+        args = *tail;
+    }
+    if !context.x[0].is_atom() || !context.x[1].is_atom() || !args.is_nil() {
+        return Err(badarg!());
+    }
+
+    context.stack = Vec::new();
+    context.callstack = Vec::new();
+    context.cp = None;
+    context.catches = 0;
+    context.timeout.take();
+    process.local_data_mut().mailbox.reset();
+
+    process.process_incoming()?;
+    if !process.local_data().mailbox.has_messages() {
+        process::gc::hibernate(process, 3);
+    }
+
+    while !process.local_data().mailbox.has_messages() {
+        let cancel = process.context_mut().recv_channel.take().unwrap();
+        process.local_data_mut().state.insert(StateFlag::HIBERNATED);
+        let _ = suspend(vm, process, cancel).await;
+        process.local_data_mut().state.remove(StateFlag::HIBERNATED);
+
+        process.process_incoming()?;
+        wait_suspended(vm, process).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_hibernate() {
+        let vm = crate::vm::Machine::new();
+        let module: *const crate::module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        let context = process.context_mut();
+
+        // the arguments have to be a proper list
+        context.x[0] = Term::atom(atom::ERLANG);
+        context.x[1] = Term::atom(atom::APPLY);
+        context.x[2] = atom!(OK);
+        assert!(block_on(hibernate(&vm, &process)).is_err());
+
+        // a waiting message wakes the process up right away, the stack is gone either way
+        context.x[2] = Term::nil();
+        context.stack.push(Term::int(1));
+        context.cp = Some(context.ip);
+        process.send_message(process.pid, atom!(OK));
+        assert!(block_on(hibernate(&vm, &process)).is_ok());
+        assert!(context.stack.is_empty());
+        assert!(context.cp.is_none());
+        assert!(process.local_data().mailbox.has_messages());
+    }
+
+    #[test]
+    fn test_acquire_free_slot() {
+        let queues = RunQueues::new(1);
+        block_on(queues.acquire(Priority::Normal));
+        assert_eq!(queues.inner.lock().free, 0);
+        queues.release();
+        assert_eq!(queues.inner.lock().free, 1);
+    }
+
+    #[test]
+    fn test_release_by_priority() {
+        let queues = RunQueues::new(0);
+        let mut inner = queues.inner.lock();
+        let mut waiting = Vec::new();
+        for priority in &[Priority::Low, Priority::Normal, Priority::High, Priority::Max] {
+            let (wake, wait) = oneshot::channel();
+            inner.queues[priority.queue()].push_back(Waiter {
+                priority: *priority,
+                skipped: 0,
+                wake,
+            });
+            waiting.push((*priority, wait));
+        }
+        assert_eq!(inner.next().unwrap().priority, Priority::Max);
+        assert_eq!(inner.next().unwrap().priority, Priority::High);
+        // low gets skipped over in favor of normal
+        assert_eq!(inner.next().unwrap().priority, Priority::Normal);
+        assert_eq!(inner.next().unwrap().priority, Priority::Low);
+        assert!(inner.next().is_none());
+    }
+
+    #[test]
+    fn test_low_is_not_starved() {
+        let queues = RunQueues::new(0);
+        let mut inner = queues.inner.lock();
+        let mut waiting = Vec::new();
+        let mut push = |inner: &mut Inner, priority: Priority| {
+            let (wake, wait) = oneshot::channel();
+            inner.queues[priority.queue()].push_back(Waiter {
+                priority,
+                skipped: 0,
+                wake,
+            });
+            waiting.push(wait);
+        };
+
+        push(&mut *inner, Priority::Low);
+        for _ in 0..RESCHEDULE_LOW * 2 {
+            push(&mut *inner, Priority::Normal);
+        }
+
+        let mut scheduled = Vec::new();
+        while let Some(waiter) = inner.next() {
+            scheduled.push(waiter.priority);
+        }
+        let low = scheduled.iter().position(|p| *p == Priority::Low).unwrap();
+        assert!(low > 0 && low < scheduled.len() - 1);
+    }
+}
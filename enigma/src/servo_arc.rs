@@ -354,6 +354,12 @@ impl<T: ?Sized> Arc<T> {
         }
     }
 
+    /// The current reference count.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().count.load(Acquire)
+    }
+
     /// Whether or not the `Arc` is uniquely owned (is the refcount 1?)
     #[inline]
     pub fn is_unique(&self) -> bool {
@@ -3,6 +3,7 @@ use std::collections::VecDeque;
 
 use crate::bitstring;
 use crate::exception::Exception;
+use crate::immix::Heap;
 use crate::port;
 use crate::process::{Ref, PID};
 use crate::value::Term;
@@ -25,6 +26,12 @@ pub enum Signal {
         from: PID,
         value: Term,
     },
+    /// A message sent through an alias, dropped if the alias isn't active anymore.
+    AliasMessage {
+        from: PID,
+        alias: Ref,
+        value: Term,
+    },
     PortMessage {
         from: port::ID,
         value: bitstring::RcBinary,
@@ -48,17 +55,113 @@ pub enum Signal {
         from: PID,
         reference: Ref,
     },
+    SystemTask {
+        from: PID,
+        request: Term,
+    },
+    /// Suspends the receiver on behalf of `from`. If `reply` is set, `{Tag, Result}` gets sent
+    /// back once the receiver actually suspended.
+    Suspend {
+        from: PID,
+        reply: Option<SuspendReply>,
+    },
+    /// Takes back `count` suspends made by `from`.
+    Resume {
+        from: PID,
+        count: usize,
+    },
+    /// Sets `save_calls` to `len` on behalf of `from`, the receiver replies with
+    /// `{Reference, OldValue}`, or `{Reference, badarg}` if it exits first.
+    SaveCalls {
+        from: PID,
+        reference: Ref,
+        len: usize,
+    },
+}
+
+/// How to report back to the process that asked for a suspend.
+#[derive(Debug, Clone, Copy)]
+pub struct SuspendReply {
+    pub tag: Term,
+    /// Synchronous requests reply with `true` (or `badarg` if the process exits), asynchronous
+    /// ones with `suspended`, `not_suspended` or `exited`.
+    pub sync: bool,
 }
 
+impl Signal {
+    /// Copies any terms carried by the signal into a heap fragment that travels with it. The
+    /// sender's heap can get collected (or freed) before the receiver gets to the signal, so
+    /// signals can never point into it.
+    fn into_envelope(self) -> Envelope {
+        match self {
+            Signal::Message { from, value } => {
+                let (value, heap) = copy_to_fragment(value);
+                (Signal::Message { from, value }, heap)
+            }
+            Signal::AliasMessage { from, alias, value } => {
+                let (value, heap) = copy_to_fragment(value);
+                (Signal::AliasMessage { from, alias, value }, heap)
+            }
+            Signal::Exit { from, reason, kind } => {
+                let (value, heap) = copy_to_fragment(reason.value);
+                let reason = Exception::with_value(reason.reason, value);
+                (Signal::Exit { from, reason, kind }, heap)
+            }
+            Signal::MonitorDown {
+                from,
+                reason,
+                reference,
+            } => {
+                let (value, heap) = copy_to_fragment(reason.value);
+                let reason = Exception::with_value(reason.reason, value);
+                (
+                    Signal::MonitorDown {
+                        from,
+                        reason,
+                        reference,
+                    },
+                    heap,
+                )
+            }
+            Signal::SystemTask { from, request } => {
+                let (request, heap) = copy_to_fragment(request);
+                (Signal::SystemTask { from, request }, heap)
+            }
+            Signal::Suspend {
+                from,
+                reply: Some(reply),
+            } => {
+                let (tag, heap) = copy_to_fragment(reply.tag);
+                let reply = Some(SuspendReply { tag, ..reply });
+                (Signal::Suspend { from, reply }, heap)
+            }
+            signal => (signal, None),
+        }
+    }
+}
+
+/// Copies a term into a new heap fragment, unless it's an immediate.
+fn copy_to_fragment(value: Term) -> (Term, Option<Heap>) {
+    if value.is_immed() {
+        return (value, None);
+    }
+    let heap = Heap::fragment();
+    (value.deep_clone(&heap), Some(heap))
+}
+
+/// A signal, along with the heap fragment owning the terms it carries (if any). The receiving
+/// process adopts the fragment once it processes the signal.
+pub type Envelope = (Signal, Option<Heap>);
+
 #[derive(Default, Debug)]
 pub struct SignalQueue {
     /// Internal mailbox from which the process is safe to read.
     /// It only holds messages, other signals are processed as we read the external queue.
-    internal: VecDeque<Signal>,
+    internal: VecDeque<Envelope>,
 
     /// External mailbox, to which other processes can write (while holding the lock)
     /// It holds a mixture of different signals and messages.
-    external: VecDeque<Signal>,
+    external: VecDeque<Envelope>,
 
     /// Used for synchronizing writes to the external part.
     write_lock: Mutex<()>,
@@ -73,18 +176,24 @@ impl SignalQueue {
         }
     }
 
-    pub fn send_external(&mut self, message: Signal) {
+    /// Queues up a signal, returning the amount of bytes that had to be copied.
+    pub fn send_external(&mut self, message: Signal) -> usize {
+        // copy outside of the lock
+        let envelope = message.into_envelope();
+        let size = envelope.1.as_ref().map_or(0, Heap::used);
+
         let _lock = self.write_lock.lock();
 
-        self.external.push_back(message);
+        self.external.push_back(envelope);
+        size
     }
 
     // TODO: I'm not sure if skipping external is allowed since it'll break ordering
     pub fn send_internal(&mut self, message: Signal) {
-        self.internal.push_back(message);
+        self.internal.push_back((message, None));
     }
 
-    pub fn receive(&mut self) -> Option<Signal> {
+    pub fn receive(&mut self) -> Option<Envelope> {
         if self.internal.is_empty() {
             let _lock = self.write_lock.lock();
 
@@ -98,6 +207,14 @@ impl SignalQueue {
     //     self.internal.remove(self.save);
     // }
 
+    /// Returns true if any of the signals other processes queued up matches `pred`. The internal
+    /// part is left alone, so it's safe to call while the process is running elsewhere.
+    pub fn any_external<F: Fn(&Signal) -> bool>(&self, pred: F) -> bool {
+        let _lock = self.write_lock.lock();
+
+        self.external.iter().any(|(signal, _)| pred(signal))
+    }
+
     pub fn has_messages(&self) -> bool {
         if !self.internal.is_empty() {
             return true;
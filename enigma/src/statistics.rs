@@ -0,0 +1,286 @@
+//! Counters backing `erlang:statistics/1`. Most of them get updated by the scheduler loop in
+//! `vm::run_with_error_handling`, once per time slice.
+use crate::scheduler;
+use crate::vm::Machine;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+pub struct Statistics {
+    /// Reductions executed by all processes so far.
+    reductions: AtomicUsize,
+    /// Number of times a process got scheduled in.
+    context_switches: AtomicUsize,
+    /// Number of garbage collections performed so far.
+    gcs: AtomicUsize,
+    /// Words reclaimed by garbage collections so far.
+    reclaimed: AtomicUsize,
+    /// Bytes received from ports.
+    input: AtomicUsize,
+    /// Bytes sent to ports.
+    output: AtomicUsize,
+
+    // Values as of the last call, for the items that report the difference since then.
+    last_reductions: AtomicUsize,
+    last_exact_reductions: AtomicUsize,
+    last_runtime: AtomicU64,
+    last_wall_clock: AtomicU64,
+
+    /// Whether scheduler wall time is being measured.
+    wall_time: AtomicBool,
+    /// When scheduler wall time measurement got turned on.
+    wall_time_since: Mutex<Instant>,
+    /// Time spent executing per scheduler (in nanoseconds), in `scheduler::id` order.
+    active: Vec<AtomicU64>,
+    /// Number of normal schedulers.
+    schedulers: usize,
+    /// Number of dirty CPU schedulers.
+    dirty_cpu_schedulers: usize,
+}
+
+/// Per scheduler `(id, active time, total time)`, as reported by
+/// `statistics(scheduler_wall_time)`. Times are in nanoseconds.
+pub type WallTime = Vec<(usize, u64, u64)>;
+
+impl Statistics {
+    pub fn new(
+        schedulers: usize,
+        dirty_cpu_schedulers: usize,
+        dirty_io_schedulers: usize,
+    ) -> Self {
+        let total = schedulers + dirty_cpu_schedulers + dirty_io_schedulers;
+        Statistics {
+            reductions: AtomicUsize::new(0),
+            context_switches: AtomicUsize::new(0),
+            gcs: AtomicUsize::new(0),
+            reclaimed: AtomicUsize::new(0),
+            input: AtomicUsize::new(0),
+            output: AtomicUsize::new(0),
+            last_reductions: AtomicUsize::new(0),
+            last_exact_reductions: AtomicUsize::new(0),
+            last_runtime: AtomicU64::new(0),
+            last_wall_clock: AtomicU64::new(0),
+            wall_time: AtomicBool::new(false),
+            wall_time_since: Mutex::new(Instant::now()),
+            active: (0..total).map(|_| AtomicU64::new(0)).collect(),
+            schedulers,
+            dirty_cpu_schedulers,
+        }
+    }
+
+    /// Scheduler id for the `n`th thread started by the pool of the given scheduler type.
+    pub fn scheduler_id(&self, dirty: Option<scheduler::Dirty>, n: usize) -> usize {
+        let dirty_io_schedulers = self.active.len() - self.schedulers - self.dirty_cpu_schedulers;
+        match dirty {
+            None => n % self.schedulers,
+            Some(scheduler::Dirty::Cpu) => self.schedulers + n % self.dirty_cpu_schedulers,
+            Some(scheduler::Dirty::Io) => {
+                self.schedulers + self.dirty_cpu_schedulers + n % dirty_io_schedulers
+            }
+        }
+    }
+
+    /// Accounts for a time slice a process just finished executing.
+    pub fn slice(&self, reductions: usize, gcs: usize, reclaimed: usize) {
+        self.context_switches.fetch_add(1, Ordering::Relaxed);
+        self.reductions.fetch_add(reductions, Ordering::Relaxed);
+        if gcs > 0 {
+            self.gcs.fetch_add(gcs, Ordering::Relaxed);
+            self.reclaimed.fetch_add(reclaimed, Ordering::Relaxed);
+        }
+    }
+
+    pub fn io_input(&self, bytes: usize) {
+        self.input.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn io_output(&self, bytes: usize) {
+        self.output.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Total reductions, and reductions since the last call.
+    pub fn reductions(&self) -> (usize, usize) {
+        since_last(&self.reductions, &self.last_reductions)
+    }
+
+    /// Same as `reductions`, but with its own last call.
+    pub fn exact_reductions(&self) -> (usize, usize) {
+        since_last(&self.reductions, &self.last_exact_reductions)
+    }
+
+    /// CPU time used by the VM, and CPU time since the last call, in milliseconds.
+    pub fn runtime(&self, elapsed: Duration) -> (u64, u64) {
+        let total = cpu_time(elapsed).as_millis() as u64;
+        let last = self.last_runtime.swap(total, Ordering::Relaxed);
+        (total, total.saturating_sub(last))
+    }
+
+    /// Wall clock time since the VM started, and since the last call, in milliseconds.
+    pub fn wall_clock(&self, elapsed: Duration) -> (u64, u64) {
+        let total = elapsed.as_millis() as u64;
+        let last = self.last_wall_clock.swap(total, Ordering::Relaxed);
+        (total, total.saturating_sub(last))
+    }
+
+    pub fn context_switches(&self) -> usize {
+        self.context_switches.load(Ordering::Relaxed)
+    }
+
+    /// Number of garbage collections, and words reclaimed by them.
+    pub fn garbage_collection(&self) -> (usize, usize) {
+        (
+            self.gcs.load(Ordering::Relaxed),
+            self.reclaimed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Bytes received from and sent to ports.
+    pub fn io(&self) -> (usize, usize) {
+        (
+            self.input.load(Ordering::Relaxed),
+            self.output.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Turns scheduler wall time measurement on or off, returning whether it was on.
+    pub fn set_scheduler_wall_time(&self, enabled: bool) -> bool {
+        let mut since = self.wall_time_since.lock();
+        let old = self.wall_time.swap(enabled, Ordering::Relaxed);
+        if enabled && !old {
+            for active in &self.active {
+                active.store(0, Ordering::Relaxed);
+            }
+            *since = Instant::now();
+        }
+        old
+    }
+
+    /// Charges time spent executing to the scheduler the current thread belongs to.
+    pub fn charge(&self, elapsed: Duration) {
+        if !self.wall_time.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(id) = scheduler::id() {
+            self.active[id].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Active and total time per scheduler since measurement got turned on, `None` if it's off.
+    /// Only includes dirty schedulers if `all` is set.
+    pub fn scheduler_wall_time(&self, all: bool) -> Option<WallTime> {
+        let since = self.wall_time_since.lock();
+        if !self.wall_time.load(Ordering::Relaxed) {
+            return None;
+        }
+        let total = since.elapsed().as_nanos() as u64;
+        let count = if all {
+            self.active.len()
+        } else {
+            self.schedulers
+        };
+        let res = self.active[..count]
+            .iter()
+            .enumerate()
+            .map(|(i, active)| (i + 1, active.load(Ordering::Relaxed), total))
+            .collect();
+        Some(res)
+    }
+}
+
+/// Returns the counter's value, and the difference to its value as of the last call.
+fn since_last(counter: &AtomicUsize, last: &AtomicUsize) -> (usize, usize) {
+    let total = counter.load(Ordering::Relaxed);
+    let last = last.swap(total, Ordering::Relaxed);
+    (total, total.saturating_sub(last))
+}
+
+/// CPU time used by all threads of the VM.
+#[cfg(unix)]
+fn cpu_time(_elapsed: Duration) -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+    let duration =
+        |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+    duration(usage.ru_utime) + duration(usage.ru_stime)
+}
+
+/// Not available, fall back to wall clock time.
+#[cfg(not(unix))]
+fn cpu_time(elapsed: Duration) -> Duration {
+    elapsed
+}
+
+/// Charges the time spent polling a future to the scheduler thread polling it, so that the time
+/// a process spends waiting doesn't count as active.
+pub struct Timed<'a, F> {
+    vm: &'a Machine,
+    future: F,
+}
+
+impl<'a, F> Timed<'a, F> {
+    pub fn new(vm: &'a Machine, future: F) -> Self {
+        Timed { vm, future }
+    }
+}
+
+impl<'a, F: Future> Future for Timed<'a, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let vm = self.vm;
+        // the future is never moved out of the pin
+        let future = unsafe { self.map_unchecked_mut(|timed| &mut timed.future) };
+
+        if !vm.stats.wall_time.load(Ordering::Relaxed) {
+            return future.poll(cx);
+        }
+        let started = Instant::now();
+        let res = future.poll(cx);
+        vm.stats.charge(started.elapsed());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since_last_call() {
+        let stats = Statistics::new(2, 2, 1);
+        stats.slice(100, 0, 0);
+        assert_eq!(stats.reductions(), (100, 100));
+        stats.slice(50, 1, 10);
+        assert_eq!(stats.reductions(), (150, 50));
+        // exact_reductions keeps its own last call
+        assert_eq!(stats.exact_reductions(), (150, 150));
+        assert_eq!(stats.context_switches(), 2);
+        assert_eq!(stats.garbage_collection(), (1, 10));
+    }
+
+    #[test]
+    fn test_scheduler_ids() {
+        let stats = Statistics::new(2, 2, 1);
+        assert_eq!(stats.scheduler_id(None, 3), 1);
+        assert_eq!(stats.scheduler_id(Some(scheduler::Dirty::Cpu), 0), 2);
+        assert_eq!(stats.scheduler_id(Some(scheduler::Dirty::Io), 4), 4);
+    }
+
+    #[test]
+    fn test_scheduler_wall_time() {
+        let stats = Statistics::new(2, 2, 1);
+        assert_eq!(stats.scheduler_wall_time(false), None);
+
+        assert!(!stats.set_scheduler_wall_time(true));
+        let wall_time = stats.scheduler_wall_time(false).unwrap();
+        assert_eq!(wall_time.len(), 2);
+        assert_eq!(wall_time[0].0, 1);
+        assert_eq!(stats.scheduler_wall_time(true).unwrap().len(), 5);
+
+        assert!(stats.set_scheduler_wall_time(false));
+        assert_eq!(stats.scheduler_wall_time(true), None);
+    }
+}
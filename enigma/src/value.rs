@@ -505,24 +505,36 @@ impl Term {
     }
 
     pub fn binary(heap: &Heap, value: bitstring::Binary) -> Self {
-        Term::from(heap.alloc(Boxed {
+        Term::rc_binary(heap, crate::servo_arc::Arc::new(value))
+    }
+
+    /// Boxes a reference to an off-heap binary, which counts towards the binary virtual heap.
+    pub fn rc_binary(heap: &Heap, value: bitstring::RcBinary) -> Self {
+        let boxed = heap.alloc(Boxed {
             header: BOXED_BINARY,
-            value: crate::servo_arc::Arc::new(value),
-        }))
+            value,
+        });
+        heap.grow_bin_vheap(boxed.value.data.len());
+        heap.track_binary(boxed as *const _ as usize, &mut boxed.value);
+        Term::from(boxed)
     }
 
     pub fn subbinary(heap: &Heap, value: bitstring::SubBinary) -> Self {
-        Term::from(heap.alloc(Boxed {
+        let boxed = heap.alloc(Boxed {
             header: BOXED_SUBBINARY,
             value,
-        }))
+        });
+        heap.track_binary(boxed as *const _ as usize, &mut boxed.value.original);
+        Term::from(boxed)
     }
 
     pub fn matchbuffer(heap: &Heap, value: bitstring::MatchBuffer) -> Self {
-        Term::from(heap.alloc(Boxed {
+        let boxed = heap.alloc(Boxed {
             header: BOXED_MATCHBUFFER,
             value,
-        }))
+        });
+        heap.track_binary(boxed as *const _ as usize, &mut boxed.value.original);
+        Term::from(boxed)
     }
 
     pub fn catch(heap: &Heap, value: instruction::Ptr) -> Self {
@@ -623,7 +635,7 @@ impl Term {
     #[inline(always)]
     pub fn is_immed(self) -> bool {
         let tag = self.value.tag() as u8;
-        tag != TERM_CONS || tag != TERM_POINTER
+        tag != TERM_CONS && tag != TERM_POINTER
     }
 
     #[inline]
@@ -909,6 +921,19 @@ impl Term {
                         let export = &(*(ptr as *const Boxed<module::MFA>)).value;
                         Term::export(heap, *export)
                     }
+                    BOXED_CLOSURE => {
+                        let closure = &(*(ptr as *const Boxed<Closure>)).value;
+                        Term::closure(
+                            heap,
+                            Closure {
+                                ptr: closure.ptr,
+                                mfa: closure.mfa,
+                                binding: closure.binding.as_ref().map(|binding| {
+                                    binding.iter().map(|val| val.deep_clone(heap)).collect()
+                                }),
+                            },
+                        )
+                    }
                     BOXED_REF => {
                         let reference = &(*(ptr as *const Boxed<process::Ref>)).value;
                         Term::reference(heap, *reference)
@@ -923,10 +948,7 @@ impl Term {
                     }
                     BOXED_BINARY => {
                         let bin = &(*(ptr as *const Boxed<bitstring::RcBinary>)).value;
-                        Term::from(heap.alloc(Boxed {
-                            header: BOXED_BINARY,
-                            value: bin.clone(),
-                        }))
+                        Term::rc_binary(heap, bin.clone())
                     }
                     _ => unimplemented!("deep_clone for {}", self), // TODO: deep clone for Ref<>
                 }
@@ -1221,8 +1243,13 @@ impl std::fmt::Display for Variant {
             Variant::Integer(i) => write!(f, "{}", i),
             Variant::Float(self::Float(i)) => write!(f, "{}", i),
             Variant::Atom(i) => write!(f, ":{}", i.to_str().unwrap()),
-            Variant::Port(i) => write!(f, "#Port<{}>", i),
-            Variant::Pid(i) => write!(f, "#Pid<{}>", i),
+            Variant::Port(i) => write!(f, "#Port<0.{}>", i),
+            Variant::Pid(i) => write!(
+                f,
+                "<0.{}.{}>",
+                process::table::number(*i),
+                process::table::serial(*i)
+            ),
             Variant::Cons(c) => unsafe {
                 let cons = &**c;
                 let is_printable = cons.iter().all(|v| match v.into_variant() {
@@ -1237,7 +1264,7 @@ impl std::fmt::Display for Variant {
 
                 if is_printable {
                     write!(f, "\"")?;
-                    let string = cons::unicode_list_to_buf(cons, 8096).unwrap();
+                    let string = cons::unicode_list_to_buf(cons, std::usize::MAX).unwrap();
                     write!(f, "{}", string)?;
                     write!(f, "\"")
                 } else {
@@ -1280,7 +1307,8 @@ impl std::fmt::Display for Variant {
                     }
                     BOXED_REF => {
                         let reference = &(*(*ptr as *const Boxed<process::Ref>)).value;
-                        write!(f, "#Ref<0.0.0.{}>", reference)
+                        let reference = *reference as u64;
+                        write!(f, "#Ref<0.0.{}.{}>", reference >> 32, reference & 0xFFFF_FFFF)
                     }
                     BOXED_BINARY => {
                         let binary = &(*(*ptr as *const Boxed<bitstring::RcBinary>)).value;
@@ -1335,22 +1363,29 @@ impl std::fmt::Display for Variant {
 
 #[allow(clippy::mut_from_ref)]
 pub fn tuple(heap: &Heap, len: u32) -> &mut Tuple {
-    let tuple = heap.alloc(self::Tuple {
-        header: BOXED_TUPLE,
-        len,
-    });
+    // The elements follow the header, so they have to be a part of the same allocation.
     //
     // TODO: Layout::array::<Term>(len as usize).unwrap() once it's stable
     // https://github.com/rust-lang/rust/issues/55724#issuecomment-515489742
     let layout = Layout::from_size_align(
         std::mem::size_of::<Term>()
             .checked_mul(len as usize)
+            .and_then(|size| size.checked_add(std::mem::size_of::<Tuple>()))
             .unwrap(),
-        std::mem::align_of::<Term>(),
+        std::cmp::max(std::mem::align_of::<Tuple>(), std::mem::align_of::<Term>()),
     )
     .unwrap();
-    heap.alloc_layout(layout);
-    tuple
+    unsafe {
+        let tuple = heap.alloc_layout(layout).as_ptr() as *mut Tuple;
+        std::ptr::write(
+            tuple,
+            self::Tuple {
+                header: BOXED_TUPLE,
+                len,
+            },
+        );
+        &mut *tuple
+    }
 }
 
 #[inline]
@@ -128,24 +128,26 @@ impl Cons {
     }
 }
 
-/// @brief Fill buf with the UTF8 contents of the unicode list
-/// @param len Max number of characters to write.
-/// @param written NULL or bytes written.
-/// @return 0 ok,
-///        -1 type error,
-///        -2 list too long, only \c len characters written
+/// Decodes a list of code points. Lists longer than `max_len` characters are a `system_limit`
+/// error.
 pub fn unicode_list_to_buf(
     list: &Cons,
-    _max_len: usize,
+    max_len: usize,
 ) -> Result<String, crate::exception::Exception> {
-    // TODO: handle max_len
-    list.iter()
-        .map(|v| {
-            v.to_uint()
-                .and_then(std::char::from_u32)
-                .ok_or_else(|| badarg!())
-        })
-        .collect()
+    use crate::exception::{Exception, Reason};
+
+    let mut string = String::new();
+    for (i, v) in list.iter().enumerate() {
+        if i >= max_len {
+            return Err(Exception::new(Reason::EXC_SYSTEM_LIMIT));
+        }
+        let c = v
+            .to_uint()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| badarg!())?;
+        string.push(c);
+    }
+    Ok(string)
 }
 
 #[cfg(test)]
@@ -182,9 +184,22 @@ mod tests {
 
     #[test]
     fn test_unicode_list_to_buf() {
-        let _heap = Heap::new();
+        use crate::exception::{Exception, Reason};
+
+        let heap = Heap::new();
 
         // '関数に渡すことで'
-        // [38306, 25968, 12395, 28193, 12377, 12371, 12392, 12391]
+        let chars = [38306, 25968, 12395, 28193, 12377, 12371, 12392, 12391];
+        let list = Cons::from_iter(chars.iter().map(|c| Term::int(*c)), &heap);
+        let cons: &Cons = list.cast_into().unwrap();
+        assert_eq!(unicode_list_to_buf(cons, 8).unwrap(), "関数に渡すことで");
+
+        // the limit counts characters, not bytes
+        let res = unicode_list_to_buf(cons, 7);
+        assert_eq!(res, Err(Exception::new(Reason::EXC_SYSTEM_LIMIT)));
+
+        let list = cons!(&heap, Term::int(-1), Term::nil());
+        let cons: &Cons = list.cast_into().unwrap();
+        assert_eq!(unicode_list_to_buf(cons, 8), Err(badarg!()));
     }
 }
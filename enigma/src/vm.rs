@@ -1,10 +1,10 @@
 use crate::atom::Atom;
-use crate::{bitstring, module, instruction};
+use crate::{bitstring, module, instruction, scheduler, statistics};
 use crate::exception::{self, Exception, Reason};
 use crate::process::{self, RcProcess};
 // needs arbitrary_self_types
 // use crate::servo_arc::Arc;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use crate::value::{Cons, Term};
 
 use crate::ets::{RcTableRegistry, TableRegistry};
@@ -17,19 +17,34 @@ use crate::process::{
     table::Table as ProcessTable,
 };
 
+use once_cell::sync::OnceCell;
 use std::cell::RefCell;
 // use log::debug;
 use parking_lot::{Mutex, RwLock};
 use std::panic;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time;
 
 // use tokio::prelude::*;
+use futures::channel::oneshot;
+use tokio::runtime::{Runtime, TaskExecutor};
+use futures::future::{self, Either};
 use futures::prelude::*;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
 
 /// A reference counted State.
 pub type RcMachine = Arc<Machine>;
 
+pub const SYSTEM_VERSION: &str = "Erlang/OTP 22 [erts-10.3.4] [source] [64-bit] [smp:8:8] [ds:8:8:10] [async-threads:1] [enigma]\n";
+
+/// Exit status when the VM gets interrupted by Ctrl-C (128 + SIGINT).
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Maximum length of a crash dump slogan given to `erlang:halt/1,2`.
+pub const HALT_SLOGAN_SIZE: usize = 200;
+
 pub struct Machine {
     /// Table containing all processes.
     pub process_table: Mutex<ProcessTable<RcProcess>>,
@@ -38,23 +53,45 @@ pub struct Machine {
     pub process_registry: Mutex<ProcessRegistry<RcProcess>>,
 
     pub port_table: RcPortTable,
-    /// TODO: Use priorities later on
+
+    /// Run queues handing out scheduler slots to processes, by priority.
+    pub run_queues: scheduler::RunQueues,
 
     /// Start time of the VM boot-up
     pub start_time: time::Instant,
 
+    /// Counters reported by `erlang:statistics/1`.
+    pub stats: statistics::Statistics,
+
+    /// Configuration the machine was built with.
+    pub config: Config,
+
     pub next_ref: AtomicUsize,
 
     /// PID pointing to the process handling system-wide logging.
     pub system_logger: AtomicUsize,
 
+    /// PID of the erts_literal_area_collector process.
+    pub literal_collector: AtomicUsize,
+
+    /// PID of the erts_dirty_process_signal_handler process.
+    pub dirty_signal_handler: AtomicUsize,
+
+    /// Literal areas of purged modules, waiting to be released.
+    pub literal_areas: Mutex<module::LiteralAreas>,
+
     /// Futures pool for running processes
-    pub process_pool: tokio::runtime::Runtime,
+    pub process_pool: TaskExecutor,
 
-    /// Futures pool for running I/O and other utility tasks ("dirty scheduler")
-    pub runtime: tokio::runtime::Runtime,
+    /// Futures pool for running I/O and other utility tasks, doubles as the dirty I/O scheduler
+    pub runtime: TaskExecutor,
 
-    pub exit: Option<futures::channel::oneshot::Sender<()>>,
+    /// Futures pool for running CPU-bound dirty BIFs ("dirty CPU scheduler")
+    pub dirty_cpu: TaskExecutor,
+
+    /// Tells the owning `Instance` to shut the VM down, with the exit status and whether to flush
+    /// pending output first. Taken by the first halt.
+    pub exit: Mutex<Option<oneshot::Sender<(i32, bool)>>>,
 
     /// Exports table
     pub exports: RwLock<ExportsTable>,
@@ -67,6 +104,255 @@ pub struct Machine {
     pub persistent_terms: PersistentTermTable,
 }
 
+/// Configuration of a machine, see `MachineBuilder`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Number of normal scheduler threads.
+    pub schedulers: usize,
+
+    /// Number of dirty CPU scheduler threads.
+    pub dirty_cpu_schedulers: usize,
+
+    /// Number of dirty I/O scheduler threads.
+    pub dirty_io_schedulers: usize,
+
+    /// Root directory of the OTP installation to boot.
+    pub root: PathBuf,
+
+    /// Extra code paths, added in front of the code path with `-pa`.
+    pub code_paths: Vec<PathBuf>,
+
+    /// Default minimum heap size of processes, in words.
+    pub min_heap_size: usize,
+
+    /// Default minimum binary virtual heap size of processes, in words.
+    pub min_bin_vheap_size: usize,
+
+    /// Maximum number of processes alive at the same time.
+    pub process_limit: usize,
+
+    /// Maximum number of atoms.
+    pub atom_limit: usize,
+
+    /// Arguments passed on to `init`, after the ones derived from the configuration.
+    pub args: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            schedulers: num_cpus::get(),
+            dirty_cpu_schedulers: num_cpus::get(),
+            dirty_io_schedulers: scheduler::DIRTY_IO_SCHEDULERS,
+            root: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../otp")),
+            code_paths: Vec::new(),
+            min_heap_size: process::gc::DEFAULT_MIN_HEAP_SIZE / process::gc::WORD_SIZE,
+            min_bin_vheap_size: process::gc::DEFAULT_MIN_BIN_VHEAP_SIZE / process::gc::WORD_SIZE,
+            process_limit: process::table::MAX_PID as usize,
+            atom_limit: crate::atom::DEFAULT_ATOM_LIMIT,
+            args: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The argument list handed to `init:boot/1`, laid out the way erlexec does it.
+    pub fn init_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "--".to_string(),
+            "-root".to_string(),
+            self.root.to_string_lossy().into_owned(),
+            "-progname".to_string(),
+            "enigma".to_string(),
+            "--".to_string(),
+        ];
+        if let Some(home) = dirs::home_dir() {
+            args.push("-home".to_string());
+            args.push(home.to_string_lossy().into_owned());
+        }
+        args.push("--".to_string());
+        if !self.code_paths.is_empty() {
+            args.push("-pa".to_string());
+            args.extend(
+                self.code_paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned()),
+            );
+        }
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Builds a machine, shared by the command line and programs embedding the VM.
+///
+/// ```ignore
+/// let vm = MachineBuilder::new()
+///     .schedulers(2)
+///     .code_path("ebin")
+///     .args(vec!["-noshell".to_string()])
+///     .build();
+/// vm.preload_modules();
+/// std::process::exit(vm.start());
+/// ```
+#[derive(Debug, Default)]
+pub struct MachineBuilder {
+    config: Config,
+}
+
+impl MachineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of normal scheduler threads.
+    pub fn schedulers(mut self, n: usize) -> Self {
+        self.config.schedulers = std::cmp::max(n, 1);
+        self
+    }
+
+    /// Sets the number of dirty CPU scheduler threads.
+    pub fn dirty_cpu_schedulers(mut self, n: usize) -> Self {
+        self.config.dirty_cpu_schedulers = std::cmp::max(n, 1);
+        self
+    }
+
+    /// Sets the number of dirty I/O scheduler threads.
+    pub fn dirty_io_schedulers(mut self, n: usize) -> Self {
+        self.config.dirty_io_schedulers = std::cmp::max(n, 1);
+        self
+    }
+
+    /// Sets the OTP root directory.
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.config.root = root.into();
+        self
+    }
+
+    /// Adds a directory to the front of the code path.
+    pub fn code_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.code_paths.push(path.into());
+        self
+    }
+
+    /// Sets the default minimum heap size of processes, in words.
+    pub fn min_heap_size(mut self, words: usize) -> Self {
+        self.config.min_heap_size = words;
+        self
+    }
+
+    /// Sets the default minimum binary virtual heap size of processes, in words.
+    pub fn min_bin_vheap_size(mut self, words: usize) -> Self {
+        self.config.min_bin_vheap_size = words;
+        self
+    }
+
+    /// Sets the maximum number of processes alive at the same time.
+    pub fn process_limit(mut self, limit: usize) -> Self {
+        self.config.process_limit = limit;
+        self
+    }
+
+    /// Sets the maximum number of atoms. The atom table is shared by every machine in the OS
+    /// process, and so is this limit: the first machine built sets it, later ones can only raise
+    /// it.
+    pub fn atom_limit(mut self, limit: usize) -> Self {
+        self.config.atom_limit = limit;
+        self
+    }
+
+    /// Appends arguments for `init`, such as `-noshell` or `-s mod fun`.
+    pub fn args<I: IntoIterator<Item = String>>(mut self, args: I) -> Self {
+        self.config.args.extend(args);
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Builds the machine, along with the thread pools it runs on.
+    pub fn build(self) -> Instance {
+        let config = self.config;
+        crate::atom::raise_limit(config.atom_limit);
+        let (exit, halted) = oneshot::channel();
+
+        // pool threads need a reference to the machine, which needs the pools to spawn tasks on.
+        // Threads only start once there's a task for them, by then the machine is set.
+        let current = Arc::new(OnceCell::new());
+        let runtimes = Runtimes {
+            process_pool: pool(&current, config.schedulers, "scheduler-", None),
+            runtime: pool(
+                &current,
+                config.dirty_io_schedulers,
+                "dirty-io-",
+                Some(scheduler::Dirty::Io),
+            ),
+            dirty_cpu: pool(
+                &current,
+                config.dirty_cpu_schedulers,
+                "dirty-cpu-",
+                Some(scheduler::Dirty::Cpu),
+            ),
+        };
+
+        let vm = Arc::new(Machine {
+            process_table: Mutex::new(ProcessTable::with_limit(config.process_limit)),
+            process_registry: Mutex::new(ProcessRegistry::new()),
+            port_table: PortTable::new(),
+            run_queues: scheduler::RunQueues::new(config.schedulers),
+            start_time: time::Instant::now(),
+            stats: statistics::Statistics::new(
+                config.schedulers,
+                config.dirty_cpu_schedulers,
+                config.dirty_io_schedulers,
+            ),
+            config,
+            process_pool: runtimes.process_pool.executor(),
+            runtime: runtimes.runtime.executor(),
+            dirty_cpu: runtimes.dirty_cpu.executor(),
+            exit: Mutex::new(Some(exit)),
+            next_ref: AtomicUsize::new(1),
+            system_logger: AtomicUsize::new(0),
+            literal_collector: AtomicUsize::new(0),
+            dirty_signal_handler: AtomicUsize::new(0),
+            literal_areas: Mutex::new(module::LiteralAreas::new()),
+            exports: ExportsTable::with_rc(),
+            modules: ModuleRegistry::with_rc(),
+            ets_tables: TableRegistry::with_rc(),
+            persistent_terms: PersistentTermTable::new(),
+        });
+        let _ = current.set(Arc::downgrade(&vm));
+
+        Instance {
+            machine: vm,
+            runtimes: Some(runtimes),
+            halted: Some(halted),
+        }
+    }
+}
+
+/// The thread pools a machine runs on. They're owned by the `Instance`, the machine only gets to
+/// spawn tasks on them.
+struct Runtimes {
+    process_pool: Runtime,
+    runtime: Runtime,
+    dirty_cpu: Runtime,
+}
+
+/// Owns a machine along with the thread pools it runs on.
+///
+/// Any number of instances can exist side by side in one OS process, each with its own processes,
+/// ports, tables and registries. Only the atom table is shared. Dropping the instance shuts its
+/// thread pools down, dropping any processes still running, so everything they held on to gets
+/// released. This blocks until the pools stopped, so it can't happen on one of the VM's threads.
+pub struct Instance {
+    machine: RcMachine,
+    runtimes: Option<Runtimes>,
+    /// Receives the exit status once the VM halts, until `start` waits for it.
+    halted: Option<oneshot::Receiver<(i32, bool)>>,
+}
+
 thread_local!(
     static CURRENT: RefCell<Option<Arc<Machine>>> = RefCell::new(None);
 );
@@ -93,6 +379,13 @@ impl Machine {
         })
     }
 
+    /// Unset the current machine, so that a stopping pool thread doesn't keep it alive.
+    fn clear_current() {
+        CURRENT.with(|s| {
+            s.borrow_mut().take();
+        })
+    }
+
     /// Execute function with machine reference.
     pub fn with_current<F, R>(f: F) -> R
     where
@@ -149,52 +442,9 @@ pub const PRE_LOADED: &[&[u8]] = &[
 ];
 
 impl Machine {
-    pub fn new() -> Arc<Machine> {
-        let vm = Arc::new(Machine {
-            process_table: Mutex::new(ProcessTable::new()),
-            process_registry: Mutex::new(ProcessRegistry::new()),
-            port_table: PortTable::new(),
-            start_time: time::Instant::now(),
-            process_pool: unsafe { std::mem::uninitialized() }, // I'm sorry, but we need a ref to vm in threadpool
-            runtime: unsafe { std::mem::uninitialized() }, // I'm sorry, but we need a ref to vm in threadpool
-            exit: None,
-            next_ref: AtomicUsize::new(1),
-            system_logger: AtomicUsize::new(0),
-            exports: ExportsTable::with_rc(),
-            modules: ModuleRegistry::with_rc(),
-            ets_tables: TableRegistry::with_rc(),
-            persistent_terms: PersistentTermTable::new(),
-        });
-
-        // initialize tokio here
-
-        // let (trigger, exit) = futures::sync::oneshot::channel();
-
-        // Create the runtime
-        let machine = vm.clone();
-        let runtime = tokio::runtime::Builder::new()
-            .panic_handler(|err| std::panic::resume_unwind(err))
-            .after_start(move || {
-                Machine::set_current(machine.clone()); // ughh double clone
-            })
-            .build()
-            .expect("failed to start new Runtime");
-
-        let machine = vm.clone();
-        let process_pool = tokio::runtime::Builder::new()
-            .panic_handler(|err| std::panic::resume_unwind(err))
-            .after_start(move || {
-                Machine::set_current(machine.clone());
-            })
-            .build()
-            .expect("failed to start new Runtime");
-
-        unsafe {
-            std::ptr::write(&vm.runtime as *const tokio::runtime::Runtime as *mut tokio::runtime::Runtime, runtime);
-            std::ptr::write(&vm.process_pool as *const tokio::runtime::Runtime as *mut tokio::runtime::Runtime, process_pool);
-        }
-
-        vm
+    /// Builds a machine with the default configuration.
+    pub fn new() -> Instance {
+        MachineBuilder::new().build()
     }
 
     pub fn next_ref(&self) -> process::Ref {
@@ -214,32 +464,35 @@ impl Machine {
         })
     }
 
-    /// Starts the VM
-    ///
-    /// This method will block the calling thread until it returns.
-    ///
-    /// This method returns true if the VM terminated successfully, false
-    /// otherwise.
-    pub fn start(self: Arc<Self>, args: Vec<String>) {
-        let (tx, rx) = futures::channel::oneshot::channel::<()>();
-
-        let vm = unsafe { &mut *(&*self as *const Machine as *mut Machine) };
-
-        vm.exit = Some(tx);
-
-        self.start_main_process(args);
-
-        // Wait until the runtime becomes idle and shut it down.
-        vm.runtime.block_on(async {
-            let mut ctrl_c = tokio_net::signal::ctrl_c().unwrap();
-            ctrl_c.next().await;
-        })
-        // TODO: proper shutdown
-        // self.process_pool.shutdown_on_idle().wait().unwrap();
-        // runtime.shutdown_now().wait(); // TODO: block_on
+    /// Halts the VM with the given exit status, `flush` tells whether pending output gets written
+    /// out first. The owning `Instance` shuts the VM down, the OS process keeps running. Only the
+    /// first halt counts, the VM is already going down after that.
+    pub fn halt(&self, status: i32, flush: bool) {
+        if let Some(exit) = self.exit.lock().take() {
+            let _ = exit.send((status, flush));
+        }
     }
 
-    fn terminate(&self) {}
+    /// Writes a crash dump to `$ERL_CRASH_DUMP`, or erl_crash.dump in the current directory.
+    pub fn crash_dump(&self, slogan: &str) {
+        let path =
+            std::env::var("ERL_CRASH_DUMP").unwrap_or_else(|_| "erl_crash.dump".to_string());
+        eprint!("\r\nCrash dump is being written to: {}...", path);
+
+        let processes = self.process_table.lock().all().len();
+        let res = File::create(&path).and_then(|mut file| {
+            writeln!(file, "=erl_crash_dump:0.5")?;
+            writeln!(file, "{}", chrono::Local::now().format("%a %b %e %T %Y"))?;
+            writeln!(file, "Slogan: {}", slogan)?;
+            write!(file, "System version: {}", SYSTEM_VERSION)?;
+            writeln!(file, "Processes: {}", processes)?;
+            file.flush()
+        });
+        match res {
+            Ok(()) => eprintln!("done\r"),
+            Err(err) => eprintln!("failed: {}\r", err),
+        }
+    }
 
     /// Starts the main process
     pub fn start_main_process(&self, args: Vec<String>) {
@@ -263,35 +516,175 @@ impl Machine {
         context.ip.ptr = module.funs[&(fun, arity)];
 
         let future = run_with_error_handling(process);
-        self.process_pool.executor().spawn(future);
+        self.process_pool.spawn(future);
 
         // ------
 
         // start system processes
-        // TODO: start as a special process and panic if it halts
         let module = registry.lookup(Atom::from("erts_code_purger")).unwrap();
         let process = process::allocate(&self, 0 /* itself */, 0, module).unwrap();
+        process.local_data_mut().flags |= process::Flag::SYSTEM_PROC;
+        let context = process.context_mut();
+        let fun = Atom::from("start");
+        let arity = 0;
+        context.ip.ptr = module.funs[&(fun, arity)];
+        let future = run_with_error_handling(process);
+        self.process_pool.spawn(future);
+
+        let module = registry.lookup(Atom::from("erts_literal_area_collector")).unwrap();
+        let process = process::allocate(&self, 0 /* itself */, 0, module).unwrap();
+        process.local_data_mut().flags |= process::Flag::SYSTEM_PROC;
+        self.literal_collector
+            .store(process.pid as usize, std::sync::atomic::Ordering::Relaxed);
         let context = process.context_mut();
         let fun = Atom::from("start");
         let arity = 0;
         context.ip.ptr = module.funs[&(fun, arity)];
         let future = run_with_error_handling(process);
-        self.process_pool.executor().spawn(future);
+        self.process_pool.spawn(future);
 
+        let module = registry.lookup(Atom::from("erts_dirty_process_signal_handler")).unwrap();
+        let process = process::allocate(&self, 0 /* itself */, 0, module).unwrap();
+        process.local_data_mut().flags |= process::Flag::SYSTEM_PROC;
+        self.dirty_signal_handler
+            .store(process.pid as usize, std::sync::atomic::Ordering::Relaxed);
+        process.local_data_mut().priority = scheduler::Priority::Max;
+        let context = process.context_mut();
+        let fun = Atom::from("start");
+        let arity = 0;
+        context.ip.ptr = module.funs[&(fun, arity)];
+        let future = run_with_error_handling(process);
+        self.process_pool.spawn(future);
 
         // self.process_pool.schedule(process);
     }
 }
 
+/// Builds one of the thread pools a machine runs on. `current` is the machine the pool's threads
+/// run processes and tasks for.
+fn pool(
+    current: &Arc<OnceCell<Weak<Machine>>>,
+    threads: usize,
+    name: &str,
+    dirty: Option<scheduler::Dirty>,
+) -> Runtime {
+    let current = current.clone();
+    let started = AtomicUsize::new(0);
+    tokio::runtime::Builder::new()
+        .panic_handler(|err| std::panic::resume_unwind(err))
+        .core_threads(threads)
+        .name_prefix(name)
+        .after_start(move || {
+            let vm = current
+                .get()
+                .and_then(Weak::upgrade)
+                .expect("pool thread started without a machine");
+            let n = started.fetch_add(1, Ordering::Relaxed);
+            scheduler::set_id(vm.stats.scheduler_id(dirty, n));
+            if let Some(dirty) = dirty {
+                scheduler::set_current(dirty);
+            }
+            Machine::set_current(vm);
+        })
+        .before_stop(Machine::clear_current)
+        .build()
+        .expect("failed to start new Runtime")
+}
+
+impl Instance {
+    pub fn machine(&self) -> &RcMachine {
+        &self.machine
+    }
+
+    /// Starts the VM
+    ///
+    /// This method will block the calling thread until the VM halts, returning the exit status.
+    /// The instance gets shut down once it does.
+    pub fn start(mut self) -> i32 {
+        let halted = self.halted.take().expect("only start takes the receiver");
+
+        self.start_main_process(self.config.init_args());
+
+        // Wait until the VM gets halted, or interrupted.
+        let runtime = &self.runtimes.as_ref().unwrap().runtime;
+        let (status, flush) = runtime.block_on(async {
+            let ctrl_c = Box::pin(async {
+                let mut ctrl_c = tokio_net::signal::ctrl_c().unwrap();
+                ctrl_c.next().await;
+            });
+            match future::select(halted, ctrl_c).await {
+                Either::Left((Ok(halt), _)) => halt,
+                _ => (EXIT_INTERRUPTED, true),
+            }
+        });
+
+        drop(self);
+
+        if flush {
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().flush();
+        }
+        status
+    }
+}
+
+impl std::ops::Deref for Instance {
+    type Target = Machine;
+
+    fn deref(&self) -> &Machine {
+        &self.machine
+    }
+}
+
+impl Drop for Instance {
+    /// Shuts down the thread pools. Processes that are still running get dropped.
+    fn drop(&mut self) {
+        if let Some(runtimes) = self.runtimes.take() {
+            runtimes.process_pool.shutdown_now();
+            runtimes.dirty_cpu.shutdown_now();
+            runtimes.runtime.shutdown_now();
+        }
+    }
+}
+
 /// Executes a single process, terminating in the event of an error.
+/// System processes aren't supposed to ever exit, so we take the whole VM down when one does.
+fn system_process_terminated(vm: &Machine, process: &RcProcess, reason: Term) {
+    let slogan = format!(
+        "System process {} terminated: {}",
+        Term::pid(process.pid),
+        reason
+    );
+    vm.crash_dump(&slogan);
+    vm.halt(1, true);
+}
+
 pub async fn run_with_error_handling(mut process: RcProcess) {
     // We are using AssertUnwindSafe here so we can pass a &mut Worker to
     // run()/panic(). This might be risky if values captured are not unwind
     // safe, so take care when capturing new variables.
     //let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
     let vm = Machine::current();
+    scheduler::acquire(&vm, &process).await;
     loop {
-        match instruction::run(&*vm, &mut process).await {
+        let reductions = process.context().reductions;
+        let (collections, reclaimed) = {
+            let gc = &process.local_data().gc;
+            (gc.collections, gc.reclaimed)
+        };
+
+        let res = statistics::Timed::new(&vm, instruction::run(&*vm, &mut process)).await;
+
+        let gc = &process.local_data().gc;
+        vm.stats.slice(
+            process.context().reductions - reductions,
+            gc.collections - collections,
+            (gc.reclaimed - reclaimed) / process::gc::WORD_SIZE,
+        );
+
+        match res {
+            // halt/2 got called, leave the process be while the VM shuts down
+            Err(message) if message.reason == Reason::HALT => break,
             Err(message) => {
                 if message.reason != Reason::TRAP {
                     // just a regular error
@@ -301,6 +694,18 @@ pub async fn run_with_error_handling(mut process: RcProcess) {
                         context.ip = new_pc;
                         // yield
                     } else {
+                        // the system can't keep going without init
+                        if process.is_main() {
+                            let slogan =
+                                format!("Kernel pid terminated (init) ({})", message.value);
+                            vm.crash_dump(&slogan);
+                            process.exit(&vm, message);
+                            vm.halt(1, true);
+                            break
+                        }
+                        if process.is_system() {
+                            system_process_terminated(&vm, &process, message.value);
+                        }
                         process.exit(&vm, message);
                         // println!("pid={} action=exited", process.pid);
                         break // crashed
@@ -311,21 +716,27 @@ pub async fn run_with_error_handling(mut process: RcProcess) {
                     // yield
                 }
             }
-            Ok(process::State::Yield) => (), // yield
+            Ok(process::State::Yield) => {
+                // give up our slot so that waiting processes get a turn
+                vm.run_queues.release();
+                scheduler::acquire(&vm, &process).await;
+            }
             Ok(process::State::Done) => {
+                if process.is_system() {
+                    system_process_terminated(&vm, &process, atom!(NORMAL));
+                }
                 process.exit(&vm, Exception::with_value(Reason::EXC_EXIT, atom!(NORMAL)));
 
                 // Terminate once the main process has finished execution.
                 if process.is_main() {
-                    vm.terminate();
+                    vm.halt(0, true);
                 }
 
                 break
             }, // exited OK
-            // TODO: wait is an await on a oneshot
-            // TODO: waittimeout is an select on a oneshot or a delay
         }
     }
+    vm.run_queues.release();
     // }));
 
     /*
@@ -344,3 +755,77 @@ pub async fn run_with_error_handling(mut process: RcProcess) {
     };
     }*/
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_independent_instances() {
+        let a = Machine::new();
+        let b = Machine::new();
+        let module: *const module::Module = std::ptr::null();
+        let name = Atom::from("init");
+
+        let process = process::allocate(&a, 0, 0, module).unwrap();
+        a.process_registry.lock().register(name, process.clone());
+
+        // each instance keeps its own processes and names
+        let other = process::allocate(&b, 0, 0, module).unwrap();
+        assert_eq!(process.pid, other.pid);
+        assert!(b.process_registry.lock().whereis(name).is_none());
+
+        // dropping the instance releases the machine
+        let machine = Arc::downgrade(a.machine());
+        drop(a);
+        assert!(machine.upgrade().is_none());
+        assert!(b.process_table.lock().get(other.pid).is_some());
+    }
+
+    #[test]
+    fn test_machine_builder() {
+        let vm = MachineBuilder::new()
+            .schedulers(2)
+            .dirty_io_schedulers(3)
+            .process_limit(1)
+            .min_heap_size(1000)
+            .root("/opt/otp")
+            .code_path("ebin")
+            .args(vec!["-noshell".to_string()])
+            .build();
+        assert_eq!(vm.config.schedulers, 2);
+        assert_eq!(vm.config.dirty_io_schedulers, 3);
+
+        let module: *const module::Module = std::ptr::null();
+        let process = process::allocate(&vm, 0, 0, module).unwrap();
+        assert_eq!(
+            process.local_data().gc.min_heap_size,
+            1000 * process::gc::WORD_SIZE
+        );
+        assert_eq!(
+            process::allocate(&vm, 0, 0, module).err().map(|err| err.reason),
+            Some(Reason::EXC_SYSTEM_LIMIT)
+        );
+
+        let args = vm.config.init_args();
+        assert_eq!(&args[..3], &["--", "-root", "/opt/otp"]);
+        assert!(args.ends_with(&["-pa".to_string(), "ebin".to_string(), "-noshell".to_string()]));
+    }
+
+    #[test]
+    fn test_halt_signals_the_instance() {
+        let mut vm = Machine::new();
+        vm.halt(3, false);
+        // the VM is already going down, a second halt doesn't change the status
+        vm.halt(4, true);
+        let mut halted = vm.halted.take().unwrap();
+        assert_eq!(halted.try_recv(), Ok(Some((3, false))));
+    }
+
+    #[test]
+    fn test_atom_limit_only_grows() {
+        let _vm = Machine::new();
+        let _other = MachineBuilder::new().atom_limit(10).build();
+        assert!(crate::atom::limit() >= crate::atom::DEFAULT_ATOM_LIMIT);
+    }
+}
@@ -342,11 +342,17 @@ pub fn instruction(tokens: TokenStream) -> TokenStream {
         ) -> impl std::future::Future<Output = Result<process::State, Exception>> + Captures<'a> + Captures<'b> + 'c {
             async move {  // workaround for https://github.com/rust-lang/rust/issues/56238
             let context = process.context_mut();
-            context.reds = 2000; // self.config.reductions;
+            context.reds = process::CONTEXT_REDS;
 
             // process the incoming signal queue
             process.process_incoming()?;
 
+            // stay off the run queue while suspended
+            scheduler::wait_suspended(vm, process).await?;
+
+            // finish running a BIF that yielded on the last run
+            op_resume_bif!(vm, context, process);
+
             // a fake constant nil
             let NIL = Term::nil();
             let mut ins;